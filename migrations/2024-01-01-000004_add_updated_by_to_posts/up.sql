@@ -0,0 +1,3 @@
+ALTER TABLE posts ADD COLUMN updated_by UUID REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_posts_updated_by ON posts(updated_by);
@@ -0,0 +1,23 @@
+CREATE TABLE audit_log (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    entity_type VARCHAR NOT NULL,
+    entity_id UUID NOT NULL,
+    action VARCHAR NOT NULL,
+    -- No foreign key: the trail must outlive the users it mentions.
+    actor_id UUID,
+    at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    diff JSONB
+);
+
+CREATE INDEX idx_audit_log_entity_id ON audit_log(entity_id);
+CREATE INDEX idx_audit_log_at ON audit_log(at);
+
+CREATE FUNCTION audit_log_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_append_only
+    BEFORE UPDATE OR DELETE ON audit_log
+    FOR EACH ROW EXECUTE PROCEDURE audit_log_append_only();
@@ -0,0 +1,8 @@
+CREATE TABLE favorites (
+    fk_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    fk_post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (fk_user_id, fk_post_id)
+);
+
+CREATE INDEX idx_favorites_post_id ON favorites(fk_post_id);
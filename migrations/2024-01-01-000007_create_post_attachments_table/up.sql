@@ -0,0 +1,10 @@
+CREATE TABLE post_attachments (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    fk_post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    content_type VARCHAR NOT NULL,
+    size_bytes BIGINT NOT NULL CHECK (size_bytes > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_post_attachments_post_id ON post_attachments(fk_post_id);
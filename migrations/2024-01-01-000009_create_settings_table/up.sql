@@ -0,0 +1,5 @@
+CREATE TABLE settings (
+    key VARCHAR PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,2 @@
+ALTER TABLE posts ADD COLUMN featured BOOLEAN NOT NULL DEFAULT FALSE;
+CREATE INDEX idx_posts_featured ON posts(created_at) WHERE featured;
@@ -0,0 +1 @@
+ALTER TABLE posts ADD COLUMN view_count BIGINT NOT NULL DEFAULT 0;
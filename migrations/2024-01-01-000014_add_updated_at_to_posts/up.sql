@@ -0,0 +1,2 @@
+ALTER TABLE posts ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+UPDATE posts SET updated_at = created_at;
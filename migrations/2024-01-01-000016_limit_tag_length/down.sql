@@ -0,0 +1 @@
+ALTER TABLE posts_tags DROP CONSTRAINT posts_tags_tag_length;
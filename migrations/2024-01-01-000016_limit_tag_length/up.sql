@@ -0,0 +1,3 @@
+-- Keep in step with MAX_TAG_LENGTH in src/models.rs.
+ALTER TABLE posts_tags
+    ADD CONSTRAINT posts_tags_tag_length CHECK (char_length(tag) <= 50);
@@ -0,0 +1,796 @@
+//! End-to-end tests that drive the full Rocket app through a local client.
+//! Each test gets its own single-connection pool inside a rolled-back
+//! transaction (see `db::test::pool`), so tests never see each other's rows
+//! and leave nothing behind. They are skipped when `TEST_DATABASE_URL` is
+//! unset.
+
+use rocket::http::{ContentType, Header, Status};
+use rocket::local::blocking::{Client, LocalResponse};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::config::{Config, DEFAULT_API_BASE_PATH, DEFAULT_MAX_JSON_BODY_BYTES};
+use crate::db::{self, DbPool};
+use crate::models::{IdVersion, NewPostWithTags, NewUser, Post, User};
+use crate::repository::{PostRepository, UserRepository};
+
+const ADMIN_TOKEN: &str = "test-admin-token";
+const SITE_URL: &str = "https://blog.example";
+
+fn client() -> Option<Client> {
+    let pool = db::test::pool()?;
+    let config = Config {
+        admin_token: Some(ADMIN_TOKEN.to_string()),
+        site_url: Some(SITE_URL.to_string()),
+        ..Config::default()
+    };
+    Some(Client::tracked(crate::rocket(config, pool)).expect("valid rocket instance"))
+}
+
+/// `path` under the default API base path.
+fn url(path: &str) -> String {
+    format!("{DEFAULT_API_BASE_PATH}{path}")
+}
+
+fn body(response: LocalResponse<'_>) -> Value {
+    response.into_json().expect("JSON response body")
+}
+
+/// A unique marker so searches only match the current test's rows.
+fn marker() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+/// Inserts a user and one post tagged `tags` directly through the
+/// repositories. The connection goes back to the pool before returning, so
+/// requests made afterwards can use it.
+fn insert_user_and_post(client: &Client, marker: &str, tags: &[&str]) -> (User, Post) {
+    let pool = client.rocket().state::<DbPool>().expect("managed pool");
+    let mut conn = pool.get().expect("test connection");
+
+    let user = UserRepository::create(
+        &mut conn,
+        NewUser {
+            username: format!("author_{marker}"),
+            first_name: "Test".to_string(),
+            last_name: "Author".to_string(),
+        },
+        IdVersion::default(),
+    )
+    .unwrap();
+    let post = PostRepository::create_with_tags(
+        &mut conn,
+        NewPostWithTags {
+            title: format!("{marker} fixture"),
+            body: "fixture body".to_string(),
+            created_by: user.id,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        },
+        IdVersion::default(),
+        None,
+    )
+    .unwrap();
+
+    (user, post)
+}
+
+#[test]
+fn create_user_returns_the_new_user() {
+    let Some(client) = client() else {
+        return;
+    };
+    let username = format!("new_{}", marker());
+
+    let response = client
+        .post(url("/users"))
+        .header(ContentType::JSON)
+        .body(
+            json!({
+                "username": username,
+                "first_name": "Ada",
+                "last_name": "Lovelace"
+            })
+            .to_string(),
+        )
+        .dispatch();
+
+    assert_eq!(response.status(), Status::Ok);
+    let body = body(response);
+    assert_eq!(body["success"], true);
+    assert_eq!(body["data"]["username"], username.as_str());
+    assert!(Uuid::parse_str(body["data"]["id"].as_str().unwrap()).is_ok());
+}
+
+#[test]
+fn create_post_normalizes_tags() {
+    let Some(client) = client() else {
+        return;
+    };
+    let marker = marker();
+    let (user, _) = insert_user_and_post(&client, &marker, &[]);
+
+    let response = client
+        .post(url("/posts"))
+        .header(ContentType::JSON)
+        .body(
+            json!({
+                "title": format!("  {marker} new  "),
+                "body": "Hello",
+                "created_by": user.id,
+                "tags": ["Rust", " web ", "rust"]
+            })
+            .to_string(),
+        )
+        .dispatch();
+
+    assert_eq!(response.status(), Status::Ok);
+    let created = body(response);
+    assert_eq!(created["success"], true);
+    assert_eq!(created["data"]["title"], format!("{marker} new"));
+    assert!(created.get("warnings").is_none());
+
+    let id = created["data"]["id"].as_str().unwrap();
+    let detail = body(client.get(url(&format!("/posts/{id}"))).dispatch());
+    assert_eq!(detail["data"]["tags"], json!(["rust", "web"]));
+    assert_eq!(detail["data"]["created_by"]["user_id"], json!(user.id));
+}
+
+#[test]
+fn oversized_bodies_are_refused_with_413() {
+    let Some(client) = client() else {
+        return;
+    };
+    let marker = marker();
+    let (user, _) = insert_user_and_post(&client, &marker, &[]);
+    let huge = "x".repeat(DEFAULT_MAX_JSON_BODY_BYTES as usize);
+
+    let response = client
+        .post(url("/posts"))
+        .header(ContentType::JSON)
+        .body(
+            json!({
+                "title": format!("{marker} huge"),
+                "body": huge,
+                "created_by": user.id,
+            })
+            .to_string(),
+        )
+        .dispatch();
+    assert_eq!(response.status(), Status::PayloadTooLarge);
+    let error = body(response);
+    assert_eq!(error["success"], false);
+    assert_eq!(
+        error["error"],
+        format!("Request body is larger than {DEFAULT_MAX_JSON_BODY_BYTES} bytes")
+    );
+    let count = body(
+        client
+            .get(url(&format!("/posts/count?search={marker}")))
+            .dispatch(),
+    );
+    assert_eq!(count["data"]["count"], 1);
+
+    let response = client
+        .post(url("/users"))
+        .header(ContentType::JSON)
+        .body(json!({ "username": huge, "first_name": "Huge", "last_name": "Body" }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::PayloadTooLarge);
+}
+
+#[test]
+fn list_posts_returns_matching_records_with_meta() {
+    let Some(client) = client() else {
+        return;
+    };
+    let marker = marker();
+    let (user, post) = insert_user_and_post(&client, &marker, &["fixture"]);
+
+    let response = client
+        .get(url(&format!("/posts?search={marker}&limit=5")))
+        .dispatch();
+
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response.headers().get_one("Link").is_some());
+    let list = body(response);
+    assert_eq!(list["success"], true);
+    let records = list["data"]["records"].as_array().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["id"], json!(post.id));
+    assert_eq!(records[0]["created_by"]["username"], user.username.as_str());
+    assert_eq!(records[0]["tags"], json!(["fixture"]));
+    assert_eq!(list["data"]["meta"]["total_docs"], 1);
+    assert_eq!(list["data"]["meta"]["per_page"], 5);
+}
+
+#[test]
+fn posts_export_streams_one_post_per_line() {
+    let Some(client) = client() else {
+        return;
+    };
+    let marker = marker();
+    let (user, post) = insert_user_and_post(&client, &marker, &["fixture"]);
+
+    let anonymous = client.get(url("/posts/export")).dispatch();
+    assert_eq!(anonymous.status(), Status::Unauthorized);
+
+    let response = client
+        .get(url("/posts/export"))
+        .header(Header::new("X-Admin-Token", ADMIN_TOKEN))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.content_type(),
+        Some(ContentType::new("application", "x-ndjson"))
+    );
+    let text = response.into_string().unwrap();
+    assert!(text.ends_with('\n'));
+
+    let lines: Vec<Value> = text
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("one JSON object per line"))
+        .collect();
+    let ours: Vec<&Value> = lines
+        .iter()
+        .filter(|line| line["id"] == json!(post.id))
+        .collect();
+    assert_eq!(ours.len(), 1);
+    assert_eq!(ours[0]["title"], format!("{marker} fixture"));
+    assert_eq!(ours[0]["created_by"]["username"], user.username.as_str());
+    assert_eq!(ours[0]["tags"], json!(["fixture"]));
+}
+
+#[test]
+fn admin_routes_only_accept_the_exact_token() {
+    let Some(client) = client() else {
+        return;
+    };
+    let audit = |token: Option<&str>| {
+        let mut request = client.get(url("/audit"));
+        if let Some(token) = token {
+            request = request.header(Header::new("X-Admin-Token", token.to_string()));
+        }
+        request.dispatch().status()
+    };
+
+    assert_eq!(audit(Some(ADMIN_TOKEN)), Status::Ok);
+    assert_eq!(audit(None), Status::Unauthorized);
+    assert_eq!(audit(Some("wrong")), Status::Forbidden);
+    assert_eq!(audit(Some(&ADMIN_TOKEN[1..])), Status::Forbidden);
+    assert_eq!(audit(Some(&format!("{ADMIN_TOKEN}x"))), Status::Forbidden);
+}
+
+#[test]
+fn batch_posts_reports_missing_ids() {
+    let Some(client) = client() else {
+        return;
+    };
+    let (_, post) = insert_user_and_post(&client, &marker(), &[]);
+    let missing = Uuid::new_v4();
+
+    let response = client
+        .post(url("/posts/batch"))
+        .header(ContentType::JSON)
+        .body(json!({ "ids": [missing, post.id] }).to_string())
+        .dispatch();
+
+    assert_eq!(response.status(), Status::Ok);
+    let batch = body(response);
+    let records = batch["data"]["records"].as_array().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["id"], json!(post.id));
+    assert_eq!(batch["data"]["not_found"], json!([missing]));
+}
+
+#[test]
+fn create_user_conflicts_on_username_differing_only_in_case() {
+    let Some(client) = client() else {
+        return;
+    };
+    let marker = marker();
+    let create = |username: String| {
+        client
+            .post(url("/users"))
+            .header(ContentType::JSON)
+            .body(json!({ "username": username, "first_name": "B", "last_name": "B" }).to_string())
+            .dispatch()
+    };
+
+    assert_eq!(create(format!("Bob_{marker}")).status(), Status::Ok);
+    let duplicate = create(format!("bob_{marker}"));
+    assert_eq!(duplicate.status(), Status::Conflict);
+    assert_eq!(body(duplicate)["error"], "Username is already taken");
+}
+
+#[test]
+fn import_dry_run_reports_items_without_storing_them() {
+    let Some(client) = client() else {
+        return;
+    };
+    let (user_id, post_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let document = json!({
+        "users": [{ "id": user_id, "username": format!("dry_{}", marker()), "first_name": "D", "last_name": "R" }],
+        "posts": [
+            { "id": post_id, "title": "Dry run", "body": "body", "created_by": user_id },
+            { "id": Uuid::new_v4(), "title": "  ", "body": "body", "created_by": user_id },
+        ],
+    });
+    let import = |query: &str, document: &Value| {
+        client
+            .post(url(&format!("/admin/import{query}")))
+            .header(ContentType::JSON)
+            .header(Header::new("X-Admin-Token", ADMIN_TOKEN))
+            .body(document.to_string())
+            .dispatch()
+    };
+
+    let response = import("?dry_run=true", &document);
+    assert_eq!(response.status(), Status::Ok);
+    let preview = body(response)["data"].clone();
+    assert_eq!(preview["valid"], false);
+    assert_eq!(preview["users"][0]["action"], "create");
+    assert_eq!(preview["posts"][0]["id"], json!(post_id));
+    assert!(preview["posts"][0].get("errors").is_none());
+    assert_eq!(
+        preview["posts"][1]["errors"]["title"][0],
+        "must not be empty"
+    );
+    assert!(preview["summary"].is_null());
+
+    // The real run rejects the same row.
+    let response = import("", &document);
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    assert!(body(response)["errors"]["posts[1].title"].is_array());
+
+    let mut valid = document.clone();
+    valid["posts"].as_array_mut().unwrap().truncate(1);
+    let preview = body(import("?dry_run=true", &valid))["data"].clone();
+    assert_eq!(preview["valid"], true);
+    assert_eq!(preview["summary"]["posts"]["inserted"], 1);
+
+    let missing = client.get(url(&format!("/posts/{post_id}"))).dispatch();
+    assert_eq!(missing.status(), Status::NotFound);
+}
+
+#[test]
+fn list_posts_flags_pages_past_the_end() {
+    let Some(client) = client() else {
+        return;
+    };
+    let marker = marker();
+    insert_user_and_post(&client, &marker, &[]);
+
+    let page = |n: i64| {
+        let response = client
+            .get(url(&format!("/posts?search={marker}&page={n}")))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        body(response)["data"].clone()
+    };
+
+    let first = page(1);
+    assert_eq!(first["meta"]["out_of_range"], false);
+    assert_eq!(first["records"].as_array().unwrap().len(), 1);
+
+    let beyond = page(999);
+    assert_eq!(beyond["meta"]["out_of_range"], true);
+    assert_eq!(beyond["meta"]["current_page"], 999);
+    assert_eq!(beyond["meta"]["total_pages"], 1);
+    assert!(beyond["records"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn paginated_lists_reject_pages_below_one() {
+    let Some(client) = client() else {
+        return;
+    };
+    let marker = marker();
+    let (user, _) = insert_user_and_post(&client, &marker, &[]);
+
+    for path in [
+        "/posts?page=0".to_string(),
+        "/posts?page=-1".to_string(),
+        format!("/users/{}/favorites?page=0", user.id),
+    ] {
+        let response = client.get(url(&path)).dispatch();
+        assert_eq!(response.status(), Status::UnprocessableEntity, "{path}");
+        assert!(body(response)["errors"]["page"].is_array(), "{path}");
+    }
+
+    let response = client
+        .get(url("/audit?page=0"))
+        .header(Header::new("X-Admin-Token", ADMIN_TOKEN))
+        .dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+}
+
+#[test]
+fn list_posts_flags_a_clamped_limit() {
+    let Some(client) = client() else {
+        return;
+    };
+    let marker = marker();
+    insert_user_and_post(&client, &marker, &[]);
+
+    let list = |query: &str| {
+        let response = client
+            .get(url(&format!("/posts?search={marker}{query}")))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let clamped = response
+            .headers()
+            .get_one("X-Limit-Clamped")
+            .map(str::to_string);
+        (clamped, body(response)["data"]["meta"].clone())
+    };
+
+    let (clamped, meta) = list("&limit=5000");
+    assert_eq!(clamped.as_deref(), Some("true"));
+    assert_eq!(meta["per_page"], 100);
+    assert_eq!(meta["requested_limit"], 5000);
+
+    let (clamped, meta) = list("&limit=0");
+    assert_eq!(clamped.as_deref(), Some("true"));
+    assert_eq!(meta["per_page"], 1);
+
+    for query in ["", "&limit=100"] {
+        let (clamped, meta) = list(query);
+        assert_eq!(clamped, None);
+        assert!(meta.get("requested_limit").is_none());
+    }
+}
+
+#[test]
+fn list_posts_uses_camel_case_keys_when_asked() {
+    let Some(client) = client() else {
+        return;
+    };
+    let marker = marker();
+    insert_user_and_post(&client, &marker, &["rust"]);
+    let list = |accept: &'static str| {
+        let response = client
+            .get(url(&format!("/posts?search={marker}")))
+            .header(Header::new("Accept", accept))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        body(response)["data"].clone()
+    };
+
+    let camel = list("application/json; case=camel");
+    assert_eq!(camel["meta"]["currentPage"], 1);
+    assert_eq!(camel["meta"]["outOfRange"], false);
+    let record = &camel["records"][0];
+    assert!(record["createdBy"]["userId"].is_string());
+    assert!(record["viewCount"].is_number());
+    assert!(record.get("created_at").is_none());
+
+    // Validation errors stay keyed by the request's own field names.
+    let invalid = client
+        .post(url("/users"))
+        .header(ContentType::JSON)
+        .header(Header::new("Accept", "application/json; case=camel"))
+        .body(json!({ "username": "", "first_name": "", "last_name": "" }).to_string())
+        .dispatch();
+    assert_eq!(invalid.status(), Status::UnprocessableEntity);
+    let errors = &body(invalid)["errors"];
+    assert!(errors.get("first_name").is_some(), "{errors}");
+    assert!(errors.get("firstName").is_none());
+
+    let spec = client
+        .get("/openapi.json")
+        .header(Header::new("Accept", "application/json; case=camel"))
+        .dispatch();
+    assert!(
+        body(spec)["components"]["schemas"]["PostWithUserAndTags"]["properties"]
+            .get("created_at")
+            .is_some()
+    );
+
+    let snake = list("application/json");
+    assert_eq!(snake["meta"]["current_page"], 1);
+    // RFC 3339 in UTC with microseconds, e.g. 2024-01-01T00:00:00.000000Z.
+    let created_at = snake["records"][0]["created_at"].as_str().unwrap();
+    assert_eq!(created_at.len(), 27, "{created_at}");
+    assert!(created_at.ends_with('Z'));
+}
+
+#[test]
+fn exhausted_pool_returns_503_with_retry_after() {
+    let Some(pool) = db::test::pool_with_timeout(std::time::Duration::from_millis(200)) else {
+        return;
+    };
+    let client =
+        Client::tracked(crate::rocket(Config::default(), pool)).expect("valid rocket instance");
+    let pool = client.rocket().state::<DbPool>().expect("managed pool");
+    let _held = pool.get().expect("test connection");
+
+    let response = client.get(url("/posts")).dispatch();
+    assert_eq!(response.status(), Status::ServiceUnavailable);
+    assert_eq!(response.headers().get_one("Retry-After"), Some("1"));
+    assert_eq!(body(response)["success"], false);
+}
+
+#[test]
+fn per_page_is_an_alias_for_limit() {
+    let Some(client) = client() else {
+        return;
+    };
+    let marker = marker();
+    for n in 0..3 {
+        insert_user_and_post(&client, &format!("{marker}{n}"), &[]);
+    }
+    let per_page = |query: &str| {
+        let response = client
+            .get(url(&format!("/posts?search={marker}&{query}")))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        body(response)["data"]["meta"]["per_page"].clone()
+    };
+
+    assert_eq!(per_page("per_page=2"), 2);
+    assert_eq!(per_page("per_page=2&limit=1"), 1);
+}
+
+fn graphql(client: &Client, query: &str) -> Value {
+    let response = client
+        .post(url("/graphql"))
+        .header(ContentType::JSON)
+        .body(json!({ "query": query }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    body(response)
+}
+
+#[test]
+fn graphql_lists_posts_with_nested_author_and_tags() {
+    let Some(client) = client() else {
+        return;
+    };
+    let marker = marker();
+    let (user, post) = insert_user_and_post(&client, &marker, &["rust", "graphql"]);
+
+    let response = graphql(
+        &client,
+        &format!(
+            r#"{{ posts(search: "{marker}", limit: 5) {{
+                records {{ id title tags createdBy {{ userId username }} }}
+                meta {{ totalDocs perPage }}
+            }} }}"#
+        ),
+    );
+
+    assert!(response.get("errors").is_none(), "{response}");
+    let posts = &response["data"]["posts"];
+    assert_eq!(posts["meta"], json!({ "totalDocs": 1, "perPage": 5 }));
+    assert_eq!(
+        posts["records"][0],
+        json!({
+            "id": post.id,
+            "title": post.title,
+            "tags": ["graphql", "rust"],
+            "createdBy": { "userId": user.id, "username": user.username }
+        })
+    );
+}
+
+#[test]
+fn graphql_fetches_a_post_and_a_user_by_id() {
+    let Some(client) = client() else {
+        return;
+    };
+    let (user, post) = insert_user_and_post(&client, &marker(), &[]);
+
+    let response = graphql(
+        &client,
+        &format!(
+            r#"{{
+                post(id: "{}") {{ title createdBy {{ username }} }}
+                user(id: "{}") {{ username firstName }}
+                missing: post(id: "{}") {{ id }}
+            }}"#,
+            post.id,
+            user.id,
+            Uuid::new_v4()
+        ),
+    );
+
+    assert!(response.get("errors").is_none(), "{response}");
+    assert_eq!(
+        response["data"],
+        json!({
+            "post": { "title": post.title, "createdBy": { "username": user.username } },
+            "user": { "username": user.username, "firstName": "Test" },
+            "missing": null
+        })
+    );
+}
+
+#[test]
+fn openapi_spec_documents_every_api_route() {
+    let Some(client) = client() else {
+        return;
+    };
+
+    let response = client.get("/openapi.json").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let spec = body(response);
+    assert_eq!(spec["servers"][0]["url"], DEFAULT_API_BASE_PATH);
+
+    for route in client.rocket().routes() {
+        if route.uri.base() != DEFAULT_API_BASE_PATH {
+            continue;
+        }
+        // `/posts/<id>` is documented as `/posts/{id}`.
+        let path = route.uri.path()[DEFAULT_API_BASE_PATH.len()..]
+            .replace('<', "{")
+            .replace('>', "}");
+        // Only built with the `seed` feature, and not meant for clients.
+        if path.starts_with("/dev/") {
+            continue;
+        }
+        let method = route.method.as_str().to_lowercase();
+        assert!(
+            spec["paths"][&path][&method].is_object(),
+            "{method} {path} is missing from the spec"
+        );
+    }
+
+    let docs = client.get("/docs").dispatch();
+    assert_eq!(docs.status(), Status::Ok);
+    assert_eq!(docs.content_type(), Some(ContentType::HTML));
+    assert!(docs.into_string().unwrap().contains("/openapi.json"));
+}
+
+#[test]
+fn tags_index_links_to_each_tags_posts_and_feed() {
+    let Some(client) = client() else {
+        return;
+    };
+    let marker = marker();
+    let tag = format!("c++ {marker}");
+    let (_, post) = insert_user_and_post(&client, &marker, &[&tag]);
+
+    let find = |tags: &Value| {
+        tags.as_array()
+            .unwrap()
+            .iter()
+            .find(|entry| entry["tag"] == tag.as_str())
+            .cloned()
+            .expect("tag in the index")
+    };
+
+    let plain = find(&body(client.get(url("/tags")).dispatch())["data"]);
+    assert_eq!(plain, json!({ "tag": tag, "count": 1 }));
+
+    let linked = find(&body(client.get(url("/tags?links=true")).dispatch())["data"]);
+    let query = format!("tag=c%2B%2B%20{marker}");
+    assert_eq!(linked["posts_url"], url(&format!("/posts?{query}")));
+    assert_eq!(linked["feed_url"], url(&format!("/feed.xml?{query}")));
+
+    // The links resolve to the tag's posts.
+    let posts = body(
+        client
+            .get(linked["posts_url"].as_str().unwrap().to_string())
+            .dispatch(),
+    );
+    assert_eq!(posts["data"]["records"][0]["id"], json!(post.id));
+    assert_eq!(posts["data"]["meta"]["total_docs"], 1);
+    let feed = client
+        .get(linked["feed_url"].as_str().unwrap().to_string())
+        .dispatch();
+    assert_eq!(feed.status(), Status::Ok);
+    assert!(feed.into_string().unwrap().contains(&post.title));
+}
+
+#[test]
+fn tag_posts_match_the_tag_in_any_case_and_page_like_the_post_list() {
+    let Some(client) = client() else {
+        return;
+    };
+    let marker = marker();
+    let tag = format!("landing-{marker}");
+    let (_, post) = insert_user_and_post(&client, &marker, &[&tag]);
+
+    let response = client
+        .get(url(&format!("/tags/{}/posts?limit=5", tag.to_uppercase())))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let page = body(response);
+    let records = page["data"]["records"].as_array().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["id"], json!(post.id));
+    assert_eq!(page["data"]["meta"]["total_docs"], 1);
+    assert_eq!(page["data"]["meta"]["per_page"], 5);
+
+    let unused = body(
+        client
+            .get(url(&format!("/tags/unused-{marker}/posts")))
+            .dispatch(),
+    );
+    assert_eq!(unused["success"], true);
+    assert_eq!(unused["data"]["records"], json!([]));
+    assert_eq!(unused["data"]["meta"]["total_docs"], 0);
+
+    let blank = client.get(url("/tags/%20/posts")).dispatch();
+    assert_eq!(blank.status(), Status::UnprocessableEntity);
+}
+
+#[test]
+fn feed_links_ignore_the_host_header_and_resolve_under_the_base_path() {
+    let Some(client) = client() else {
+        return;
+    };
+    let marker = marker();
+    let tag = format!("feed-{marker}");
+    let (_, post) = insert_user_and_post(&client, &marker, &[&tag]);
+
+    let feed = client
+        .get(url(&format!("/feed.xml?tag={tag}")))
+        .header(Header::new("Host", "attacker.example"))
+        .dispatch()
+        .into_string()
+        .unwrap();
+    assert!(!feed.contains("attacker.example"));
+    let path = url(&format!("/posts/{}/{marker}-fixture", post.id));
+    assert!(
+        feed.contains(&format!("<link>{SITE_URL}{path}</link>")),
+        "{feed}"
+    );
+
+    let linked = body(client.get(path).dispatch());
+    assert_eq!(linked["data"]["id"], json!(post.id));
+}
+
+#[test]
+fn the_feed_is_not_served_without_a_site_url() {
+    let Some(pool) = db::test::pool() else {
+        return;
+    };
+    let client =
+        Client::tracked(crate::rocket(Config::default(), pool)).expect("valid rocket instance");
+    let marker = marker();
+    insert_user_and_post(&client, &marker, &[&marker]);
+
+    let feed = client.get(url("/feed.xml")).dispatch();
+    assert_eq!(feed.status(), Status::NotFound);
+
+    let tags = body(client.get(url("/tags?links=true")).dispatch());
+    let entry = tags["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entry| entry["tag"] == marker.as_str())
+        .expect("tag in the index");
+    assert!(entry["posts_url"].is_string());
+    assert!(entry.get("feed_url").is_none());
+}
+
+#[test]
+fn username_availability_ignores_case_and_rejects_blank_names() {
+    let Some(client) = client() else {
+        return;
+    };
+    let marker = marker();
+    insert_user_and_post(&client, &marker, &[]);
+
+    let available = |username: &str| {
+        let response = client
+            .get(url(&format!("/users/available?username={username}")))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        body(response)["data"]["available"].clone()
+    };
+    assert_eq!(available(&format!("AUTHOR_{marker}")), json!(false));
+    assert_eq!(available(&format!("free_{marker}")), json!(true));
+
+    for query in ["?username=%20%20", ""] {
+        let response = client
+            .get(url(&format!("/users/available{query}")))
+            .dispatch();
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        assert_eq!(
+            body(response)["errors"]["username"],
+            json!(["must not be empty"])
+        );
+    }
+}
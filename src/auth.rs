@@ -0,0 +1,80 @@
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::State;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::models::User;
+use crate::repository::UserRepository;
+
+/// Request guard for admin-only routes. Succeeds when the `X-Admin-Token`
+/// header matches the configured `ADMIN_TOKEN`; admin routes are refused
+/// outright when `ADMIN_TOKEN` is unset.
+pub struct Admin;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let expected = req
+            .rocket()
+            .state::<Config>()
+            .and_then(|config| config.admin_token.as_deref());
+        let Some(expected) = expected else {
+            return Outcome::Error((Status::Forbidden, ()));
+        };
+
+        match req.headers().get_one("X-Admin-Token") {
+            Some(token) if tokens_match(token, expected) => Outcome::Success(Admin),
+            Some(_) => Outcome::Error((Status::Forbidden, ())),
+            None => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
+
+/// Compares the digests of both tokens in constant time, so neither the
+/// position of the first wrong byte nor the token's length shows in how long
+/// the check takes.
+fn tokens_match(given: &str, expected: &str) -> bool {
+    Sha256::digest(given)
+        .ct_eq(&Sha256::digest(expected))
+        .into()
+}
+
+/// Request guard for the acting user. The `X-User-Id` header must name an
+/// existing user; the API trusts it, so it is meant to be set by an
+/// authenticating gateway in front of the service.
+pub struct AuthUser(pub User);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AuthUser {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(id) = req
+            .headers()
+            .get_one("X-User-Id")
+            .and_then(|value| Uuid::parse_str(value).ok())
+        else {
+            return Outcome::Error((Status::Unauthorized, ()));
+        };
+
+        let pool = match req.guard::<&State<DbPool>>().await {
+            Outcome::Success(pool) => pool,
+            _ => return Outcome::Error((Status::InternalServerError, ())),
+        };
+        let Ok(mut conn) = pool.get() else {
+            return Outcome::Error((Status::ServiceUnavailable, ()));
+        };
+
+        match UserRepository::get(&mut conn, id) {
+            Ok(Some(user)) => Outcome::Success(AuthUser(user)),
+            Ok(None) => Outcome::Error((Status::Unauthorized, ())),
+            Err(_) => Outcome::Error((Status::InternalServerError, ())),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::models::CreatedBy;
+
+pub const DEFAULT_AUTHOR_CACHE_CAPACITY: usize = 1000;
+pub const DEFAULT_AUTHOR_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Managed cache of post authors keyed by user id, so busy post listings
+/// only load the authors they haven't seen recently. Entries expire after
+/// `ttl`, and once `capacity` authors are cached the least recently used one
+/// is evicted. A capacity of 0 disables caching.
+pub struct AuthorCache {
+    capacity: usize,
+    ttl: Duration,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<Uuid, Entry>,
+    /// Bumped on every hit or insert; an entry's `last_used` is its value at
+    /// that moment, so the smallest one is the least recently used.
+    clock: u64,
+}
+
+struct Entry {
+    author: CreatedBy,
+    cached_at: Instant,
+    last_used: u64,
+}
+
+impl AuthorCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        AuthorCache {
+            capacity,
+            ttl,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// Sized by `AUTHOR_CACHE_CAPACITY` and `AUTHOR_CACHE_TTL_SECS`.
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(config.author_cache_capacity, config.author_cache_ttl)
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<CreatedBy> {
+        self.get_at(id, Instant::now())
+    }
+
+    pub fn insert(&self, author: CreatedBy) {
+        self.insert_at(author, Instant::now())
+    }
+
+    fn get_at(&self, id: Uuid, now: Instant) -> Option<CreatedBy> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let fresh = inner
+            .entries
+            .get(&id)
+            .map(|entry| now.duration_since(entry.cached_at) < self.ttl)?;
+        if !fresh {
+            inner.entries.remove(&id);
+            return None;
+        }
+
+        inner.clock += 1;
+        let clock = inner.clock;
+        let entry = inner.entries.get_mut(&id)?;
+        entry.last_used = clock;
+        Some(entry.author.clone())
+    }
+
+    fn insert_at(&self, author: CreatedBy, now: Instant) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+
+        if !inner.entries.contains_key(&author.user_id) && inner.entries.len() >= self.capacity {
+            // Expired entries go first; otherwise the least recently used.
+            let ttl = self.ttl;
+            inner
+                .entries
+                .retain(|_, entry| now.duration_since(entry.cached_at) < ttl);
+            if inner.entries.len() >= self.capacity {
+                let oldest = inner
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.last_used)
+                    .map(|(id, _)| *id);
+                if let Some(oldest) = oldest {
+                    inner.entries.remove(&oldest);
+                }
+            }
+        }
+
+        inner.clock += 1;
+        let last_used = inner.clock;
+        inner.entries.insert(
+            author.user_id,
+            Entry {
+                author,
+                cached_at: now,
+                last_used,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn author(name: &str) -> CreatedBy {
+        CreatedBy {
+            user_id: Uuid::new_v4(),
+            username: name.to_string(),
+            first_name: name.to_string(),
+            last_name: None,
+        }
+    }
+
+    #[test]
+    fn entries_expire_after_ttl() {
+        let cache = AuthorCache::new(10, Duration::from_secs(60));
+        let ada = author("ada");
+        let start = Instant::now();
+        cache.insert_at(ada.clone(), start);
+
+        let hit = cache.get_at(ada.user_id, start + Duration::from_secs(59));
+        assert_eq!(hit.map(|a| a.username), Some("ada".to_string()));
+        assert!(cache
+            .get_at(ada.user_id, start + Duration::from_secs(60))
+            .is_none());
+    }
+
+    #[test]
+    fn least_recently_used_entry_is_evicted_when_full() {
+        let cache = AuthorCache::new(2, Duration::from_secs(60));
+        let (ada, bob, cy) = (author("ada"), author("bob"), author("cy"));
+        let now = Instant::now();
+        cache.insert_at(ada.clone(), now);
+        cache.insert_at(bob.clone(), now);
+
+        // Touch ada so bob becomes the least recently used.
+        assert!(cache.get_at(ada.user_id, now).is_some());
+        cache.insert_at(cy.clone(), now);
+
+        assert!(cache.get_at(ada.user_id, now).is_some());
+        assert!(cache.get_at(bob.user_id, now).is_none());
+        assert!(cache.get_at(cy.user_id, now).is_some());
+    }
+
+    #[test]
+    fn zero_capacity_disables_caching() {
+        let cache = AuthorCache::new(0, Duration::from_secs(60));
+        let ada = author("ada");
+        cache.insert(ada.clone());
+        assert!(cache.get(ada.user_id).is_none());
+    }
+}
@@ -0,0 +1,344 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::author_cache::{DEFAULT_AUTHOR_CACHE_CAPACITY, DEFAULT_AUTHOR_CACHE_TTL};
+use crate::models::{IdVersion, DEFAULT_MAX_TAGS_PER_POST, DEFAULT_MIN_SEARCH_LENGTH};
+use crate::rate_limit::DEFAULT_WRITES_PER_MINUTE;
+use crate::repository::IsolationLevel;
+use crate::settings::{validate_posts_per_page, DEFAULT_POSTS_PER_PAGE, MAX_POSTS_PER_PAGE};
+
+pub const DEFAULT_API_BASE_PATH: &str = "/api";
+pub const DEFAULT_POOL_MAX_SIZE: u32 = 10;
+pub const DEFAULT_POOL_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_COMPRESSION_MIN_BYTES: usize = 1024;
+pub const DEFAULT_MAX_JSON_BODY_BYTES: u64 = 256 * 1024;
+
+/// Everything the server reads from the environment, loaded once at startup
+/// and placed in managed state. Unset or unparseable values fall back to the
+/// defaults.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// `DATABASE_URL`; `None` means the local default database.
+    pub database_url: Option<String>,
+    /// `DB_POOL_MAX_SIZE`
+    pub pool_max_size: u32,
+    /// `DB_POOL_TIMEOUT_SECS`
+    pub pool_timeout: Duration,
+    /// `DB_POOL_MIN_IDLE`, connections kept open while idle. `None` keeps
+    /// `pool_max_size` open, like r2d2's default.
+    pub pool_min_idle: Option<u32>,
+    /// `API_BASE_PATH`, normalized into a mount point.
+    pub api_base_path: String,
+    /// `DEFAULT_PAGE_SIZE` (formerly `POSTS_PER_PAGE`), the list `limit`
+    /// when the `posts_per_page` setting is unset.
+    pub default_page_size: i64,
+    /// `MAX_TAGS_PER_POST`
+    pub max_tags_per_post: usize,
+    /// `MIN_SEARCH_LENGTH`
+    pub min_search_length: usize,
+    /// `DEBUG_QUERY_TIMING`
+    pub debug_query_timing: bool,
+    /// `CORS_ALLOWED_ORIGINS`, comma separated. `*` allows any origin.
+    pub cors_origins: Vec<String>,
+    /// `ADMIN_TOKEN`; admin routes are refused when unset.
+    pub admin_token: Option<String>,
+    /// `AUTHOR_CACHE_CAPACITY`
+    pub author_cache_capacity: usize,
+    /// `AUTHOR_CACHE_TTL_SECS`
+    pub author_cache_ttl: Duration,
+    /// `WRITE_RATE_LIMIT_PER_MINUTE`
+    pub writes_per_minute: u32,
+    /// `TRUSTED_IP_HEADER`, e.g. `X-Real-IP`, set by a proxy in front to the
+    /// client's address. `None` rate limits by the connection's address,
+    /// since any other header could be sent by the client itself.
+    pub trusted_ip_header: Option<String>,
+    /// `COMPRESSION`; on unless set to `0` or `false`, e.g. when a proxy in
+    /// front already compresses.
+    pub compression: bool,
+    /// `COMPRESSION_MIN_BYTES`: smaller bodies are sent as they are.
+    pub compression_min_bytes: usize,
+    /// `SITE_URL`, the public address feed links point to, without a
+    /// trailing slash. RSS links must be absolute, so `None` leaves the feed
+    /// unmounted.
+    pub site_url: Option<String>,
+    /// `ID_VERSION`, `v4` (the default) or `v7`, for new users and posts.
+    pub id_version: IdVersion,
+    /// `POST_TRANSACTION_ISOLATION`, e.g. `serializable`, for the
+    /// transactions that create posts. `None` keeps the database default.
+    pub post_isolation_level: Option<IsolationLevel>,
+    /// `MAX_JSON_BODY_BYTES`: JSON bodies above this are refused with 413
+    /// before they are parsed.
+    pub max_json_body_bytes: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::from_vars(|_| None)
+    }
+}
+
+impl Config {
+    /// Like `from_vars` over the process environment, but refuses a page
+    /// size outside 1 to `MAX_POSTS_PER_PAGE` instead of falling back, so a
+    /// typo shows up at startup rather than as an unexpectedly dense feed.
+    pub fn from_env() -> Result<Self, String> {
+        let lookup = |name: &str| std::env::var(name).ok();
+        check_vars(&lookup)?;
+        Ok(Self::from_vars(lookup))
+    }
+
+    /// Builds the config from `lookup`, which returns a variable's value by
+    /// name.
+    pub fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        Config {
+            database_url: lookup("DATABASE_URL"),
+            pool_max_size: parse(&lookup, "DB_POOL_MAX_SIZE")
+                .filter(|&size| size > 0)
+                .unwrap_or(DEFAULT_POOL_MAX_SIZE),
+            pool_timeout: parse(&lookup, "DB_POOL_TIMEOUT_SECS")
+                .filter(|&secs| secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_POOL_TIMEOUT),
+            pool_min_idle: parse(&lookup, "DB_POOL_MIN_IDLE"),
+            api_base_path: normalize_base_path(
+                &lookup("API_BASE_PATH").unwrap_or_else(|| DEFAULT_API_BASE_PATH.to_string()),
+            ),
+            default_page_size: page_size_var(&lookup)
+                .and_then(|(_, value)| value.trim().parse().ok())
+                .filter(|n| (1..=MAX_POSTS_PER_PAGE).contains(n))
+                .unwrap_or(DEFAULT_POSTS_PER_PAGE),
+            max_tags_per_post: parse(&lookup, "MAX_TAGS_PER_POST")
+                .unwrap_or(DEFAULT_MAX_TAGS_PER_POST),
+            min_search_length: parse(&lookup, "MIN_SEARCH_LENGTH")
+                .unwrap_or(DEFAULT_MIN_SEARCH_LENGTH),
+            debug_query_timing: flag(&lookup, "DEBUG_QUERY_TIMING").unwrap_or(false),
+            cors_origins: lookup("CORS_ALLOWED_ORIGINS")
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(|origin| origin.trim().trim_end_matches('/').to_string())
+                        .filter(|origin| !origin.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            admin_token: lookup("ADMIN_TOKEN").filter(|token| !token.is_empty()),
+            author_cache_capacity: parse(&lookup, "AUTHOR_CACHE_CAPACITY")
+                .unwrap_or(DEFAULT_AUTHOR_CACHE_CAPACITY),
+            author_cache_ttl: parse(&lookup, "AUTHOR_CACHE_TTL_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_AUTHOR_CACHE_TTL),
+            writes_per_minute: parse(&lookup, "WRITE_RATE_LIMIT_PER_MINUTE")
+                .unwrap_or(DEFAULT_WRITES_PER_MINUTE),
+            trusted_ip_header: lookup("TRUSTED_IP_HEADER")
+                .map(|header| header.trim().to_string())
+                .filter(|header| !header.is_empty()),
+            compression: flag(&lookup, "COMPRESSION").unwrap_or(true),
+            compression_min_bytes: parse(&lookup, "COMPRESSION_MIN_BYTES")
+                .unwrap_or(DEFAULT_COMPRESSION_MIN_BYTES),
+            site_url: lookup("SITE_URL")
+                .map(|url| url.trim().trim_end_matches('/').to_string())
+                .filter(|url| !url.is_empty()),
+            id_version: parse(&lookup, "ID_VERSION").unwrap_or_default(),
+            post_isolation_level: parse(&lookup, "POST_TRANSACTION_ISOLATION"),
+            max_json_body_bytes: parse(&lookup, "MAX_JSON_BODY_BYTES")
+                .filter(|&bytes| bytes > 0)
+                .unwrap_or(DEFAULT_MAX_JSON_BODY_BYTES),
+        }
+    }
+
+    /// Whether a browser page served from `origin` may call the API.
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.cors_origins
+            .iter()
+            .any(|allowed| allowed == "*" || allowed == origin)
+    }
+
+    /// `path` (starting with `/`) under `api_base_path`, for links in
+    /// responses.
+    pub fn api_url(&self, path: &str) -> String {
+        format!("{}{path}", self.api_base_path.trim_end_matches('/'))
+    }
+}
+
+/// The page size variable that is set, preferring `DEFAULT_PAGE_SIZE` over
+/// the older `POSTS_PER_PAGE`, with its value.
+fn page_size_var(lookup: &impl Fn(&str) -> Option<String>) -> Option<(&'static str, String)> {
+    ["DEFAULT_PAGE_SIZE", "POSTS_PER_PAGE"]
+        .into_iter()
+        .find_map(|name| lookup(name).map(|value| (name, value)))
+}
+
+/// Errors for variables that are set to values `from_vars` would ignore.
+fn check_vars(lookup: &impl Fn(&str) -> Option<String>) -> Result<(), String> {
+    if let Some((name, value)) = page_size_var(lookup) {
+        validate_posts_per_page(value.trim()).map_err(|e| format!("{name} {e}, got {value:?}"))?;
+    }
+    Ok(())
+}
+
+fn parse<T: FromStr>(lookup: &impl Fn(&str) -> Option<String>, name: &str) -> Option<T> {
+    lookup(name).and_then(|value| value.trim().parse().ok())
+}
+
+/// `1`/`true` or `0`/`false` (any case); `None` when unset or anything else.
+fn flag(lookup: &impl Fn(&str) -> Option<String>, name: &str) -> Option<bool> {
+    let value = lookup(name)?;
+    let value = value.trim();
+    if value == "1" || value.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if value == "0" || value.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Normalizes `API_BASE_PATH` into a mount point: a leading slash and no
+/// trailing one, with an empty value or `/` meaning the root.
+fn normalize_base_path(path: &str) -> String {
+    let trimmed = path.trim().trim_matches('/');
+    format!("/{trimmed}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    fn with_vars(vars: &[(&str, &str)]) -> Config {
+        Config::from_vars(lookup(vars))
+    }
+
+    #[test]
+    fn base_path_is_normalized() {
+        assert_eq!(normalize_base_path("/api"), "/api");
+        assert_eq!(normalize_base_path("api/"), "/api");
+        assert_eq!(normalize_base_path(" /blog/v1/ "), "/blog/v1");
+        assert_eq!(normalize_base_path(""), "/");
+        assert_eq!(normalize_base_path("/"), "/");
+    }
+
+    #[test]
+    fn api_urls_join_the_base_path() {
+        assert_eq!(Config::default().api_url("/posts"), "/api/posts");
+        let root = with_vars(&[("API_BASE_PATH", "/")]);
+        assert_eq!(root.api_url("/posts"), "/posts");
+    }
+
+    #[test]
+    fn unset_variables_use_the_defaults() {
+        let config = Config::default();
+        assert_eq!(config.database_url, None);
+        assert_eq!(config.pool_max_size, DEFAULT_POOL_MAX_SIZE);
+        assert_eq!(config.pool_min_idle, None);
+        assert_eq!(config.api_base_path, "/api");
+        assert_eq!(config.default_page_size, DEFAULT_POSTS_PER_PAGE);
+        assert_eq!(config.max_tags_per_post, DEFAULT_MAX_TAGS_PER_POST);
+        assert!(!config.debug_query_timing);
+        assert!(config.cors_origins.is_empty());
+        assert_eq!(config.admin_token, None);
+        assert_eq!(config.writes_per_minute, DEFAULT_WRITES_PER_MINUTE);
+        assert_eq!(config.trusted_ip_header, None);
+        assert!(config.compression);
+        assert_eq!(config.compression_min_bytes, DEFAULT_COMPRESSION_MIN_BYTES);
+        assert_eq!(config.max_json_body_bytes, DEFAULT_MAX_JSON_BODY_BYTES);
+    }
+
+    #[test]
+    fn variables_override_the_defaults() {
+        let config = with_vars(&[
+            ("DATABASE_URL", "postgres://db/blog"),
+            ("DB_POOL_MAX_SIZE", "4"),
+            ("DB_POOL_TIMEOUT_SECS", "5"),
+            ("DB_POOL_MIN_IDLE", "2"),
+            ("API_BASE_PATH", "blog/v1/"),
+            ("DEFAULT_PAGE_SIZE", "25"),
+            ("POSTS_PER_PAGE", "30"),
+            ("DEBUG_QUERY_TIMING", "TRUE"),
+            (
+                "CORS_ALLOWED_ORIGINS",
+                "https://a.example, https://b.example/,",
+            ),
+            ("ADMIN_TOKEN", "secret"),
+            ("AUTHOR_CACHE_TTL_SECS", "0"),
+            ("TRUSTED_IP_HEADER", " X-Real-IP "),
+            ("COMPRESSION", "false"),
+            ("SITE_URL", "https://blog.example/"),
+            ("ID_VERSION", "V7"),
+            ("POST_TRANSACTION_ISOLATION", "repeatable_read"),
+        ]);
+        assert_eq!(config.database_url.as_deref(), Some("postgres://db/blog"));
+        assert_eq!(config.pool_max_size, 4);
+        assert_eq!(config.pool_timeout, Duration::from_secs(5));
+        assert_eq!(config.pool_min_idle, Some(2));
+        assert_eq!(config.api_base_path, "/blog/v1");
+        assert_eq!(config.default_page_size, 25);
+        assert!(config.debug_query_timing);
+        assert_eq!(
+            config.cors_origins,
+            vec!["https://a.example", "https://b.example"]
+        );
+        assert_eq!(config.admin_token.as_deref(), Some("secret"));
+        assert_eq!(config.author_cache_ttl, Duration::ZERO);
+        assert_eq!(config.trusted_ip_header.as_deref(), Some("X-Real-IP"));
+        assert!(!config.compression);
+        assert_eq!(config.site_url.as_deref(), Some("https://blog.example"));
+        assert_eq!(config.id_version, IdVersion::V7);
+        assert_eq!(
+            config.post_isolation_level,
+            Some(IsolationLevel::RepeatableRead)
+        );
+    }
+
+    #[test]
+    fn invalid_values_fall_back_to_the_defaults() {
+        let config = with_vars(&[
+            ("DB_POOL_MAX_SIZE", "0"),
+            ("POSTS_PER_PAGE", "1000"),
+            ("MAX_TAGS_PER_POST", "many"),
+            ("ADMIN_TOKEN", ""),
+            ("ID_VERSION", "v6"),
+            ("POST_TRANSACTION_ISOLATION", "snapshot"),
+        ]);
+        assert_eq!(config.pool_max_size, DEFAULT_POOL_MAX_SIZE);
+        assert_eq!(config.default_page_size, DEFAULT_POSTS_PER_PAGE);
+        assert_eq!(config.max_tags_per_post, DEFAULT_MAX_TAGS_PER_POST);
+        assert_eq!(config.admin_token, None);
+        assert_eq!(config.id_version, IdVersion::V4);
+        assert_eq!(config.post_isolation_level, None);
+    }
+
+    #[test]
+    fn page_size_falls_back_to_the_old_name() {
+        let config = with_vars(&[("POSTS_PER_PAGE", "40")]);
+        assert_eq!(config.default_page_size, 40);
+    }
+
+    #[test]
+    fn out_of_range_page_size_is_refused_at_startup() {
+        let check = |vars: &[(&str, &str)]| check_vars(&lookup(vars));
+        assert!(check(&[]).is_ok());
+        assert!(check(&[("DEFAULT_PAGE_SIZE", "100")]).is_ok());
+        let error = check(&[("DEFAULT_PAGE_SIZE", "0")]).unwrap_err();
+        assert!(error.starts_with("DEFAULT_PAGE_SIZE must be an integer between 1 and 100"));
+        assert!(check(&[("POSTS_PER_PAGE", "ten")]).is_err());
+    }
+
+    #[test]
+    fn origins_match_exactly_unless_wildcarded() {
+        let config = with_vars(&[("CORS_ALLOWED_ORIGINS", "https://a.example")]);
+        assert!(config.allows_origin("https://a.example"));
+        assert!(!config.allows_origin("https://b.example"));
+
+        let any = with_vars(&[("CORS_ALLOWED_ORIGINS", "*")]);
+        assert!(any.allows_origin("https://b.example"));
+    }
+}
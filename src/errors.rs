@@ -0,0 +1,208 @@
+use rocket::data::Limits;
+use rocket::http::{Header, Status};
+use rocket::request::Request;
+use rocket::response::{self, Responder};
+use rocket::serde::json::Json;
+
+use crate::models::{add_field_error, FieldErrors};
+use crate::rate_limit::RetryAfter;
+
+/// Error for handlers that need a status other than 200. Renders the same
+/// `{ "success": false, "error": ... }` envelope as the rest of the API,
+/// except validation failures, which carry an `errors` map keyed by field.
+#[derive(Debug)]
+pub enum ApiError {
+    Validation(FieldErrors),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    /// No database connection came free within `DB_POOL_TIMEOUT_SECS`.
+    /// Sent as 503 with `Retry-After` so clients back off.
+    Unavailable(String),
+    Internal(String),
+}
+
+impl ApiError {
+    /// Validation error for a single field.
+    pub fn field(field: &str, message: impl Into<String>) -> Self {
+        let mut errors = FieldErrors::new();
+        add_field_error(&mut errors, field, message);
+        ApiError::Validation(errors)
+    }
+}
+
+/// r2d2 only fails a checkout when it times out waiting for a connection.
+impl From<diesel::r2d2::PoolError> for ApiError {
+    fn from(_: diesel::r2d2::PoolError) -> Self {
+        ApiError::Unavailable(UNAVAILABLE_MESSAGE.to_string())
+    }
+}
+
+/// Seconds a client should wait before retrying a 503.
+const UNAVAILABLE_RETRY_AFTER: u64 = 1;
+const UNAVAILABLE_MESSAGE: &str = "Server is busy, please retry shortly";
+
+fn error_body(message: String) -> serde_json::Value {
+    serde_json::json!({
+        "success": false,
+        "error": message
+    })
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let (status, body) = match self {
+            ApiError::Validation(errors) => (
+                Status::UnprocessableEntity,
+                serde_json::json!({
+                    "success": false,
+                    "errors": errors
+                }),
+            ),
+            ApiError::Unauthorized(message) => (Status::Unauthorized, error_body(message)),
+            ApiError::Forbidden(message) => (Status::Forbidden, error_body(message)),
+            ApiError::NotFound(message) => (Status::NotFound, error_body(message)),
+            ApiError::Conflict(message) => (Status::Conflict, error_body(message)),
+            ApiError::Unavailable(message) => return unavailable(message).respond_to(req),
+            ApiError::Internal(message) => (Status::InternalServerError, error_body(message)),
+        };
+
+        (status, Json(body)).respond_to(req)
+    }
+}
+
+#[catch(404)]
+pub fn not_found() -> Json<serde_json::Value> {
+    Json(error_body("not found".to_string()))
+}
+
+/// Also reached when a handler or guard panics, so clients get JSON rather
+/// than Rocket's HTML page.
+#[catch(500)]
+pub fn internal_error() -> Json<serde_json::Value> {
+    Json(error_body("internal server error".to_string()))
+}
+
+/// Any other error status without a catcher of its own, such as a body that
+/// fails to parse, keeps the envelope with the status's reason as the error.
+#[catch(default)]
+pub fn default_catcher(status: Status, _req: &Request<'_>) -> (Status, Json<serde_json::Value>) {
+    let reason = status.reason_lossy().to_lowercase();
+    (status, Json(error_body(reason)))
+}
+
+/// JSON bodies over `MAX_JSON_BODY_BYTES`, which Rocket stops reading at the
+/// limit.
+#[catch(413)]
+pub fn payload_too_large(req: &Request<'_>) -> Json<serde_json::Value> {
+    let limit = req.limits().get("json").unwrap_or(Limits::JSON);
+    Json(error_body(format!(
+        "Request body is larger than {} bytes",
+        limit.as_u64()
+    )))
+}
+
+#[catch(415)]
+pub fn unsupported_media_type() -> Json<serde_json::Value> {
+    Json(error_body(
+        "Content-Type must be application/json".to_string(),
+    ))
+}
+
+#[derive(Responder)]
+#[response(status = 429)]
+pub struct TooManyRequests {
+    body: Json<serde_json::Value>,
+    retry_after: Header<'static>,
+}
+
+/// Rejections from `WriteLimit`, telling the client when to retry.
+#[catch(429)]
+pub fn too_many_requests(req: &Request<'_>) -> TooManyRequests {
+    let seconds = req.local_cache(|| RetryAfter(None)).0.unwrap_or(60);
+    TooManyRequests {
+        body: Json(error_body(format!(
+            "Too many requests, retry in {seconds} seconds"
+        ))),
+        retry_after: Header::new("Retry-After", seconds.to_string()),
+    }
+}
+
+#[derive(Responder)]
+#[response(status = 503)]
+pub struct ServiceUnavailable {
+    body: Json<serde_json::Value>,
+    retry_after: Header<'static>,
+}
+
+fn unavailable(message: String) -> ServiceUnavailable {
+    ServiceUnavailable {
+        body: Json(error_body(message)),
+        retry_after: Header::new("Retry-After", UNAVAILABLE_RETRY_AFTER.to_string()),
+    }
+}
+
+/// Guards that can't get a database connection, such as `AuthUser`.
+#[catch(503)]
+pub fn service_unavailable() -> ServiceUnavailable {
+    unavailable(UNAVAILABLE_MESSAGE.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::local::blocking::Client;
+
+    #[get("/panic")]
+    fn panics() -> &'static str {
+        panic!("handler bug")
+    }
+
+    #[post("/echo", format = "json", data = "<body>")]
+    fn echo(body: Json<serde_json::Value>) -> Json<serde_json::Value> {
+        body
+    }
+
+    fn client() -> Client {
+        let rocket = rocket::build()
+            .mount("/", routes![panics, echo])
+            .register("/", catchers![not_found, internal_error, default_catcher]);
+        Client::tracked(rocket).unwrap()
+    }
+
+    fn error_of(response: rocket::local::blocking::LocalResponse<'_>) -> serde_json::Value {
+        assert_eq!(
+            response.content_type(),
+            Some(rocket::http::ContentType::JSON)
+        );
+        let body: serde_json::Value = response.into_json().unwrap();
+        assert_eq!(body["success"], false);
+        body["error"].clone()
+    }
+
+    #[test]
+    fn panics_and_unknown_routes_keep_the_json_envelope() {
+        let client = client();
+
+        let response = client.get("/panic").dispatch();
+        assert_eq!(response.status(), Status::InternalServerError);
+        assert_eq!(error_of(response), "internal server error");
+
+        let response = client.get("/nowhere").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        assert_eq!(error_of(response), "not found");
+    }
+
+    #[test]
+    fn other_errors_use_the_status_reason() {
+        let client = client();
+        let response = client
+            .post("/echo")
+            .header(rocket::http::ContentType::JSON)
+            .body("{not json")
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+        assert_eq!(error_of(response), "bad request");
+    }
+}
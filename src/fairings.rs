@@ -0,0 +1,472 @@
+use std::io::{Cursor, Write};
+
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::Compression as Level;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Method, Status};
+use rocket::{Request, Response};
+
+use crate::config::Config;
+use crate::guards::RequestId;
+use crate::responders::camel_case_keys;
+
+/// Logs one line per request, tagged with its `RequestId`, and echoes the id
+/// back in the `X-Request-Id` response header.
+pub struct RequestLog;
+
+#[rocket::async_trait]
+impl Fairing for RequestLog {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request Log",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _: &mut rocket::Data<'_>) {
+        // Resolve the id up front so it is fixed before any handler runs.
+        RequestId::of(req);
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        let id = RequestId::of(req);
+        println!(
+            "[{}] {} {} -> {}",
+            id,
+            req.method(),
+            req.uri(),
+            res.status()
+        );
+        res.set_raw_header(RequestId::HEADER, id.to_string());
+    }
+}
+
+/// Adds CORS headers for requests from the origins in
+/// `Config::cors_origins`, and answers their preflight `OPTIONS` requests.
+/// Other origins get no CORS headers, so browsers block them.
+pub struct Cors;
+
+impl Cors {
+    /// Request headers a browser may send from another origin.
+    const ALLOW_HEADERS: &'static str =
+        "Content-Type, X-User-Id, X-Admin-Token, X-Request-Id, If-None-Match, If-Modified-Since";
+    /// Response headers scripts may read, beyond the CORS-safelisted ones.
+    const EXPOSE_HEADERS: &'static str =
+        "Link, X-Request-Id, X-Limit-Clamped, ETag, Last-Modified, Retry-After";
+}
+
+#[rocket::async_trait]
+impl Fairing for Cors {
+    fn info(&self) -> Info {
+        Info {
+            name: "CORS",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        let Some(config) = req.rocket().state::<Config>() else {
+            return;
+        };
+        let Some(origin) = req.headers().get_one("Origin") else {
+            return;
+        };
+        if !config.allows_origin(origin) {
+            return;
+        }
+
+        res.set_raw_header("Access-Control-Allow-Origin", origin.to_string());
+        res.adjoin_raw_header("Vary", "Origin");
+        res.set_raw_header("Access-Control-Expose-Headers", Self::EXPOSE_HEADERS);
+
+        // No route handles OPTIONS, so a preflight would otherwise be a 404.
+        if req.method() == Method::Options && res.status() == Status::NotFound {
+            res.set_status(Status::NoContent);
+            res.set_sized_body(0, Cursor::new(""));
+            res.remove_header("Content-Type");
+            res.set_raw_header(
+                "Access-Control-Allow-Methods",
+                "GET, POST, PUT, PATCH, DELETE",
+            );
+            res.set_raw_header("Access-Control-Allow-Headers", Self::ALLOW_HEADERS);
+            res.set_raw_header("Access-Control-Max-Age", "86400");
+        }
+    }
+}
+
+/// Rewrites JSON response keys to camelCase for clients that ask for it with
+/// `Accept: application/json; case=camel`. Everyone else gets the usual
+/// snake_case. GraphQL responses and the OpenAPI spec are left alone, since
+/// their keys are the client's aliases and schema names rather than ours.
+/// Attach before `Compression`, which needs the final body.
+pub struct KeyCase;
+
+impl KeyCase {
+    fn wants_camel_case(req: &Request<'_>) -> bool {
+        req.accept().is_some_and(|accept| {
+            accept.media_types().any(|media_type| {
+                media_type.is_json()
+                    && media_type
+                        .param("case")
+                        .is_some_and(|case| case.eq_ignore_ascii_case("camel"))
+            })
+        })
+    }
+
+    fn keeps_its_keys(req: &Request<'_>) -> bool {
+        let path = req.uri().path();
+        path == "/openapi.json"
+            || req
+                .rocket()
+                .state::<Config>()
+                .is_some_and(|config| path == config.api_url("/graphql").as_str())
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for KeyCase {
+    fn info(&self) -> Info {
+        Info {
+            name: "Key Case",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        if res.content_type() != Some(ContentType::JSON) {
+            return;
+        }
+        res.adjoin_raw_header("Vary", "Accept");
+        // Streams keep their keys; rewriting them would mean buffering.
+        if !Self::wants_camel_case(req)
+            || Self::keeps_its_keys(req)
+            || res.body().preset_size().is_none()
+        {
+            return;
+        }
+
+        let Ok(body) = res.body_mut().to_bytes().await else {
+            return;
+        };
+        let rewritten = serde_json::from_slice(&body)
+            .ok()
+            .and_then(|value| serde_json::to_vec(&camel_case_keys(value)).ok())
+            .unwrap_or(body);
+        res.set_sized_body(rewritten.len(), Cursor::new(rewritten));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+
+    fn compress(self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Level::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Encoding::Deflate => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Level::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// Picks the encoding from an `Accept-Encoding` header: whichever of gzip
+/// and deflate has the higher quality, gzip on a tie. `q=0` refuses an
+/// encoding, and `*` stands for any encoding not listed.
+fn preferred_encoding(accept: &str) -> Option<Encoding> {
+    let mut gzip = None;
+    let mut deflate = None;
+    let mut any = None;
+    for item in accept.split(',') {
+        let mut parts = item.split(';');
+        let coding = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let quality = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        match coding.as_str() {
+            "gzip" | "x-gzip" => gzip = Some(quality),
+            "deflate" => deflate = Some(quality),
+            "*" => any = Some(quality),
+            _ => {}
+        }
+    }
+
+    let gzip = gzip.or(any).unwrap_or(0.0);
+    let deflate = deflate.or(any).unwrap_or(0.0);
+    if gzip > 0.0 && gzip >= deflate {
+        Some(Encoding::Gzip)
+    } else if deflate > 0.0 {
+        Some(Encoding::Deflate)
+    } else {
+        None
+    }
+}
+
+/// Compresses JSON responses with gzip or deflate when the client accepts
+/// it. Controlled by `Config::compression`, and only bodies of known size of
+/// at least `Config::compression_min_bytes` are compressed, so streams pass
+/// through untouched.
+pub struct Compression;
+
+#[rocket::async_trait]
+impl Fairing for Compression {
+    fn info(&self) -> Info {
+        Info {
+            name: "Compression",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        let Some(config) = req.rocket().state::<Config>() else {
+            return;
+        };
+        if !config.compression
+            || res.content_type() != Some(ContentType::JSON)
+            || res.headers().contains("Content-Encoding")
+        {
+            return;
+        }
+        res.adjoin_raw_header("Vary", "Accept-Encoding");
+
+        let Some(encoding) = req
+            .headers()
+            .get_one("Accept-Encoding")
+            .and_then(preferred_encoding)
+        else {
+            return;
+        };
+        match res.body().preset_size() {
+            Some(size) if size >= config.compression_min_bytes => {}
+            _ => return,
+        }
+
+        let Ok(body) = res.body_mut().to_bytes().await else {
+            return;
+        };
+        match encoding.compress(&body) {
+            Ok(compressed) => {
+                res.set_sized_body(compressed.len(), Cursor::new(compressed));
+                res.set_raw_header("Content-Encoding", encoding.name());
+            }
+            Err(_) => res.set_sized_body(body.len(), Cursor::new(body)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use rocket::local::blocking::Client;
+    use std::io::Read;
+
+    #[get("/probe")]
+    fn probe(id: RequestId) -> String {
+        id.to_string()
+    }
+
+    fn client() -> Client {
+        let rocket = rocket::build()
+            .attach(RequestLog)
+            .mount("/", routes![probe]);
+        Client::tracked(rocket).unwrap()
+    }
+
+    #[test]
+    fn client_request_id_is_echoed() {
+        let client = client();
+        let response = client
+            .get("/probe")
+            .header(rocket::http::Header::new(RequestId::HEADER, "abc-123"))
+            .dispatch();
+
+        assert_eq!(
+            response.headers().get_one(RequestId::HEADER),
+            Some("abc-123")
+        );
+        assert_eq!(response.into_string().as_deref(), Some("abc-123"));
+    }
+
+    #[test]
+    fn missing_or_unsafe_request_id_is_replaced() {
+        let client = client();
+        for header in [None, Some("bad id\twith whitespace")] {
+            let mut request = client.get("/probe");
+            if let Some(value) = header {
+                request = request.header(rocket::http::Header::new(RequestId::HEADER, value));
+            }
+            let response = request.dispatch();
+
+            let echoed = response
+                .headers()
+                .get_one(RequestId::HEADER)
+                .unwrap()
+                .to_string();
+            assert!(uuid::Uuid::parse_str(&echoed).is_ok());
+            assert_eq!(response.into_string(), Some(echoed));
+        }
+
+        // Unmatched routes are tagged too.
+        let response = client.get("/missing").dispatch();
+        assert!(response.headers().get_one(RequestId::HEADER).is_some());
+    }
+
+    fn cors_client(origins: &str) -> Client {
+        let origins = origins.to_string();
+        let config =
+            Config::from_vars(|name| (name == "CORS_ALLOWED_ORIGINS").then(|| origins.clone()));
+        let rocket = rocket::build()
+            .manage(config)
+            .attach(Cors)
+            .mount("/", routes![probe]);
+        Client::tracked(rocket).unwrap()
+    }
+
+    #[test]
+    fn allowed_origins_get_cors_headers() {
+        let client = cors_client("https://app.example");
+        let origin = |value: &'static str| rocket::http::Header::new("Origin", value);
+
+        let response = client
+            .get("/probe")
+            .header(origin("https://app.example"))
+            .dispatch();
+        assert_eq!(
+            response.headers().get_one("Access-Control-Allow-Origin"),
+            Some("https://app.example")
+        );
+        assert_eq!(response.headers().get_one("Vary"), Some("Origin"));
+        let exposed = response
+            .headers()
+            .get_one("Access-Control-Expose-Headers")
+            .unwrap();
+        for header in [
+            "Link",
+            "X-Request-Id",
+            "X-Limit-Clamped",
+            "ETag",
+            "Retry-After",
+        ] {
+            assert!(exposed.contains(header), "{header} in {exposed}");
+        }
+
+        let response = client
+            .get("/probe")
+            .header(origin("https://evil.example"))
+            .dispatch();
+        assert_eq!(
+            response.headers().get_one("Access-Control-Allow-Origin"),
+            None
+        );
+    }
+
+    #[test]
+    fn preflight_from_an_allowed_origin_succeeds() {
+        let client = cors_client("*");
+        let response = client
+            .options("/probe")
+            .header(rocket::http::Header::new("Origin", "https://app.example"))
+            .dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+        assert!(response
+            .headers()
+            .get_one("Access-Control-Allow-Methods")
+            .is_some());
+        let allowed = response
+            .headers()
+            .get_one("Access-Control-Allow-Headers")
+            .unwrap();
+        assert!(allowed.contains("If-None-Match"));
+        assert!(allowed.contains("If-Modified-Since"));
+
+        let response = client.options("/probe").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn encoding_follows_accept_encoding_quality() {
+        assert_eq!(
+            preferred_encoding("gzip, deflate, br"),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(
+            preferred_encoding("gzip;q=0.5, deflate"),
+            Some(Encoding::Deflate)
+        );
+        assert_eq!(preferred_encoding("gzip;q=0, *"), Some(Encoding::Deflate));
+        assert_eq!(preferred_encoding("*;q=0.1"), Some(Encoding::Gzip));
+        assert_eq!(preferred_encoding("br, identity"), None);
+        assert_eq!(preferred_encoding("gzip;q=0, deflate;q=0"), None);
+    }
+
+    #[get("/large")]
+    fn large() -> rocket::serde::json::Json<Vec<&'static str>> {
+        rocket::serde::json::Json(vec!["compress me"; 200])
+    }
+
+    #[get("/small")]
+    fn small() -> rocket::serde::json::Json<&'static str> {
+        rocket::serde::json::Json("tiny")
+    }
+
+    fn compression_client(config: Config) -> Client {
+        let rocket = rocket::build()
+            .manage(config)
+            .attach(Compression)
+            .mount("/", routes![large, small]);
+        Client::tracked(rocket).unwrap()
+    }
+
+    #[test]
+    fn large_json_is_gzipped_for_clients_that_accept_it() {
+        let client = compression_client(Config::default());
+        let gzip = || rocket::http::Header::new("Accept-Encoding", "gzip");
+
+        let response = client.get("/large").header(gzip()).dispatch();
+        assert_eq!(response.headers().get_one("Content-Encoding"), Some("gzip"));
+        assert_eq!(response.headers().get_one("Vary"), Some("Accept-Encoding"));
+        let mut json = String::new();
+        GzDecoder::new(&response.into_bytes().unwrap()[..])
+            .read_to_string(&mut json)
+            .unwrap();
+        assert!(json.starts_with("[\"compress me\""));
+
+        let response = client.get("/small").header(gzip()).dispatch();
+        assert_eq!(response.headers().get_one("Content-Encoding"), None);
+        assert_eq!(response.into_string().as_deref(), Some("\"tiny\""));
+
+        let response = client.get("/large").dispatch();
+        assert_eq!(response.headers().get_one("Content-Encoding"), None);
+    }
+
+    #[test]
+    fn compression_can_be_disabled() {
+        let client = compression_client(Config {
+            compression: false,
+            ..Config::default()
+        });
+        let response = client
+            .get("/large")
+            .header(rocket::http::Header::new("Accept-Encoding", "gzip"))
+            .dispatch();
+        assert_eq!(response.headers().get_one("Content-Encoding"), None);
+    }
+}
@@ -0,0 +1,162 @@
+use rocket::http::ContentType;
+use rocket::request::Request;
+use rocket::response::{self, Responder};
+
+use crate::models::{collapse_whitespace, slugify, PostWithUserAndTags};
+
+pub const DEFAULT_FEED_ITEMS: i64 = 20;
+pub const MAX_FEED_ITEMS: i64 = 50;
+/// Characters of the body kept for an item's description.
+const EXCERPT_LENGTH: usize = 280;
+
+/// An RSS 2.0 document, sent as `application/rss+xml`.
+pub struct Rss(pub String);
+
+impl<'r> Responder<'r, 'static> for Rss {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        (ContentType::new("application", "rss+xml"), self.0).respond_to(req)
+    }
+}
+
+/// Builds the feed for `posts`, newest first. `base_url` is the absolute
+/// address the API's routes live under, without a trailing slash; each item
+/// links to `{base_url}/posts/{id}/{slug}`. A feed without
+/// posts is still a valid, empty channel.
+pub fn rss(base_url: &str, tag: Option<&str>, posts: &[PostWithUserAndTags]) -> String {
+    let title = match tag {
+        Some(tag) => format!("Posts tagged {tag}"),
+        None => "Latest posts".to_string(),
+    };
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\">\n<channel>\n");
+    xml.push_str(&element("title", &title));
+    xml.push_str(&element("link", base_url));
+    xml.push_str(&element("description", &title));
+    if let Some(newest) = posts.first() {
+        xml.push_str(&element("lastBuildDate", &newest.created_at.to_rfc2822()));
+    }
+
+    for post in posts {
+        xml.push_str("<item>\n");
+        xml.push_str(&element("title", &post.title));
+        xml.push_str(&element(
+            "link",
+            &format!("{base_url}/posts/{}/{}", post.id, slugify(&post.title)),
+        ));
+        xml.push_str(&format!("<guid isPermaLink=\"false\">{}</guid>\n", post.id));
+        xml.push_str(&element("pubDate", &post.created_at.to_rfc2822()));
+        xml.push_str(&element("description", &excerpt(&post.body)));
+        for tag in &post.tags {
+            xml.push_str(&element("category", tag));
+        }
+        xml.push_str("</item>\n");
+    }
+
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+fn element(name: &str, text: &str) -> String {
+    format!("<{name}>{}</{name}>\n", escape_xml(text))
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The body on one line, cut to `EXCERPT_LENGTH` characters with an
+/// ellipsis when it was longer.
+fn excerpt(body: &str) -> String {
+    let body = collapse_whitespace(body);
+    if body.chars().count() <= EXCERPT_LENGTH {
+        return body;
+    }
+    let cut: String = body.chars().take(EXCERPT_LENGTH).collect();
+    format!("{}…", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use uuid::Uuid;
+
+    fn post(title: &str, body: &str, tags: &[&str]) -> PostWithUserAndTags {
+        let created_at = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        PostWithUserAndTags {
+            id: Uuid::nil(),
+            title: title.to_string(),
+            body: body.to_string(),
+            created_by: None,
+            created_at,
+            updated_at: created_at,
+            featured: false,
+            view_count: 0,
+            comment_count: None,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn items_are_escaped_and_linked_by_slug() {
+        let xml = rss(
+            "https://blog.example",
+            Some("rust"),
+            &[post("Fish & <Chips>", "Line one\n\nline two", &["rust"])],
+        );
+
+        assert!(xml.contains("<title>Posts tagged rust</title>"));
+        assert!(xml.contains("<title>Fish &amp; &lt;Chips&gt;</title>"));
+        assert!(xml.contains(&format!(
+            "<link>https://blog.example/posts/{}/fish-chips</link>",
+            Uuid::nil()
+        )));
+        assert!(xml.contains("<pubDate>Tue, 2 Jan 2024 03:04:05 +0000</pubDate>"));
+        assert!(xml.contains("<description>Line one line two</description>"));
+        assert!(xml.contains("<category>rust</category>"));
+    }
+
+    #[test]
+    fn links_keep_the_base_path() {
+        let xml = rss(
+            "https://blog.example/api",
+            None,
+            &[post("Hello", "body", &[])],
+        );
+        assert!(xml.contains("<link>https://blog.example/api</link>"));
+        assert!(xml.contains(&format!(
+            "<link>https://blog.example/api/posts/{}/hello</link>",
+            Uuid::nil()
+        )));
+    }
+
+    #[test]
+    fn empty_feed_is_a_channel_without_items() {
+        let xml = rss("https://blog.example", None, &[]);
+        assert!(xml.contains("<title>Latest posts</title>"));
+        assert!(!xml.contains("<item>"));
+        assert!(!xml.contains("lastBuildDate"));
+        assert!(xml.ends_with("</channel>\n</rss>\n"));
+    }
+
+    #[test]
+    fn long_bodies_are_cut_to_an_excerpt() {
+        let body = "word ".repeat(100);
+        let cut = excerpt(&body);
+        assert!(cut.ends_with("word…"));
+        assert!(cut.chars().count() <= EXCERPT_LENGTH + 1);
+        assert_eq!(excerpt("short"), "short");
+    }
+}
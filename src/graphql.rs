@@ -0,0 +1,206 @@
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, Error, Object, Result, Schema, SimpleObject,
+};
+use chrono::{DateTime, Utc};
+use rocket::serde::json::Json;
+use rocket::State;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::models::{self, PaginationMeta, PostFilters, PostSort, PostWithUserAndTags};
+use crate::openapi::schemas::{GraphqlRequest, GraphqlResponse};
+use crate::repository::{PostRepository, UserRepository};
+use crate::settings::{Settings, MAX_POSTS_PER_PAGE};
+
+pub type BlogSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+/// Read-only schema over the same repositories as the REST routes.
+pub fn schema(pool: DbPool, config: &Config) -> BlogSchema {
+    Schema::build(Query, EmptyMutation, EmptySubscription)
+        .data(pool)
+        .data(MinSearchLength(config.min_search_length))
+        .finish()
+}
+
+struct MinSearchLength(usize);
+
+/// The `posts_per_page` setting when the request came in, used when a
+/// query leaves out `limit`.
+struct PageSize(i64);
+
+#[utoipa::path(
+    tag = "graphql",
+    request_body = GraphqlRequest,
+    responses(
+        (status = 200, description = "Errors are reported in `errors`, still with 200", body = GraphqlResponse),
+    )
+)]
+#[post("/graphql", data = "<request>")]
+pub async fn graphql(
+    schema: &State<BlogSchema>,
+    settings: &State<Settings>,
+    request: Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    let request = request
+        .into_inner()
+        .data(PageSize(settings.posts_per_page()));
+    Json(schema.execute(request).await)
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    /// Posts newest first, filtered like `GET /posts?search=`.
+    async fn posts(
+        &self,
+        ctx: &Context<'_>,
+        page: Option<i64>,
+        limit: Option<i64>,
+        search: Option<String>,
+    ) -> Result<PostPage> {
+        let page = page.unwrap_or(1);
+        if page < 1 {
+            return Err(Error::new("page must be 1 or more"));
+        }
+        let limit = limit
+            .unwrap_or(ctx.data::<PageSize>()?.0)
+            .clamp(1, MAX_POSTS_PER_PAGE);
+        let filters = PostFilters {
+            search,
+            ..Default::default()
+        };
+        if let Err(errors) = filters.validate(ctx.data::<MinSearchLength>()?.0) {
+            let message = errors
+                .into_iter()
+                .flat_map(|(field, messages)| {
+                    messages
+                        .into_iter()
+                        .map(move |message| format!("{field} {message}"))
+                })
+                .collect::<Vec<_>>()
+                .join("; ");
+            return Err(Error::new(message));
+        }
+
+        let mut conn = ctx.data::<DbPool>()?.get()?;
+        let (posts, meta) = PostRepository::find_with_user_and_tags(
+            &mut conn,
+            page,
+            limit,
+            &PostSort::default(),
+            &filters,
+            None,
+        )
+        .map_err(|_| Error::new("Failed to fetch posts"))?;
+
+        Ok(PostPage {
+            records: posts.into_iter().map(Post::from).collect(),
+            meta: meta.into(),
+        })
+    }
+
+    async fn post(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<Post>> {
+        let mut conn = ctx.data::<DbPool>()?.get()?;
+        let detail =
+            PostRepository::find(&mut conn, id).map_err(|_| Error::new("Failed to fetch post"))?;
+        Ok(detail.map(|detail| detail.post.into()))
+    }
+
+    async fn user(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<User>> {
+        let mut conn = ctx.data::<DbPool>()?.get()?;
+        let user =
+            UserRepository::get(&mut conn, id).map_err(|_| Error::new("Failed to fetch user"))?;
+        Ok(user.map(User::from))
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct PostPage {
+    records: Vec<Post>,
+    meta: PageMeta,
+}
+
+#[derive(SimpleObject)]
+pub struct PageMeta {
+    current_page: i64,
+    per_page: i64,
+    total_pages: i64,
+    total_docs: i64,
+}
+
+impl From<PaginationMeta> for PageMeta {
+    fn from(meta: PaginationMeta) -> Self {
+        PageMeta {
+            current_page: meta.current_page,
+            per_page: meta.per_page,
+            total_pages: meta.total_pages,
+            total_docs: meta.total_docs,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Post {
+    id: Uuid,
+    title: String,
+    body: String,
+    /// `null` for posts whose author no longer exists.
+    created_by: Option<Author>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    featured: bool,
+    view_count: i64,
+    tags: Vec<String>,
+}
+
+impl From<PostWithUserAndTags> for Post {
+    fn from(post: PostWithUserAndTags) -> Self {
+        Post {
+            id: post.id,
+            title: post.title,
+            body: post.body,
+            created_by: post.created_by.map(|author| Author {
+                user_id: author.user_id,
+                username: author.username,
+                first_name: author.first_name,
+                last_name: author.last_name,
+            }),
+            created_at: post.created_at,
+            updated_at: post.updated_at,
+            featured: post.featured,
+            view_count: post.view_count,
+            tags: post.tags,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Author {
+    user_id: Uuid,
+    username: String,
+    first_name: String,
+    last_name: Option<String>,
+}
+
+#[derive(SimpleObject)]
+pub struct User {
+    id: Uuid,
+    username: String,
+    first_name: String,
+    last_name: String,
+    created_at: DateTime<Utc>,
+}
+
+impl From<models::User> for User {
+    fn from(user: models::User) -> Self {
+        User {
+            id: user.id,
+            username: user.username,
+            first_name: user.first_name,
+            last_name: user.last_name,
+            created_at: user.created_at,
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use chrono::{DateTime, Utc};
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use uuid::Uuid;
+
+/// Request guard for routes that take a JSON body. Fails with
+/// `415 Unsupported Media Type` unless the request declares
+/// `Content-Type: application/json`, so a form-encoded body is rejected up
+/// front instead of surfacing as a confusing parse error.
+pub struct JsonContentType;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for JsonContentType {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req.content_type() {
+            Some(content_type) if content_type.is_json() => Outcome::Success(JsonContentType),
+            _ => Outcome::Error((Status::UnsupportedMediaType, ())),
+        }
+    }
+}
+
+/// Correlation id for a request: the client's `X-Request-Id` when it is a
+/// reasonable token, otherwise a fresh UUID v4. Resolved once and kept in
+/// request-local state, so every guard, handler and fairing sees the same id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(String);
+
+impl RequestId {
+    pub const HEADER: &'static str = "X-Request-Id";
+    const MAX_LENGTH: usize = 128;
+
+    pub fn of<'r>(req: &'r Request<'_>) -> &'r RequestId {
+        req.local_cache(|| {
+            let id = req
+                .headers()
+                .get_one(Self::HEADER)
+                .filter(|id| Self::is_acceptable(id))
+                .map(str::to_string)
+                .unwrap_or_else(|| Uuid::new_v4().to_string());
+            RequestId(id)
+        })
+    }
+
+    /// Client ids end up in logs, so only short printable tokens are kept.
+    fn is_acceptable(id: &str) -> bool {
+        !id.is_empty() && id.len() <= Self::MAX_LENGTH && id.chars().all(|c| c.is_ascii_graphic())
+    }
+}
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestId {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(RequestId::of(req).clone())
+    }
+}
+
+/// The client's cache validators, from `If-None-Match` and
+/// `If-Modified-Since`. Unparseable dates are ignored rather than rejected.
+#[derive(Debug, Default)]
+pub struct CacheValidators {
+    if_none_match: Option<String>,
+    if_modified_since: Option<DateTime<Utc>>,
+}
+
+impl CacheValidators {
+    /// Whether the client's copy of a resource with this `etag` and
+    /// `last_modified` is still current. As RFC 9110 requires,
+    /// `If-Modified-Since` is only consulted without `If-None-Match`, and
+    /// tags are compared weakly.
+    pub fn is_fresh(&self, etag: &str, last_modified: DateTime<Utc>) -> bool {
+        if let Some(tags) = &self.if_none_match {
+            let etag = etag.trim_start_matches("W/");
+            return tags
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag);
+        }
+        // HTTP dates only have whole seconds.
+        self.if_modified_since
+            .is_some_and(|since| last_modified.timestamp() <= since.timestamp())
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CacheValidators {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let headers = req.headers();
+        Outcome::Success(CacheValidators {
+            if_none_match: headers.get_one("If-None-Match").map(str::to_string),
+            if_modified_since: headers
+                .get_one("If-Modified-Since")
+                .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+                .map(|date| date.with_timezone(&Utc)),
+        })
+    }
+}
@@ -1,88 +1,2099 @@
+use diesel::pg::PgConnection;
+use diesel::result::DatabaseErrorKind;
+use rocket::futures::{SinkExt, StreamExt};
+use rocket::http::uri::Origin;
+use rocket::http::{ContentType, RawStr};
+use rocket::response::stream::{Event, EventStream, TextStream};
 use rocket::serde::json::Json;
-use rocket::State;
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::{Shutdown, State};
+use rocket_ws::{Channel, Message, WebSocket};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
 
+use crate::auth::{Admin, AuthUser};
+use crate::author_cache::AuthorCache;
+use crate::config::Config;
 use crate::db::DbPool;
-use crate::models::{NewPostWithTags, NewUser, PaginatedResponse};
-use crate::repository::{PostRepository, UserRepository};
+use crate::errors::ApiError;
+use crate::feed::{self, Rss, DEFAULT_FEED_ITEMS, MAX_FEED_ITEMS};
+use crate::guards::{CacheValidators, JsonContentType};
+use crate::models::{
+    build_comment_tree, collapse_whitespace, normalize_tags, validate_username, ArchiveMonth,
+    Attachment, AuditEntry, BatchIds, Comment, CommentNode, DumpPost, DumpUser, FieldErrors,
+    ImportItemResult, ImportPreview, ImportRequest, ImportSummary, NewAttachment,
+    NewAttachmentRequest, NewComment, NewCommentRequest, NewPostWithTags, NewUser, NewWebhook,
+    OnboardRequest, Onboarded, PaginatedResponse, Post, PostChanges, PostDetail, PostFilters,
+    PostSearchResult, PostSort, PostTag, PostWithUserAndTags, PurgedPosts, Reassign, Reassignment,
+    RenameTag, SetTags, Setting, SettingValue, Stats, TagChanges, TagCount, TagRename,
+    TagWithLinks, UpdateComment, UpdatePost, User, UserWithStats, Webhook, DEFAULT_POPULAR_DAYS,
+    EXPORT_VERSION, MAX_LATEST_PER_AUTHOR_LIMIT, MAX_POPULAR_LIMIT, MAX_SEARCH_RESULTS_LIMIT,
+    MAX_TAG_LENGTH, POST_CREATED_EVENT,
+};
+use crate::notifications::{CommentEvents, PostEvents};
+use crate::openapi::schemas::{
+    DeletedTag, ErrorBody, ExportDocument, Favorite, PostBatch, PostCount, RemovedTags, Success,
+    SuccessWithWarnings, UsernameAvailability, ValidationErrorBody,
+};
+use crate::rate_limit::WriteLimit;
+use crate::repository::{
+    is_tag_too_long, AuditRepository, CommentRepository, FavoriteRepository, ImportRepository,
+    PostRepository, SettingsRepository, StatsRepository, UserRepository, WebhookRepository,
+};
+use crate::responders::{pagination_links, Cached, LimitClamped, WithLinks};
+use crate::settings::{self, Settings, MAX_POSTS_PER_PAGE};
+use crate::webhooks::Webhooks;
 
+#[utoipa::path(
+    tag = "users",
+    request_body = NewUser,
+    responses(
+        (status = 200, body = Success<User>),
+        (status = 409, description = "Username is already taken", body = ErrorBody),
+        (status = 413, description = "Body is over `MAX_JSON_BODY_BYTES`", body = ErrorBody),
+        (status = 415, description = "`Content-Type` is not `application/json`", body = ErrorBody),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
 #[post("/users", data = "<user_data>")]
 pub async fn create_user(
+    _limit: WriteLimit,
+    _json: JsonContentType,
     pool: &State<DbPool>,
+    config: &State<Config>,
     user_data: Json<NewUser>,
-) -> Json<serde_json::Value> {
-    let new_user = NewUser {
-        username: user_data.username.clone(),
-        first_name: user_data.first_name.clone(),
-        last_name: user_data.last_name.clone(),
-    };
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut new_user = user_data.into_inner();
+    new_user.sanitize();
+    new_user.validate().map_err(ApiError::Validation)?;
 
-    let mut conn = pool.get().expect("Failed to get DB connection from pool.");
+    let mut conn = pool.get()?;
 
-    match UserRepository::create(&mut conn, new_user) {
-        Ok(user) => Json(serde_json::json!({
+    match UserRepository::create(&mut conn, new_user, config.id_version) {
+        Ok(user) => Ok(Json(serde_json::json!({
             "success": true,
             "data": user
-        })),
-        Err(_) => Json(serde_json::json!({
+        }))),
+        Err(diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            Err(ApiError::Conflict("Username is already taken".to_string()))
+        }
+        Err(_) => Ok(Json(serde_json::json!({
             "success": false,
             "error": "Failed to create user"
-        })),
+        }))),
+    }
+}
+
+/// Creates a user and their first post together for onboarding flows.
+#[utoipa::path(
+    tag = "users",
+    request_body = OnboardRequest,
+    responses(
+        (status = 200, body = SuccessWithWarnings<Onboarded>),
+        (status = 409, description = "Username is already taken", body = ErrorBody),
+        (status = 415, description = "`Content-Type` is not `application/json`", body = ErrorBody),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[post("/onboard", data = "<request>")]
+pub async fn onboard(
+    _limit: WriteLimit,
+    _json: JsonContentType,
+    pool: &State<DbPool>,
+    config: &State<Config>,
+    webhooks: &State<Webhooks>,
+    request: Json<OnboardRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut request = request.into_inner();
+    request.sanitize();
+    let warnings = request
+        .validate(config.max_tags_per_post)
+        .map_err(ApiError::Validation)?;
+
+    let mut conn = pool.get()?;
+
+    let tags = request.post.tags.clone();
+    match UserRepository::create_with_first_post(
+        &mut conn,
+        request.user,
+        request.post,
+        config.id_version,
+        config.post_isolation_level,
+    ) {
+        Ok(onboarded) => {
+            // Subscribers get the same payload as for `POST /posts`.
+            if let Ok(Some(post)) = PostRepository::find_by_id(&mut conn, onboarded.post.id) {
+                webhooks.dispatch(
+                    pool,
+                    POST_CREATED_EVENT,
+                    serde_json::json!({
+                        "event": POST_CREATED_EVENT,
+                        "data": { "post": post, "tags": tags }
+                    }),
+                );
+            }
+
+            Ok(Json(with_warnings(
+                serde_json::json!({
+                    "success": true,
+                    "data": onboarded
+                }),
+                warnings,
+            )))
+        }
+        Err(diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            Err(ApiError::Conflict("Username is already taken".to_string()))
+        }
+        Err(e) if is_tag_too_long(&e) => Err(tag_too_long("post.tags")),
+        Err(_) => Err(ApiError::Internal(
+            "Failed to create user and post".to_string(),
+        )),
+    }
+}
+
+/// For sign-up forms. The username is checked like `POST /users` checks it,
+/// so an invalid one is a 422 rather than "available".
+#[utoipa::path(
+    tag = "users",
+    responses(
+        (status = 200, body = Success<UsernameAvailability>),
+        (status = 422, description = "Invalid username", body = ValidationErrorBody),
+    )
+)]
+#[get("/users/available?<username>")]
+pub async fn username_available(
+    pool: &State<DbPool>,
+    username: Option<&str>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let username = collapse_whitespace(username.unwrap_or_default());
+    validate_username(&username).map_err(ApiError::Validation)?;
+
+    let mut conn = pool.get()?;
+
+    match UserRepository::username_exists(&mut conn, &username) {
+        Ok(exists) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": { "available": !exists }
+        }))),
+        Err(_) => Err(ApiError::Internal("Failed to check username".to_string())),
+    }
+}
+
+/// `?stats=true` adds `post_count`.
+#[utoipa::path(
+    tag = "users",
+    responses(
+        (status = 200, description = "`post_count` is only present with `?stats=true`", body = Success<UserWithStats>),
+        (status = 404, description = "User not found", body = ErrorBody),
+    )
+)]
+#[get("/users/<id>?<stats>")]
+pub async fn get_user(
+    pool: &State<DbPool>,
+    id: Uuid,
+    stats: Option<bool>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut conn = pool.get()?;
+
+    let user = if stats.unwrap_or(false) {
+        UserRepository::get_with_stats(&mut conn, id)
+            .map(|user| user.map(|user| serde_json::json!(user)))
+    } else {
+        UserRepository::get(&mut conn, id).map(|user| user.map(|user| serde_json::json!(user)))
+    };
+    match user {
+        Ok(Some(user)) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": user
+        }))),
+        Ok(None) => Err(ApiError::NotFound("User not found".to_string())),
+        Err(_) => Err(ApiError::Internal("Failed to fetch user".to_string())),
+    }
+}
+
+/// Upper bound on ids accepted by the batch lookup endpoints.
+const MAX_BATCH_IDS: usize = 100;
+
+/// `?stats=true` adds `post_count` to each user.
+#[utoipa::path(
+    tag = "users",
+    request_body = BatchIds,
+    responses(
+        (status = 200, description = "`post_count` is only present with `?stats=true`", body = Success<Vec<UserWithStats>>),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[post("/users/batch?<stats>", data = "<batch>")]
+pub async fn batch_users(
+    pool: &State<DbPool>,
+    stats: Option<bool>,
+    batch: Json<BatchIds>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    if batch.ids.len() > MAX_BATCH_IDS {
+        return Err(ApiError::field(
+            "ids",
+            format!("must contain at most {MAX_BATCH_IDS} ids"),
+        ));
+    }
+
+    let mut conn = pool.get()?;
+
+    let users = if stats.unwrap_or(false) {
+        UserRepository::find_by_ids_with_stats(&mut conn, &batch.ids)
+            .map(|users| serde_json::json!(users))
+    } else {
+        UserRepository::find_by_ids(&mut conn, &batch.ids).map(|users| serde_json::json!(users))
+    };
+    match users {
+        Ok(users) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": users
+        }))),
+        Err(_) => Err(ApiError::Internal("Failed to fetch users".to_string())),
     }
 }
 
+#[utoipa::path(
+    tag = "posts",
+    request_body = NewPostWithTags,
+    responses(
+        (status = 200, body = SuccessWithWarnings<Post>),
+        (status = 413, description = "Body is over `MAX_JSON_BODY_BYTES`", body = ErrorBody),
+        (status = 415, description = "`Content-Type` is not `application/json`", body = ErrorBody),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
 #[post("/posts", data = "<post_data>")]
 pub async fn create_post(
+    _limit: WriteLimit,
+    _json: JsonContentType,
     pool: &State<DbPool>,
+    config: &State<Config>,
+    webhooks: &State<Webhooks>,
     post_data: Json<NewPostWithTags>,
-) -> Json<serde_json::Value> {
-    let new_post_with_tags = NewPostWithTags {
-        title: post_data.title.clone(),
-        body: post_data.body.clone(),
-        created_by: post_data.created_by,
-        tags: post_data.tags.clone(),
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut new_post_with_tags = post_data.into_inner();
+    new_post_with_tags.sanitize();
+
+    let warnings = new_post_with_tags
+        .validate(config.max_tags_per_post)
+        .map_err(ApiError::Validation)?;
+
+    let mut conn = pool.get()?;
+
+    let tags = new_post_with_tags.tags.clone();
+    match PostRepository::create_with_tags(
+        &mut conn,
+        new_post_with_tags,
+        config.id_version,
+        config.post_isolation_level,
+    ) {
+        Ok(post) => {
+            webhooks.dispatch(
+                pool,
+                POST_CREATED_EVENT,
+                serde_json::json!({
+                    "event": POST_CREATED_EVENT,
+                    "data": { "post": &post, "tags": tags }
+                }),
+            );
+
+            Ok(Json(with_warnings(
+                serde_json::json!({
+                    "success": true,
+                    "data": post
+                }),
+                warnings,
+            )))
+        }
+        Err(e) if is_tag_too_long(&e) => Err(tag_too_long("tags")),
+        Err(_) => Ok(Json(serde_json::json!({
+            "success": false,
+            "error": "Failed to create post"
+        }))),
+    }
+}
+
+/// The `page` query parameter of a paginated list, defaulting to the first
+/// page. Pages count from 1; anything lower would be a negative offset.
+fn requested_page(page: Option<i64>) -> Result<i64, ApiError> {
+    match page {
+        Some(page) if page < 1 => Err(ApiError::field("page", "must be 1 or more")),
+        page => Ok(page.unwrap_or(1)),
+    }
+}
+
+/// The 422 validation gives for an over-long tag, for when only the
+/// database's length check caught it.
+fn tag_too_long(field: &str) -> ApiError {
+    ApiError::field(
+        field,
+        format!("must each be at most {MAX_TAG_LENGTH} characters"),
+    )
+}
+
+/// Adds a `warnings` array to a success envelope when validation produced
+/// any, leaving the response unchanged otherwise.
+fn with_warnings(mut body: serde_json::Value, warnings: Vec<String>) -> serde_json::Value {
+    if !warnings.is_empty() {
+        body["warnings"] = serde_json::json!(warnings);
+    }
+    body
+}
+
+/// `?debug=true` timings are only reported when `DEBUG_QUERY_TIMING` is set,
+/// so production deployments can't be probed for query latency.
+fn query_timing_enabled(config: &Config, debug: Option<bool>) -> bool {
+    debug.unwrap_or(false) && config.debug_query_timing
+}
+
+/// A `limit` query parameter with the endpoint's default applied and
+/// clamped to `1..=max`.
+struct Limit {
+    value: i64,
+    requested: Option<i64>,
+}
+
+impl Limit {
+    fn new(requested: Option<i64>, default: i64, max: i64) -> Self {
+        Limit {
+            value: requested.unwrap_or(default).clamp(1, max),
+            requested,
+        }
+    }
+
+    /// For page-based lists, which also accept `per_page` to match
+    /// `PaginationMeta`. `limit` wins when both are sent.
+    fn paginated(limit: Option<i64>, per_page: Option<i64>, default: i64, max: i64) -> Self {
+        Self::new(limit.or(per_page), default, max)
+    }
+
+    /// The limit the client asked for, when clamping changed it.
+    fn clamped_from(&self) -> Option<i64> {
+        self.requested.filter(|&requested| requested != self.value)
+    }
+
+    fn respond<R>(&self, inner: R) -> LimitClamped<R> {
+        LimitClamped::new(inner, self.clamped_from().is_some())
+    }
+}
+
+/// Weak validator for a post: it changes whenever the post is edited or its
+/// `comment_count` moves, but not on every view, so the cached `view_count`
+/// may lag behind.
+fn post_etag(post: &PostWithUserAndTags) -> String {
+    format!(
+        "W/\"{}-{}-{}\"",
+        post.id,
+        post.updated_at.timestamp_micros(),
+        post.comment_count.unwrap_or(0)
+    )
+}
+
+#[utoipa::path(
+    tag = "posts",
+    request_body = BatchIds,
+    responses(
+        (status = 200, body = Success<PostBatch>),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[post("/posts/batch", data = "<batch>")]
+pub async fn batch_posts(
+    pool: &State<DbPool>,
+    batch: Json<BatchIds>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    if batch.ids.len() > MAX_BATCH_IDS {
+        return Err(ApiError::field(
+            "ids",
+            format!("must contain at most {MAX_BATCH_IDS} ids"),
+        ));
+    }
+
+    let mut conn = pool.get()?;
+
+    match PostRepository::find_many(&mut conn, &batch.ids) {
+        Ok(posts) => {
+            let mut seen: HashSet<Uuid> = posts.iter().map(|post| post.id).collect();
+            let not_found: Vec<Uuid> = batch
+                .ids
+                .iter()
+                .copied()
+                .filter(|id| seen.insert(*id))
+                .collect();
+
+            Ok(Json(serde_json::json!({
+                "success": true,
+                "data": { "records": posts, "not_found": not_found }
+            })))
+        }
+        Err(_) => Err(ApiError::Internal("Failed to fetch posts".to_string())),
+    }
+}
+
+#[utoipa::path(
+    tag = "posts",
+    responses(
+        (status = 200, body = Success<PostDetail>),
+        (status = 304, description = "Unchanged since `If-None-Match` or `If-Modified-Since`"),
+        (status = 404, description = "Post not found", body = ErrorBody),
+    )
+)]
+#[get("/posts/<id>")]
+pub async fn get_post(
+    pool: &State<DbPool>,
+    validators: CacheValidators,
+    id: Uuid,
+) -> Result<Cached<Json<serde_json::Value>>, ApiError> {
+    let mut conn = pool.get()?;
+
+    match PostRepository::record_view(&mut conn, id)
+        .and_then(|_| PostRepository::find(&mut conn, id))
+    {
+        Ok(Some(post)) => {
+            let etag = post_etag(&post.post);
+            let last_modified = post.post.updated_at;
+            Ok(Cached::new(
+                Json(serde_json::json!({
+                    "success": true,
+                    "data": post
+                })),
+                etag,
+                last_modified,
+                &validators,
+            ))
+        }
+        Ok(None) => Err(ApiError::NotFound("Post not found".to_string())),
+        Err(_) => Err(ApiError::Internal("Failed to fetch post".to_string())),
+    }
+}
+
+/// `get_post` under the `/posts/<id>/<slug>` links the RSS feed gives out.
+/// Only the id is looked at, so links keep working after a retitle.
+#[utoipa::path(
+    tag = "posts",
+    responses(
+        (status = 200, body = Success<PostDetail>),
+        (status = 304, description = "Unchanged since `If-None-Match` or `If-Modified-Since`"),
+        (status = 404, description = "Post not found", body = ErrorBody),
+    )
+)]
+#[get("/posts/<id>/<_slug>")]
+pub async fn get_post_by_slug(
+    pool: &State<DbPool>,
+    validators: CacheValidators,
+    id: Uuid,
+    _slug: &str,
+) -> Result<Cached<Json<serde_json::Value>>, ApiError> {
+    get_post(pool, validators, id).await
+}
+
+#[utoipa::path(
+    tag = "posts",
+    request_body = UpdatePost,
+    security(("user_id" = [])),
+    responses(
+        (status = 200, body = Success<Post>),
+        (status = 401, description = "`X-User-Id` is missing or names no user", body = ErrorBody),
+        (status = 404, description = "Post not found", body = ErrorBody),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[patch("/posts/<id>", data = "<changes>")]
+pub async fn update_post(
+    _limit: WriteLimit,
+    pool: &State<DbPool>,
+    editor: AuthUser,
+    id: Uuid,
+    changes: Json<UpdatePost>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    changes.validate().map_err(ApiError::Validation)?;
+
+    let changes = PostChanges {
+        title: changes
+            .title
+            .as_deref()
+            .map(|title| title.trim().to_string()),
+        body: changes.body.clone(),
+        updated_by: Some(editor.0.id),
     };
 
-    let mut conn = pool.get().expect("Failed to get DB connection from pool.");
+    let mut conn = pool.get()?;
 
-    match PostRepository::create_with_tags(&mut conn, new_post_with_tags) {
-        Ok(post) => Json(serde_json::json!({
+    match PostRepository::update(&mut conn, id, changes) {
+        Ok(Some(post)) => Ok(Json(serde_json::json!({
             "success": true,
             "data": post
-        })),
-        Err(_) => Json(serde_json::json!({
-            "success": false,
-            "error": "Failed to create post"
-        })),
+        }))),
+        Ok(None) => Err(ApiError::NotFound("Post not found".to_string())),
+        Err(_) => Err(ApiError::Internal("Failed to update post".to_string())),
     }
 }
 
-#[get("/posts?<page>&<limit>&<search>")]
+/// Flips whether the post is featured.
+#[utoipa::path(
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = Success<Post>),
+        (status = 401, description = "`X-Admin-Token` is missing", body = ErrorBody),
+        (status = 403, description = "`X-Admin-Token` is wrong or `ADMIN_TOKEN` is unset", body = ErrorBody),
+        (status = 404, description = "Post not found", body = ErrorBody),
+    )
+)]
+#[post("/posts/<id>/feature")]
+pub async fn toggle_featured(
+    _admin: Admin,
+    pool: &State<DbPool>,
+    id: Uuid,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut conn = pool.get()?;
+
+    match PostRepository::toggle_featured(&mut conn, id) {
+        Ok(Some(post)) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": post
+        }))),
+        Ok(None) => Err(ApiError::NotFound("Post not found".to_string())),
+        Err(_) => Err(ApiError::Internal("Failed to update post".to_string())),
+    }
+}
+
+/// Moves the post to another author.
+#[utoipa::path(
+    tag = "admin",
+    request_body = Reassign,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = Success<Post>),
+        (status = 401, description = "`X-Admin-Token` is missing", body = ErrorBody),
+        (status = 403, description = "`X-Admin-Token` is wrong or `ADMIN_TOKEN` is unset", body = ErrorBody),
+        (status = 404, description = "Post not found", body = ErrorBody),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[patch("/posts/<id>/author", data = "<reassign>")]
+pub async fn reassign_post(
+    _admin: Admin,
+    pool: &State<DbPool>,
+    id: Uuid,
+    reassign: Json<Reassign>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut conn = pool.get()?;
+
+    match PostRepository::reassign(&mut conn, id, reassign.author_id) {
+        Ok(Reassignment::Reassigned(post)) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": post
+        }))),
+        Ok(Reassignment::PostNotFound) => Err(ApiError::NotFound("Post not found".to_string())),
+        Ok(Reassignment::AuthorNotFound) => {
+            Err(ApiError::field("author_id", "no user exists with this id"))
+        }
+        Err(_) => Err(ApiError::Internal("Failed to reassign post".to_string())),
+    }
+}
+
+/// Hard-deletes all of a user's posts and everything attached to them, for
+/// account deletion. The user itself is left in place.
+#[utoipa::path(
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = Success<PurgedPosts>),
+        (status = 401, description = "`X-Admin-Token` is missing", body = ErrorBody),
+        (status = 403, description = "`X-Admin-Token` is wrong or `ADMIN_TOKEN` is unset", body = ErrorBody),
+        (status = 404, description = "User not found", body = ErrorBody),
+    )
+)]
+#[delete("/users/<id>/posts")]
+pub async fn delete_user_posts(
+    _admin: Admin,
+    pool: &State<DbPool>,
+    id: Uuid,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut conn = pool.get()?;
+
+    match PostRepository::delete_all_for_user(&mut conn, id) {
+        Ok(Some(purged)) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": purged
+        }))),
+        Ok(None) => Err(ApiError::NotFound("User not found".to_string())),
+        Err(_) => Err(ApiError::Internal("Failed to delete posts".to_string())),
+    }
+}
+
+#[utoipa::path(
+    tag = "posts",
+    request_body = SetTags,
+    security(("user_id" = [])),
+    responses(
+        (status = 200, body = SuccessWithWarnings<TagChanges>),
+        (status = 401, description = "`X-User-Id` is missing or names no user", body = ErrorBody),
+        (status = 404, description = "Post not found", body = ErrorBody),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[put("/posts/<id>/tags", data = "<tags>")]
+pub async fn set_post_tags(
+    _limit: WriteLimit,
+    pool: &State<DbPool>,
+    config: &State<Config>,
+    editor: AuthUser,
+    id: Uuid,
+    tags: Json<SetTags>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let tags = SetTags {
+        tags: normalize_tags(&tags.tags),
+    };
+    let warnings = tags
+        .validate(config.max_tags_per_post)
+        .map_err(ApiError::Validation)?;
+
+    let mut conn = pool.get()?;
+
+    match PostRepository::set_tags(&mut conn, id, &tags.tags, Some(editor.0.id)) {
+        Ok(Some(changes)) => Ok(Json(with_warnings(
+            serde_json::json!({
+                "success": true,
+                "data": changes
+            }),
+            warnings,
+        ))),
+        Ok(None) => Err(ApiError::NotFound("Post not found".to_string())),
+        Err(e) if is_tag_too_long(&e) => Err(tag_too_long("tags")),
+        Err(_) => Err(ApiError::Internal("Failed to update tags".to_string())),
+    }
+}
+
+#[utoipa::path(
+    tag = "posts",
+    request_body = NewAttachmentRequest,
+    security(("user_id" = [])),
+    responses(
+        (status = 200, body = Success<Attachment>),
+        (status = 401, description = "`X-User-Id` is missing or names no user", body = ErrorBody),
+        (status = 404, description = "Post not found", body = ErrorBody),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[post("/posts/<id>/attachments", data = "<attachment>")]
+pub async fn add_attachment(
+    _limit: WriteLimit,
+    pool: &State<DbPool>,
+    _user: AuthUser,
+    id: Uuid,
+    attachment: Json<NewAttachmentRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let attachment = NewAttachmentRequest {
+        url: attachment.url.trim().to_string(),
+        content_type: attachment.content_type.trim().to_lowercase(),
+        size_bytes: attachment.size_bytes,
+    };
+    attachment.validate().map_err(ApiError::Validation)?;
+
+    let mut conn = pool.get()?;
+
+    let new_attachment = NewAttachment {
+        fk_post_id: id,
+        url: attachment.url,
+        content_type: attachment.content_type,
+        size_bytes: attachment.size_bytes,
+    };
+    match PostRepository::add_attachment(&mut conn, new_attachment) {
+        Ok(Some(attachment)) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": attachment
+        }))),
+        Ok(None) => Err(ApiError::NotFound("Post not found".to_string())),
+        Err(_) => Err(ApiError::Internal("Failed to add attachment".to_string())),
+    }
+}
+
+#[utoipa::path(
+    tag = "comments",
+    request_body = NewCommentRequest,
+    security(("user_id" = [])),
+    responses(
+        (status = 200, body = Success<Comment>),
+        (status = 401, description = "`X-User-Id` is missing or names no user", body = ErrorBody),
+        (status = 404, description = "Post not found", body = ErrorBody),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[post("/posts/<id>/comments", data = "<comment>")]
+pub async fn create_comment(
+    _limit: WriteLimit,
+    pool: &State<DbPool>,
+    events: &State<CommentEvents>,
+    author: AuthUser,
+    id: Uuid,
+    comment: Json<NewCommentRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut comment = comment.into_inner();
+    comment.sanitize();
+    comment.validate().map_err(ApiError::Validation)?;
+
+    let mut conn = pool.get()?;
+
+    match PostRepository::find_by_id(&mut conn, id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(ApiError::NotFound("Post not found".to_string())),
+        Err(_) => return Err(ApiError::Internal("Failed to create comment".to_string())),
+    }
+
+    if let Some(parent_id) = comment.parent_id {
+        match CommentRepository::find_by_id(&mut conn, parent_id) {
+            Ok(Some(parent)) if parent.fk_post_id == id && !parent.is_deleted() => {}
+            Ok(_) => {
+                return Err(ApiError::field(
+                    "parent_id",
+                    "must refer to a comment on the same post",
+                ))
+            }
+            Err(_) => return Err(ApiError::Internal("Failed to create comment".to_string())),
+        }
+    }
+
+    let new_comment = NewComment {
+        fk_post_id: id,
+        author_id: author.0.id,
+        parent_id: comment.parent_id,
+        body: comment.body,
+    };
+    match CommentRepository::create(&mut conn, new_comment) {
+        Ok(comment) => {
+            events.publish(&comment);
+            Ok(Json(serde_json::json!({
+                "success": true,
+                "data": comment
+            })))
+        }
+        Err(_) => Err(ApiError::Internal("Failed to create comment".to_string())),
+    }
+}
+
+/// Loads a live comment that `user` may modify: its author, or any caller
+/// holding the admin token.
+fn editable_comment(
+    conn: &mut PgConnection,
+    id: Uuid,
+    user: Option<&AuthUser>,
+    admin: Option<&Admin>,
+) -> Result<Comment, ApiError> {
+    if user.is_none() && admin.is_none() {
+        return Err(ApiError::Unauthorized(
+            "Authentication required".to_string(),
+        ));
+    }
+
+    let comment = match CommentRepository::find_by_id(conn, id) {
+        Ok(Some(comment)) if !comment.is_deleted() => comment,
+        Ok(_) => return Err(ApiError::NotFound("Comment not found".to_string())),
+        Err(_) => return Err(ApiError::Internal("Failed to fetch comment".to_string())),
+    };
+
+    let is_author = user.is_some_and(|user| user.0.id == comment.author_id);
+    if !is_author && admin.is_none() {
+        return Err(ApiError::Forbidden(
+            "Only the author or an admin may modify this comment".to_string(),
+        ));
+    }
+
+    Ok(comment)
+}
+
+#[utoipa::path(
+    tag = "comments",
+    request_body = UpdateComment,
+    security(("user_id" = []), ("admin_token" = [])),
+    responses(
+        (status = 200, body = Success<Comment>),
+        (status = 401, description = "Neither `X-User-Id` nor `X-Admin-Token` was sent", body = ErrorBody),
+        (status = 403, description = "Not the author", body = ErrorBody),
+        (status = 404, description = "Comment not found", body = ErrorBody),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[patch("/comments/<id>", data = "<update>")]
+pub async fn update_comment(
+    _limit: WriteLimit,
+    pool: &State<DbPool>,
+    user: Option<AuthUser>,
+    admin: Option<Admin>,
+    id: Uuid,
+    update: Json<UpdateComment>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut update = update.into_inner();
+    update.sanitize();
+    update.validate().map_err(ApiError::Validation)?;
+
+    let mut conn = pool.get()?;
+    editable_comment(&mut conn, id, user.as_ref(), admin.as_ref())?;
+
+    match CommentRepository::update_body(&mut conn, id, &update.body) {
+        Ok(Some(comment)) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": comment
+        }))),
+        Ok(None) => Err(ApiError::NotFound("Comment not found".to_string())),
+        Err(_) => Err(ApiError::Internal("Failed to update comment".to_string())),
+    }
+}
+
+#[utoipa::path(
+    tag = "comments",
+    security(("user_id" = []), ("admin_token" = [])),
+    responses(
+        (status = 200, body = Success<Comment>),
+        (status = 401, description = "Neither `X-User-Id` nor `X-Admin-Token` was sent", body = ErrorBody),
+        (status = 403, description = "Not the author", body = ErrorBody),
+        (status = 404, description = "Comment not found", body = ErrorBody),
+    )
+)]
+#[delete("/comments/<id>")]
+pub async fn delete_comment(
+    _limit: WriteLimit,
+    pool: &State<DbPool>,
+    user: Option<AuthUser>,
+    admin: Option<Admin>,
+    id: Uuid,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut conn = pool.get()?;
+    editable_comment(&mut conn, id, user.as_ref(), admin.as_ref())?;
+
+    match CommentRepository::soft_delete(&mut conn, id) {
+        Ok(Some(comment)) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": comment
+        }))),
+        Ok(None) => Err(ApiError::NotFound("Comment not found".to_string())),
+        Err(_) => Err(ApiError::Internal("Failed to delete comment".to_string())),
+    }
+}
+
+#[utoipa::path(
+    tag = "comments",
+    responses(
+        (status = 200, body = Success<Vec<CommentNode>>),
+        (status = 404, description = "Post not found", body = ErrorBody),
+    )
+)]
+#[get("/posts/<id>/comments/tree")]
+pub async fn comment_tree(
+    pool: &State<DbPool>,
+    id: Uuid,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut conn = pool.get()?;
+
+    match PostRepository::find_by_id(&mut conn, id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(ApiError::NotFound("Post not found".to_string())),
+        Err(_) => return Err(ApiError::Internal("Failed to fetch comments".to_string())),
+    }
+
+    match CommentRepository::list_for_post(&mut conn, id) {
+        Ok(comments) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": build_comment_tree(comments)
+        }))),
+        Err(_) => Err(ApiError::Internal("Failed to fetch comments".to_string())),
+    }
+}
+
+#[utoipa::path(
+    tag = "posts",
+    params(PostFilters),
+    responses(
+        (status = 200, body = Success<PaginatedResponse<PostWithUserAndTags>>),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[get("/posts?<page>&<limit>&<per_page>&<sort>&<debug>&<filters..>")]
+#[allow(clippy::too_many_arguments)]
 pub async fn list_posts(
     pool: &State<DbPool>,
+    config: &State<Config>,
+    settings: &State<Settings>,
+    authors: &State<AuthorCache>,
+    origin: &Origin<'_>,
     page: Option<i64>,
     limit: Option<i64>,
-    search: Option<String>,
-) -> Json<serde_json::Value> {
-    let page = page.unwrap_or(1);
-    let limit = limit.unwrap_or(10);
-    let search = search.as_deref();
+    per_page: Option<i64>,
+    sort: Option<&str>,
+    debug: Option<bool>,
+    filters: PostFilters,
+) -> Result<LimitClamped<WithLinks<Json<serde_json::Value>>>, ApiError> {
+    let page = requested_page(page)?;
+    let limit = Limit::paginated(
+        limit,
+        per_page,
+        settings.posts_per_page(),
+        MAX_POSTS_PER_PAGE,
+    );
+    let sort = match sort {
+        Some(sort) => PostSort::parse(sort).map_err(|message| ApiError::field("sort", message))?,
+        None => PostSort::default(),
+    };
+    filters
+        .validate(config.min_search_length)
+        .map_err(ApiError::Validation)?;
+
+    let mut conn = pool.get()?;
 
-    let mut conn = pool.get().expect("Failed to get DB connection from pool.");
+    let started = Instant::now();
+    match PostRepository::find_with_user_and_tags(
+        &mut conn,
+        page,
+        limit.value,
+        &sort,
+        &filters,
+        Some(authors.inner()),
+    ) {
+        Ok((posts, mut meta)) => {
+            if query_timing_enabled(config, debug) {
+                meta.query_ms = Some(started.elapsed().as_secs_f64() * 1000.0);
+            }
+            meta.requested_limit = limit.clamped_from();
 
-    match PostRepository::find_with_user_and_tags(&mut conn, page, limit, search) {
-        Ok((posts, meta)) => {
+            let links = pagination_links(origin, &meta);
             let response = PaginatedResponse {
                 records: posts,
                 meta,
             };
-            Json(serde_json::json!({
-                "success": true,
-                "data": response
-            }))
+            Ok(limit.respond(WithLinks::new(
+                Json(serde_json::json!({
+                    "success": true,
+                    "data": response
+                })),
+                links,
+            )))
         }
+        Err(_) => Ok(
+            limit.respond(WithLinks::without_links(Json(serde_json::json!({
+                "success": false,
+                "error": "Failed to fetch posts"
+            })))),
+        ),
+    }
+}
+
+/// `GET /posts?tag=` under a path of its own, for tag landing pages. The tag
+/// is normalized like stored tags, so any case matches. A tag no post has
+/// gives an empty page with `total_docs: 0` rather than a 404.
+#[utoipa::path(
+    tag = "tags",
+    responses(
+        (status = 200, body = Success<PaginatedResponse<PostWithUserAndTags>>),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[get("/tags/<tag>/posts?<page>&<limit>&<per_page>&<sort>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn tag_posts(
+    pool: &State<DbPool>,
+    config: &State<Config>,
+    settings: &State<Settings>,
+    authors: &State<AuthorCache>,
+    origin: &Origin<'_>,
+    tag: &str,
+    page: Option<i64>,
+    limit: Option<i64>,
+    per_page: Option<i64>,
+    sort: Option<&str>,
+) -> Result<LimitClamped<WithLinks<Json<serde_json::Value>>>, ApiError> {
+    let filters = PostFilters {
+        tag: Some(tag.to_string()),
+        ..Default::default()
+    };
+    // A blank tag would otherwise drop the filter and list every post.
+    if filters.tag_term().is_none() {
+        return Err(ApiError::field("tag", "must not be blank"));
+    }
+
+    list_posts(
+        pool, config, settings, authors, origin, page, limit, per_page, sort, None, filters,
+    )
+    .await
+}
+
+#[utoipa::path(
+    tag = "posts",
+    responses(
+        (status = 200, body = Success<Vec<PostWithUserAndTags>>),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[get("/posts/popular?<days>&<limit>")]
+pub async fn popular_posts(
+    pool: &State<DbPool>,
+    days: Option<i32>,
+    limit: Option<i64>,
+) -> Result<LimitClamped<Json<serde_json::Value>>, ApiError> {
+    let days = days.unwrap_or(DEFAULT_POPULAR_DAYS);
+    if days < 0 {
+        return Err(ApiError::field("days", "must be 0 (all time) or more"));
+    }
+    let limit = Limit::new(limit, 10, MAX_POPULAR_LIMIT);
+
+    let mut conn = pool.get()?;
+
+    match PostRepository::popular(&mut conn, days, limit.value) {
+        Ok(posts) => Ok(limit.respond(Json(serde_json::json!({
+            "success": true,
+            "data": posts
+        })))),
+        Err(_) => Err(ApiError::Internal(
+            "Failed to load popular posts".to_string(),
+        )),
+    }
+}
+
+/// Each author's most recent post, for a team activity view.
+#[utoipa::path(
+    tag = "posts",
+    responses(
+        (status = 200, body = Success<Vec<PostWithUserAndTags>>),
+    )
+)]
+#[get("/posts/latest-per-author?<limit>")]
+pub async fn latest_per_author(
+    pool: &State<DbPool>,
+    limit: Option<i64>,
+) -> Result<LimitClamped<Json<serde_json::Value>>, ApiError> {
+    let limit = Limit::new(limit, 20, MAX_LATEST_PER_AUTHOR_LIMIT);
+
+    let mut conn = pool.get()?;
+
+    match PostRepository::latest_per_author(&mut conn, limit.value) {
+        Ok(posts) => Ok(limit.respond(Json(serde_json::json!({
+            "success": true,
+            "data": posts
+        })))),
+        Err(_) => Err(ApiError::Internal(
+            "Failed to load latest posts".to_string(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    tag = "posts",
+    responses(
+        (status = 200, body = Success<Vec<ArchiveMonth>>),
+    )
+)]
+#[get("/posts/archive")]
+pub async fn post_archive(pool: &State<DbPool>) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut conn = pool.get()?;
+
+    match PostRepository::archive_counts(&mut conn) {
+        Ok(counts) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": counts.into_iter().map(ArchiveMonth::from).collect::<Vec<_>>()
+        }))),
+        Err(_) => Err(ApiError::Internal("Failed to load archive".to_string())),
+    }
+}
+
+#[utoipa::path(
+    tag = "posts",
+    params(PostFilters),
+    responses(
+        (status = 200, body = Success<PostCount>),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[get("/posts/count?<filters..>")]
+pub async fn count_posts(
+    pool: &State<DbPool>,
+    config: &State<Config>,
+    filters: PostFilters,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    filters
+        .validate(config.min_search_length)
+        .map_err(ApiError::Validation)?;
+
+    let mut conn = pool.get()?;
+
+    match PostRepository::count(&mut conn, &filters) {
+        Ok(count) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": { "count": count }
+        }))),
+        Err(_) => Ok(Json(serde_json::json!({
+            "success": false,
+            "error": "Failed to count posts"
+        }))),
+    }
+}
+
+/// Lightweight `{ id, title, slug }` matches for search-as-you-type. A `q`
+/// shorter than the minimum search length returns no results rather than an
+/// error, since clients call this on every keystroke.
+#[utoipa::path(
+    tag = "posts",
+    responses(
+        (status = 200, body = Success<Vec<PostSearchResult>>),
+    )
+)]
+#[get("/search/posts?<q>&<limit>")]
+pub async fn search_posts(
+    pool: &State<DbPool>,
+    config: &State<Config>,
+    q: Option<String>,
+    limit: Option<i64>,
+) -> Result<LimitClamped<Json<serde_json::Value>>, ApiError> {
+    let term = q.as_deref().map(str::trim).unwrap_or("");
+    let limit = Limit::new(limit, 10, MAX_SEARCH_RESULTS_LIMIT);
+    if term.chars().count() < config.min_search_length.max(1) {
+        return Ok(limit.respond(Json(serde_json::json!({
+            "success": true,
+            "data": []
+        }))));
+    }
+
+    let mut conn = pool.get()?;
+
+    match PostRepository::search_titles(&mut conn, term, limit.value) {
+        Ok(results) => Ok(limit.respond(Json(serde_json::json!({
+            "success": true,
+            "data": results
+        })))),
+        Err(_) => Err(ApiError::Internal("Failed to search posts".to_string())),
+    }
+}
+
+/// RSS 2.0 feed of the newest posts, optionally only those with `tag`. Links
+/// are absolute under `SITE_URL`, as RSS requires, and the route is only
+/// mounted when it is set; the request's `Host` is never used, since a client
+/// could plant any value there for a cache to keep.
+#[utoipa::path(
+    tag = "feeds",
+    responses(
+        (status = 200, description = "RSS 2.0 document", body = String, content_type = "application/rss+xml"),
+    )
+)]
+#[get("/feed.xml?<tag>&<limit>")]
+pub async fn rss_feed(
+    pool: &State<DbPool>,
+    config: &State<Config>,
+    tag: Option<String>,
+    limit: Option<i64>,
+) -> Result<LimitClamped<Rss>, ApiError> {
+    let limit = Limit::new(limit, DEFAULT_FEED_ITEMS, MAX_FEED_ITEMS);
+    let filters = PostFilters {
+        tag,
+        ..Default::default()
+    };
+    let Some(site_url) = config.site_url.as_deref() else {
+        return Err(ApiError::NotFound("The feed needs SITE_URL".to_string()));
+    };
+    let base_url = format!("{site_url}{}", config.api_url(""));
+
+    let mut conn = pool.get()?;
+
+    match PostRepository::find_with_user_and_tags(
+        &mut conn,
+        1,
+        limit.value,
+        &PostSort::default(),
+        &filters,
+        None,
+    ) {
+        Ok((posts, _)) => Ok(limit.respond(Rss(feed::rss(
+            &base_url,
+            filters.tag_term().as_deref(),
+            &posts,
+        )))),
+        Err(_) => Err(ApiError::Internal("Failed to build feed".to_string())),
+    }
+}
+
+#[utoipa::path(
+    tag = "tags",
+    responses(
+        (status = 200, description = "`posts_url` and `feed_url` are only present with `?links=true`", body = Success<Vec<TagWithLinks>>),
+    )
+)]
+#[get("/tags?<links>")]
+pub async fn list_tags(
+    pool: &State<DbPool>,
+    config: &State<Config>,
+    links: Option<bool>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut conn = pool.get()?;
+
+    let tags = match PostRepository::tag_counts(&mut conn) {
+        Ok(tags) => tags,
+        Err(_) => return Err(ApiError::Internal("Failed to fetch tags".to_string())),
+    };
+    let data = if links.unwrap_or(false) {
+        serde_json::json!(tags
+            .into_iter()
+            .map(|tag| tag_with_links(config, tag))
+            .collect::<Vec<_>>())
+    } else {
+        serde_json::json!(tags)
+    };
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "data": data
+    })))
+}
+
+/// Links to the post list and RSS feed filtered by `tag`, the latter only
+/// when the feed is served.
+fn tag_with_links(config: &Config, tag: TagCount) -> TagWithLinks {
+    let query = RawStr::new(&tag.tag).percent_encode();
+    TagWithLinks {
+        posts_url: config.api_url(&format!("/posts?tag={query}")),
+        feed_url: config
+            .site_url
+            .as_ref()
+            .map(|_| config.api_url(&format!("/feed.xml?tag={query}"))),
+        tag,
+    }
+}
+
+const MAX_TAG_SUGGESTIONS: i64 = 50;
+
+#[utoipa::path(
+    tag = "tags",
+    responses(
+        (status = 200, body = Success<Vec<TagCount>>),
+    )
+)]
+#[get("/tags/suggest?<q>&<limit>")]
+pub async fn suggest_tags(
+    pool: &State<DbPool>,
+    q: Option<String>,
+    limit: Option<i64>,
+) -> Result<LimitClamped<Json<serde_json::Value>>, ApiError> {
+    let prefix = q.as_deref().map(str::trim).unwrap_or("");
+    let limit = Limit::new(limit, 10, MAX_TAG_SUGGESTIONS);
+
+    let mut conn = pool.get()?;
+
+    Ok(limit.respond(
+        match PostRepository::suggest_tags(&mut conn, prefix, limit.value) {
+            Ok(tags) => Json(serde_json::json!({
+                "success": true,
+                "data": tags
+            })),
+            Err(_) => Json(serde_json::json!({
+                "success": false,
+                "error": "Failed to suggest tags"
+            })),
+        },
+    ))
+}
+
+/// Fixes a misspelled tag everywhere it is used.
+#[utoipa::path(
+    tag = "admin",
+    request_body = RenameTag,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = Success<TagRename>),
+        (status = 401, description = "`X-Admin-Token` is missing", body = ErrorBody),
+        (status = 403, description = "`X-Admin-Token` is wrong or `ADMIN_TOKEN` is unset", body = ErrorBody),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[post("/admin/tags/rename", data = "<rename>")]
+pub async fn rename_tag(
+    _admin: Admin,
+    pool: &State<DbPool>,
+    rename: Json<RenameTag>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut rename = rename.into_inner();
+    rename.sanitize();
+    rename.validate().map_err(ApiError::Validation)?;
+
+    let mut conn = pool.get()?;
+
+    match PostRepository::rename_tag(&mut conn, &rename.from, &rename.to) {
+        Ok(result) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": result
+        }))),
+        Err(e) if is_tag_too_long(&e) => Err(ApiError::field(
+            "to",
+            format!("must be at most {MAX_TAG_LENGTH} characters"),
+        )),
+        Err(_) => Err(ApiError::Internal("Failed to rename tag".to_string())),
+    }
+}
+
+/// Strips a spam or retired tag from every post. Answers 404 when no post
+/// has it, so callers can tell the delete was a no-op.
+#[utoipa::path(
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = Success<DeletedTag>),
+        (status = 401, description = "`X-Admin-Token` is missing", body = ErrorBody),
+        (status = 403, description = "`X-Admin-Token` is wrong or `ADMIN_TOKEN` is unset", body = ErrorBody),
+        (status = 404, description = "No post has this tag", body = ErrorBody),
+    )
+)]
+#[delete("/admin/tags/<tag>")]
+pub async fn delete_tag(
+    _admin: Admin,
+    pool: &State<DbPool>,
+    tag: &str,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let tag = tag.trim().to_lowercase();
+    let mut conn = pool.get()?;
+
+    match PostRepository::delete_tag(&mut conn, &tag) {
+        Ok(0) => Err(ApiError::NotFound("No post has this tag".to_string())),
+        Ok(posts) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": { "tag": tag, "posts": posts }
+        }))),
+        Err(_) => Err(ApiError::Internal("Failed to delete tag".to_string())),
+    }
+}
+
+#[utoipa::path(
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = Success<Stats>),
+        (status = 401, description = "`X-Admin-Token` is missing", body = ErrorBody),
+        (status = 403, description = "`X-Admin-Token` is wrong or `ADMIN_TOKEN` is unset", body = ErrorBody),
+    )
+)]
+#[get("/admin/stats")]
+pub async fn stats(
+    _admin: Admin,
+    pool: &State<DbPool>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut conn = pool.get()?;
+
+    match StatsRepository::summary(&mut conn) {
+        Ok(stats) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": stats
+        }))),
+        Err(_) => Err(ApiError::Internal("Failed to fetch stats".to_string())),
+    }
+}
+
+#[utoipa::path(
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = Success<Vec<PostTag>>),
+        (status = 401, description = "`X-Admin-Token` is missing", body = ErrorBody),
+        (status = 403, description = "`X-Admin-Token` is wrong or `ADMIN_TOKEN` is unset", body = ErrorBody),
+    )
+)]
+#[get("/tags/orphaned")]
+pub async fn orphaned_tags(
+    _admin: Admin,
+    pool: &State<DbPool>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut conn = pool.get()?;
+
+    Ok(match PostRepository::orphaned_tags(&mut conn) {
+        Ok(tags) => Json(serde_json::json!({
+            "success": true,
+            "data": tags
+        })),
         Err(_) => Json(serde_json::json!({
             "success": false,
-            "error": "Failed to fetch posts"
+            "error": "Failed to fetch orphaned tags"
         })),
+    })
+}
+
+#[utoipa::path(
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = Success<RemovedTags>),
+        (status = 401, description = "`X-Admin-Token` is missing", body = ErrorBody),
+        (status = 403, description = "`X-Admin-Token` is wrong or `ADMIN_TOKEN` is unset", body = ErrorBody),
+    )
+)]
+#[post("/tags/cleanup")]
+pub async fn cleanup_orphaned_tags(
+    _admin: Admin,
+    pool: &State<DbPool>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut conn = pool.get()?;
+
+    Ok(match PostRepository::delete_orphaned_tags(&mut conn) {
+        Ok(removed) => Json(serde_json::json!({
+            "success": true,
+            "data": { "removed": removed }
+        })),
+        Err(_) => Json(serde_json::json!({
+            "success": false,
+            "error": "Failed to clean up orphaned tags"
+        })),
+    })
+}
+
+/// Live thread for a post: every comment created on it afterwards is sent as
+/// a JSON text message. Messages from the client are ignored.
+#[utoipa::path(
+    tag = "comments",
+    responses(
+        (status = 101, description = "WebSocket upgrade; each new comment on the post is sent as a JSON text message"),
+        (status = 404, description = "Post not found", body = ErrorBody),
+    )
+)]
+#[get("/posts/<id>/comments/ws")]
+pub fn comment_thread_ws<'r>(
+    ws: WebSocket,
+    pool: &State<DbPool>,
+    events: &'r State<CommentEvents>,
+    mut shutdown: Shutdown,
+    id: Uuid,
+) -> Result<Channel<'r>, ApiError> {
+    let mut conn = pool.get()?;
+    match PostRepository::find_by_id(&mut conn, id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(ApiError::NotFound("Post not found".to_string())),
+        Err(_) => return Err(ApiError::Internal("Failed to fetch post".to_string())),
+    }
+
+    // Subscribed before upgrading so no comment falls between the two. The
+    // subscription lives in the channel, so it is dropped on disconnect.
+    let mut subscription = events.subscribe(id);
+
+    Ok(ws.channel(move |mut stream| {
+        Box::pin(async move {
+            loop {
+                select! {
+                    message = subscription.recv() => match message {
+                        Ok(comment) => {
+                            let json = serde_json::to_string(&comment).unwrap_or_default();
+                            stream.send(Message::Text(json)).await?;
+                        }
+                        Err(RecvError::Closed) => break,
+                        Err(RecvError::Lagged(_)) => continue,
+                    },
+                    message = stream.next() => match message {
+                        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                        Some(Ok(_)) => continue,
+                    },
+                    _ = &mut shutdown => break,
+                }
+            }
+            Ok(())
+        })
+    }))
+}
+
+/// Keep-alive comments stop idle proxies from closing the stream.
+const STREAM_HEARTBEAT: Duration = Duration::from_secs(15);
+
+#[utoipa::path(
+    tag = "feeds",
+    responses(
+        (status = 200, description = "Server-sent `post_created` events, each carrying the new post", content_type = "text/event-stream"),
+    )
+)]
+#[get("/posts/stream")]
+pub fn stream_posts(events: &State<PostEvents>, mut shutdown: Shutdown) -> EventStream![] {
+    // The receiver lives in the stream, so it is dropped as soon as Rocket
+    // notices the client has gone away.
+    let mut receiver = events.subscribe();
+
+    EventStream! {
+        loop {
+            let post = select! {
+                message = receiver.recv() => match message {
+                    Ok(post) => post,
+                    Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(_)) => continue,
+                },
+                _ = &mut shutdown => break,
+            };
+
+            yield Event::json(&post).event("post_created");
+        }
+    }
+    .heartbeat(STREAM_HEARTBEAT)
+}
+
+#[utoipa::path(
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = Success<PaginatedResponse<AuditEntry>>),
+        (status = 401, description = "`X-Admin-Token` is missing", body = ErrorBody),
+        (status = 403, description = "`X-Admin-Token` is wrong or `ADMIN_TOKEN` is unset", body = ErrorBody),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[get("/audit?<entity_id>&<page>&<limit>&<per_page>")]
+pub async fn list_audit(
+    _admin: Admin,
+    pool: &State<DbPool>,
+    entity_id: Option<Uuid>,
+    page: Option<i64>,
+    limit: Option<i64>,
+    per_page: Option<i64>,
+) -> Result<LimitClamped<Json<serde_json::Value>>, ApiError> {
+    let page = requested_page(page)?;
+    let limit = Limit::paginated(limit, per_page, 10, MAX_POSTS_PER_PAGE);
+
+    let mut conn = pool.get()?;
+
+    Ok(limit.respond(
+        match AuditRepository::list(&mut conn, entity_id, page, limit.value) {
+            Ok((entries, mut meta)) => {
+                meta.requested_limit = limit.clamped_from();
+                let response = PaginatedResponse {
+                    records: entries,
+                    meta,
+                };
+                Json(serde_json::json!({
+                    "success": true,
+                    "data": response
+                }))
+            }
+            Err(_) => Json(serde_json::json!({
+                "success": false,
+                "error": "Failed to fetch audit log"
+            })),
+        },
+    ))
+}
+
+/// Rows fetched per query while streaming an export.
+const EXPORT_PAGE_SIZE: i64 = 500;
+
+/// Joins one page of records into a JSON array fragment, preceded by a comma
+/// unless it is the first page.
+fn export_chunk<T: serde::Serialize>(records: &[T], first: bool) -> String {
+    let body = records
+        .iter()
+        .map(|record| serde_json::to_string(record).expect("export records serialize"))
+        .collect::<Vec<_>>()
+        .join(",");
+    if first {
+        body
+    } else {
+        format!(",{body}")
+    }
+}
+
+/// Streams every user and post as one import-compatible JSON document,
+/// paging through the tables so the dataset is never held in memory. Each
+/// page checks out its own connection, so the export is not a consistent
+/// snapshot: writes landing mid-stream may or may not appear, and a post can
+/// reference a user created after the users were read. Errors after the first
+/// page can only end the stream early, which leaves the document unterminated
+/// so clients cannot mistake it for a full export.
+#[utoipa::path(
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Streamed, without the success envelope", body = ExportDocument),
+        (status = 401, description = "`X-Admin-Token` is missing", body = ErrorBody),
+        (status = 403, description = "`X-Admin-Token` is wrong or `ADMIN_TOKEN` is unset", body = ErrorBody),
+    )
+)]
+#[get("/admin/export")]
+pub async fn export_data(
+    _admin: Admin,
+    pool: &State<DbPool>,
+) -> Result<(ContentType, TextStream![String]), ApiError> {
+    let user_page = |pool: &DbPool, after| -> Result<Vec<DumpUser>, ApiError> {
+        let mut conn = pool.get()?;
+        UserRepository::export_page(&mut conn, after, EXPORT_PAGE_SIZE)
+            .map_err(|_| ApiError::Internal("Failed to export data".to_string()))
+    };
+    let post_page = |pool: &DbPool, after| -> Result<Vec<DumpPost>, ApiError> {
+        let mut conn = pool.get()?;
+        PostRepository::export_page(&mut conn, after, EXPORT_PAGE_SIZE)
+            .map_err(|_| ApiError::Internal("Failed to export data".to_string()))
+    };
+
+    let pool = pool.inner().clone();
+    let mut users = user_page(&pool, None)?;
+    let stream = TextStream! {
+        yield format!(r#"{{"version":{EXPORT_VERSION},"users":["#);
+        let mut first = true;
+        while let Some(last) = users.last() {
+            let after = Some(last.id);
+            yield export_chunk(&users, first);
+            first = false;
+            users = match user_page(&pool, after) { Ok(page) => page, Err(_) => return };
+        }
+
+        yield r#"],"posts":["#.to_string();
+        let mut posts = match post_page(&pool, None) { Ok(page) => page, Err(_) => return };
+        let mut first = true;
+        while let Some(last) = posts.last() {
+            let after = Some(last.id);
+            yield export_chunk(&posts, first);
+            first = false;
+            posts = match post_page(&pool, after) { Ok(page) => page, Err(_) => return };
+        }
+        yield "]}".to_string();
+    };
+
+    Ok((ContentType::JSON, stream))
+}
+
+/// Streams every post as newline-delimited JSON, one `PostWithUserAndTags`
+/// per line in id order, paging through the table by keyset like
+/// `export_data`. Each page checks a connection out of the pool only while
+/// it is read, so a slow reader never holds one between chunks. The first
+/// page is read before responding, so a busy pool is still a 503; an error
+/// after that ends the stream early at a line boundary.
+#[utoipa::path(
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "One post per line, without the success envelope", body = PostWithUserAndTags, content_type = "application/x-ndjson"),
+        (status = 401, description = "`X-Admin-Token` is missing", body = ErrorBody),
+        (status = 403, description = "`X-Admin-Token` is wrong or `ADMIN_TOKEN` is unset", body = ErrorBody),
+    )
+)]
+#[get("/posts/export")]
+pub async fn export_posts(
+    _admin: Admin,
+    pool: &State<DbPool>,
+) -> Result<(ContentType, TextStream![String]), ApiError> {
+    let export_page = |pool: &DbPool, after| -> Result<Vec<PostWithUserAndTags>, ApiError> {
+        let mut conn = pool.get()?;
+        PostRepository::export_page_with_user_and_tags(&mut conn, after, EXPORT_PAGE_SIZE)
+            .map_err(|_| ApiError::Internal("Failed to export posts".to_string()))
+    };
+    let pool = pool.inner().clone();
+    let mut page = export_page(&pool, None)?;
+
+    let stream = TextStream! {
+        while let Some(last) = page.last() {
+            let after = Some(last.id);
+            yield ndjson_chunk(&page);
+            page = match export_page(&pool, after) {
+                Ok(page) => page,
+                Err(_) => return,
+            };
+        }
+    };
+
+    Ok((ContentType::new("application", "x-ndjson"), stream))
+}
+
+/// One record per line, each line ending in a newline.
+fn ndjson_chunk<T: serde::Serialize>(records: &[T]) -> String {
+    records
+        .iter()
+        .map(|record| serde_json::to_string(record).expect("export records serialize") + "\n")
+        .collect()
+}
+
+#[utoipa::path(
+    tag = "admin",
+    request_body = ImportRequest,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "With `?dry_run=true`, `data` is an `ImportPreview` instead", body = Success<ImportSummary>),
+        (status = 401, description = "`X-Admin-Token` is missing", body = ErrorBody),
+        (status = 403, description = "`X-Admin-Token` is wrong or `ADMIN_TOKEN` is unset", body = ErrorBody),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[post("/admin/import?<dry_run>", data = "<request>")]
+pub async fn import_data(
+    _admin: Admin,
+    pool: &State<DbPool>,
+    config: &State<Config>,
+    dry_run: Option<bool>,
+    request: Json<ImportRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut conn = pool.get()?;
+
+    if let Some(version) = request.version.filter(|v| *v != EXPORT_VERSION) {
+        return Err(ApiError::field(
+            "version",
+            format!("unsupported version {version}, expected {EXPORT_VERSION}"),
+        ));
+    }
+
+    if dry_run.unwrap_or(false) {
+        return preview_import(&mut conn, &request, config.max_tags_per_post).map(|preview| {
+            Json(serde_json::json!({
+                "success": true,
+                "data": preview
+            }))
+        });
+    }
+
+    request
+        .validate(config.max_tags_per_post)
+        .map_err(ApiError::Validation)?;
+
+    match ImportRepository::import(&mut conn, &request) {
+        Ok(summary) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": summary
+        }))),
+        // Constraint violations point at bad rows in the document.
+        Err(diesel::result::Error::DatabaseError(_, info)) if info.constraint_name().is_some() => {
+            Err(ApiError::field("import", info.message()))
+        }
+        Err(_) => Err(ApiError::Internal("Failed to import data".to_string())),
+    }
+}
+
+/// Validates every item and, when they all pass, runs the import in a
+/// rolled-back transaction to catch what only the database would reject.
+fn preview_import(
+    conn: &mut PgConnection,
+    request: &ImportRequest,
+    max_tags: usize,
+) -> Result<ImportPreview, ApiError> {
+    let (user_actions, post_actions) = ImportRepository::plan(conn, request)
+        .map_err(|_| ApiError::Internal("Failed to preview import".to_string()))?;
+
+    let users: Vec<ImportItemResult> = request
+        .users
+        .iter()
+        .zip(user_actions)
+        .enumerate()
+        .map(|(index, (user, action))| ImportItemResult {
+            index,
+            id: user.id,
+            action,
+            errors: user.validate().err().unwrap_or_default(),
+            warnings: Vec::new(),
+        })
+        .collect();
+    let posts: Vec<ImportItemResult> = request
+        .posts
+        .iter()
+        .zip(post_actions)
+        .enumerate()
+        .map(|(index, (post, action))| {
+            let (errors, warnings) = match post.validate(max_tags) {
+                Ok(warnings) => (FieldErrors::new(), warnings),
+                Err(errors) => (errors, Vec::new()),
+            };
+            ImportItemResult {
+                index,
+                id: post.id,
+                action,
+                errors,
+                warnings,
+            }
+        })
+        .collect();
+
+    let valid = users
+        .iter()
+        .chain(&posts)
+        .all(|item| item.errors.is_empty());
+    let (summary, error) = if valid {
+        match ImportRepository::dry_run(conn, request) {
+            Ok(summary) => (Some(summary), None),
+            Err(diesel::result::Error::DatabaseError(_, info)) => {
+                (None, Some(info.message().to_string()))
+            }
+            Err(_) => return Err(ApiError::Internal("Failed to preview import".to_string())),
+        }
+    } else {
+        (None, None)
+    };
+
+    Ok(ImportPreview {
+        dry_run: true,
+        valid: valid && error.is_none(),
+        users,
+        posts,
+        summary,
+        error,
+    })
+}
+
+#[utoipa::path(
+    tag = "favorites",
+    security(("user_id" = [])),
+    responses(
+        (status = 200, body = Success<Favorite>),
+        (status = 401, description = "`X-User-Id` is missing or names no user", body = ErrorBody),
+        (status = 404, description = "Post not found", body = ErrorBody),
+    )
+)]
+#[post("/posts/<id>/favorite")]
+pub async fn add_favorite(
+    _limit: WriteLimit,
+    pool: &State<DbPool>,
+    user: AuthUser,
+    id: Uuid,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut conn = pool.get()?;
+
+    match PostRepository::find_by_id(&mut conn, id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(ApiError::NotFound("Post not found".to_string())),
+        Err(_) => return Err(ApiError::Internal("Failed to favorite post".to_string())),
+    }
+
+    match FavoriteRepository::add(&mut conn, user.0.id, id) {
+        Ok(_) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": { "post_id": id, "favorited": true }
+        }))),
+        Err(_) => Err(ApiError::Internal("Failed to favorite post".to_string())),
+    }
+}
+
+#[utoipa::path(
+    tag = "favorites",
+    security(("user_id" = [])),
+    responses(
+        (status = 200, body = Success<Favorite>),
+        (status = 401, description = "`X-User-Id` is missing or names no user", body = ErrorBody),
+    )
+)]
+#[delete("/posts/<id>/favorite")]
+pub async fn remove_favorite(
+    _limit: WriteLimit,
+    pool: &State<DbPool>,
+    user: AuthUser,
+    id: Uuid,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut conn = pool.get()?;
+
+    match FavoriteRepository::remove(&mut conn, user.0.id, id) {
+        Ok(_) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": { "post_id": id, "favorited": false }
+        }))),
+        Err(_) => Err(ApiError::Internal("Failed to unfavorite post".to_string())),
+    }
+}
+
+#[utoipa::path(
+    tag = "favorites",
+    responses(
+        (status = 200, body = Success<PaginatedResponse<PostWithUserAndTags>>),
+        (status = 404, description = "User not found", body = ErrorBody),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[get("/users/<id>/favorites?<page>&<limit>&<per_page>")]
+pub async fn list_favorites(
+    pool: &State<DbPool>,
+    settings: &State<Settings>,
+    id: Uuid,
+    page: Option<i64>,
+    limit: Option<i64>,
+    per_page: Option<i64>,
+) -> Result<LimitClamped<Json<serde_json::Value>>, ApiError> {
+    let page = requested_page(page)?;
+    let limit = Limit::paginated(
+        limit,
+        per_page,
+        settings.posts_per_page(),
+        MAX_POSTS_PER_PAGE,
+    );
+
+    let mut conn = pool.get()?;
+
+    match UserRepository::get(&mut conn, id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(ApiError::NotFound("User not found".to_string())),
+        Err(_) => return Err(ApiError::Internal("Failed to fetch favorites".to_string())),
+    }
+
+    match FavoriteRepository::list_for_user(&mut conn, id, page, limit.value) {
+        Ok((posts, mut meta)) => {
+            meta.requested_limit = limit.clamped_from();
+            Ok(limit.respond(Json(serde_json::json!({
+                "success": true,
+                "data": PaginatedResponse {
+                    records: posts,
+                    meta,
+                }
+            }))))
+        }
+        Err(_) => Err(ApiError::Internal("Failed to fetch favorites".to_string())),
+    }
+}
+
+/// Upper bounds for a single `POST /dev/seed` call.
+#[cfg(feature = "seed")]
+const MAX_SEED_USERS: usize = 1_000;
+#[cfg(feature = "seed")]
+const MAX_SEED_POSTS: usize = 10_000;
+
+#[cfg(feature = "seed")]
+#[post("/dev/seed?<users>&<posts>")]
+pub async fn dev_seed(
+    pool: &State<DbPool>,
+    config: &State<Config>,
+    users: Option<usize>,
+    posts: Option<usize>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let users = users.unwrap_or(10);
+    let posts = posts.unwrap_or(50);
+
+    if users > MAX_SEED_USERS {
+        return Err(ApiError::field(
+            "users",
+            format!("must be at most {MAX_SEED_USERS}"),
+        ));
+    }
+    if posts > MAX_SEED_POSTS {
+        return Err(ApiError::field(
+            "posts",
+            format!("must be at most {MAX_SEED_POSTS}"),
+        ));
+    }
+    if users == 0 && posts > 0 {
+        return Err(ApiError::field(
+            "users",
+            "must be at least 1 when generating posts",
+        ));
+    }
+
+    let mut conn = pool.get()?;
+
+    match crate::seed::generate(&mut conn, users, posts, config.id_version) {
+        Ok(summary) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": summary
+        }))),
+        Err(_) => Err(ApiError::Internal("Failed to seed data".to_string())),
+    }
+}
+
+#[utoipa::path(
+    tag = "admin",
+    request_body = NewWebhook,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = Success<Webhook>),
+        (status = 401, description = "`X-Admin-Token` is missing", body = ErrorBody),
+        (status = 403, description = "`X-Admin-Token` is wrong or `ADMIN_TOKEN` is unset", body = ErrorBody),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[post("/admin/webhooks", data = "<webhook>")]
+pub async fn create_webhook(
+    _admin: Admin,
+    pool: &State<DbPool>,
+    webhook: Json<NewWebhook>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let webhook = NewWebhook {
+        url: webhook.url.trim().to_string(),
+        event: webhook.event.trim().to_string(),
+        secret: webhook.secret.clone(),
+    };
+    webhook.validate().map_err(ApiError::Validation)?;
+
+    let mut conn = pool.get()?;
+
+    match WebhookRepository::create(&mut conn, webhook) {
+        Ok(webhook) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": webhook
+        }))),
+        Err(_) => Err(ApiError::Internal("Failed to create webhook".to_string())),
+    }
+}
+
+#[utoipa::path(
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = Success<Vec<Webhook>>),
+        (status = 401, description = "`X-Admin-Token` is missing", body = ErrorBody),
+        (status = 403, description = "`X-Admin-Token` is wrong or `ADMIN_TOKEN` is unset", body = ErrorBody),
+    )
+)]
+#[get("/admin/webhooks")]
+pub async fn list_webhooks(
+    _admin: Admin,
+    pool: &State<DbPool>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut conn = pool.get()?;
+
+    match WebhookRepository::list(&mut conn) {
+        Ok(webhooks) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": webhooks
+        }))),
+        Err(_) => Err(ApiError::Internal("Failed to fetch webhooks".to_string())),
+    }
+}
+
+#[utoipa::path(
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = Success<Setting>),
+        (status = 401, description = "`X-Admin-Token` is missing", body = ErrorBody),
+        (status = 403, description = "`X-Admin-Token` is wrong or `ADMIN_TOKEN` is unset", body = ErrorBody),
+        (status = 404, description = "Setting not found", body = ErrorBody),
+    )
+)]
+#[get("/settings/<key>")]
+pub async fn get_setting(
+    _admin: Admin,
+    pool: &State<DbPool>,
+    key: &str,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut conn = pool.get()?;
+
+    match SettingsRepository::get(&mut conn, key) {
+        Ok(Some(setting)) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": setting
+        }))),
+        Ok(None) => Err(ApiError::NotFound("Setting not found".to_string())),
+        Err(_) => Err(ApiError::Internal("Failed to fetch setting".to_string())),
+    }
+}
+
+#[utoipa::path(
+    tag = "admin",
+    request_body = SettingValue,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = Success<Setting>),
+        (status = 401, description = "`X-Admin-Token` is missing", body = ErrorBody),
+        (status = 403, description = "`X-Admin-Token` is wrong or `ADMIN_TOKEN` is unset", body = ErrorBody),
+        (status = 422, description = "Invalid request", body = ValidationErrorBody),
+    )
+)]
+#[put("/settings/<key>", data = "<setting>")]
+pub async fn put_setting(
+    _admin: Admin,
+    pool: &State<DbPool>,
+    settings: &State<Settings>,
+    key: &str,
+    setting: Json<SettingValue>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let value = setting.value.trim();
+    settings::validate(key, value).map_err(|(field, message)| ApiError::field(field, message))?;
+
+    let mut conn = pool.get()?;
+
+    match SettingsRepository::set(&mut conn, key, value) {
+        Ok(setting) => {
+            settings.insert(&setting.key, &setting.value);
+            Ok(Json(serde_json::json!({
+                "success": true,
+                "data": setting
+            })))
+        }
+        Err(_) => Err(ApiError::Internal("Failed to save setting".to_string())),
     }
 }
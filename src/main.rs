@@ -1,33 +1,225 @@
 #[macro_use]
 extern crate rocket;
 
+#[cfg(test)]
+mod api_tests;
+mod auth;
+mod author_cache;
+mod config;
 mod db;
+mod errors;
+mod fairings;
+mod feed;
+mod graphql;
+mod guards;
 mod handlers;
 mod models;
+mod notifications;
+mod openapi;
+mod rate_limit;
 mod repository;
+mod responders;
+mod sample_data;
 mod schema;
+#[cfg(feature = "seed")]
+mod seed;
+mod settings;
+mod webhooks;
 
-use crate::db::establish_connection;
+use crate::config::Config;
+use crate::db::{database_url, establish_connection, DbPool};
+use crate::models::IdVersion;
+use crate::notifications::PostEvents;
 use rocket::fairing::AdHoc;
+use rocket::{Build, Rocket};
+use std::time::Instant;
 
-#[launch]
-fn rocket() -> _ {
-    let pool = establish_connection();
+#[rocket::main]
+async fn main() {
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("❌ {e}");
+            std::process::exit(1);
+        }
+    };
+    if config.site_url.is_none() {
+        eprintln!("⚠️  SITE_URL is unset, so the RSS feed is not served");
+    }
+    let started = Instant::now();
+    let pool = match establish_connection(&config) {
+        Ok(pool) => {
+            println!(
+                "🔥 Opened {} database connections in {:.2?}",
+                pool.state().connections,
+                started.elapsed()
+            );
+            pool
+        }
+        Err(e) => {
+            eprintln!("❌ {e}");
+            std::process::exit(1);
+        }
+    };
 
-    rocket::build()
+    if std::env::args().any(|arg| arg == "--seed") {
+        seed(&pool, config.id_version);
+        return;
+    }
+
+    if let Err(e) = rocket(config, pool).launch().await {
+        eprintln!("Rocket failed to launch: {}", e.pretty_print());
+        std::process::exit(1);
+    }
+}
+
+fn seed(pool: &DbPool, ids: IdVersion) {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool.");
+
+    match sample_data::seed(&mut conn, ids) {
+        Ok(summary) => println!(
+            "🌱 Seeded {} users, {} posts and {} tags",
+            summary.users, summary.posts, summary.tags
+        ),
+        Err(e) => {
+            eprintln!("Failed to seed sample data: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn rocket(config: Config, pool: DbPool) -> Rocket<Build> {
+    let base_path = config.api_base_path.clone();
+    let serve_feed = config.site_url.is_some();
+
+    let schema = graphql::schema(pool.clone(), &config);
+    // On top of Rocket.toml, which can still set the other limits.
+    let figment = rocket::Config::figment().merge(("limits.json", config.max_json_body_bytes));
+
+    let rocket = rocket::custom(figment)
         .manage(pool)
+        .manage(schema)
+        .manage(PostEvents::new())
+        .manage(notifications::CommentEvents::new())
+        .manage(webhooks::Webhooks::new())
+        .manage(settings::Settings::new(&config))
+        .manage(author_cache::AuthorCache::from_config(&config))
+        .manage(rate_limit::RateLimiter::from_config(&config))
+        .manage(config)
+        .attach(fairings::RequestLog)
+        .attach(fairings::Cors)
+        .attach(fairings::KeyCase)
+        .attach(fairings::Compression)
         .attach(AdHoc::on_liftoff("Database Config", |_rocket| {
             Box::pin(async move {
                 println!("🚀 Blog API server starting up...");
                 println!("📊 Database connection initialized");
             })
         }))
+        .attach(AdHoc::on_liftoff("Settings", |rocket| {
+            Box::pin(async move {
+                if let (Some(settings), Some(pool)) = (
+                    rocket.state::<settings::Settings>(),
+                    rocket.state::<DbPool>(),
+                ) {
+                    match settings.load(pool) {
+                        Ok(()) => println!("⚙️  Settings loaded"),
+                        Err(e) => eprintln!("Failed to load settings, using defaults: {e}"),
+                    }
+                }
+            })
+        }))
+        .attach(AdHoc::on_liftoff("Post Notifications", |rocket| {
+            Box::pin(async move {
+                if let (Some(events), Some(config)) =
+                    (rocket.state::<PostEvents>(), rocket.state::<Config>())
+                {
+                    events.spawn_listener(database_url(config));
+                    println!("📣 Listening for new posts");
+                }
+            })
+        }))
         .mount(
-            "/api",
+            base_path.as_str(),
             routes![
                 handlers::create_user,
+                handlers::get_user,
+                handlers::username_available,
+                handlers::onboard,
+                handlers::batch_users,
                 handlers::create_post,
                 handlers::list_posts,
+                handlers::count_posts,
+                handlers::post_archive,
+                handlers::popular_posts,
+                handlers::latest_per_author,
+                handlers::stream_posts,
+                handlers::export_posts,
+                handlers::batch_posts,
+                handlers::get_post,
+                handlers::get_post_by_slug,
+                handlers::update_post,
+                handlers::toggle_featured,
+                handlers::reassign_post,
+                handlers::delete_user_posts,
+                handlers::set_post_tags,
+                handlers::add_attachment,
+                handlers::create_comment,
+                handlers::comment_tree,
+                handlers::comment_thread_ws,
+                handlers::update_comment,
+                handlers::delete_comment,
+                handlers::list_tags,
+                handlers::tag_posts,
+                handlers::suggest_tags,
+                handlers::search_posts,
+                handlers::orphaned_tags,
+                handlers::cleanup_orphaned_tags,
+                handlers::rename_tag,
+                handlers::delete_tag,
+                handlers::stats,
+                handlers::list_audit,
+                handlers::import_data,
+                handlers::export_data,
+                handlers::create_webhook,
+                handlers::list_webhooks,
+                handlers::get_setting,
+                handlers::put_setting,
+                handlers::add_favorite,
+                handlers::remove_favorite,
+                handlers::list_favorites,
+                graphql::graphql,
+            ],
+        )
+        .mount("/", routes![openapi::openapi_json, openapi::docs])
+        .register(
+            base_path.as_str(),
+            catchers![
+                errors::payload_too_large,
+                errors::unsupported_media_type,
+                errors::too_many_requests
             ],
         )
+        // Registered at the root so paths outside the API also get JSON.
+        .register(
+            "/",
+            catchers![
+                errors::not_found,
+                errors::internal_error,
+                errors::service_unavailable,
+                errors::default_catcher
+            ],
+        );
+
+    // Feed links have to be absolute, which needs `SITE_URL`.
+    let rocket = if serve_feed {
+        rocket.mount(base_path.as_str(), routes![handlers::rss_feed])
+    } else {
+        rocket
+    };
+
+    #[cfg(feature = "seed")]
+    let rocket = rocket.mount(base_path.as_str(), routes![handlers::dev_seed]);
+
+    rocket
 }
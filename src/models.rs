@@ -1,21 +1,88 @@
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
-use crate::schema::{posts, posts_tags, users};
+use crate::schema::{
+    audit_log, comments, favorites, post_attachments, posts, posts_tags, settings, users, webhooks,
+};
 
-#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Identifiable)]
+/// Serializes timestamps as RFC 3339 in UTC with a `Z` offset and always six
+/// fractional digits (Postgres' precision), rather than chrono's default,
+/// which drops trailing zeros and so varies in length.
+pub mod timestamp {
+    use chrono::{DateTime, SecondsFormat, Utc};
+    use serde::Serializer;
+
+    pub fn format(value: &DateTime<Utc>) -> String {
+        value.to_rfc3339_opts(SecondsFormat::Micros, true)
+    }
+
+    pub fn serialize<S: Serializer>(
+        value: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format(value))
+    }
+
+    pub fn serialize_option<S: Serializer>(
+        value: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+/// Which UUID version new users and posts get as their id. v7 ids start
+/// with a millisecond timestamp, so they sort (and index) in creation order;
+/// v4 ids are fully random.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IdVersion {
+    #[default]
+    V4,
+    V7,
+}
+
+impl IdVersion {
+    pub fn new_id(self) -> Uuid {
+        match self {
+            IdVersion::V4 => Uuid::new_v4(),
+            IdVersion::V7 => Uuid::now_v7(),
+        }
+    }
+}
+
+impl FromStr for IdVersion {
+    type Err = String;
+
+    /// `v4` or `v7`, in any case.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "v4" => Ok(IdVersion::V4),
+            "v7" => Ok(IdVersion::V7),
+            _ => Err("expected v4 or v7".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Identifiable, ToSchema)]
 #[diesel(table_name = users)]
 pub struct User {
     pub id: Uuid,
     pub username: String,
     pub first_name: String,
     pub last_name: String,
+    #[serde(serialize_with = "timestamp::serialize")]
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Insertable)]
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable, ToSchema)]
 #[diesel(table_name = users)]
 pub struct NewUser {
     pub username: String,
@@ -23,7 +90,71 @@ pub struct NewUser {
     pub last_name: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Identifiable, Associations)]
+impl NewUser {
+    /// Trims every field and collapses runs of whitespace inside them.
+    pub fn sanitize(&mut self) {
+        self.username = collapse_whitespace(&self.username);
+        self.first_name = collapse_whitespace(&self.first_name);
+        self.last_name = collapse_whitespace(&self.last_name);
+    }
+
+    /// Expects a sanitized user. The last name may be left empty.
+    pub fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        check_username(&mut errors, &self.username);
+        if self.first_name.is_empty() {
+            add_field_error(&mut errors, "first_name", "must not be empty");
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Checks a sanitized username the same way `NewUser::validate` does.
+pub fn validate_username(username: &str) -> Result<(), FieldErrors> {
+    let mut errors = FieldErrors::new();
+    check_username(&mut errors, username);
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn check_username(errors: &mut FieldErrors, username: &str) {
+    if username.is_empty() {
+        add_field_error(errors, "username", "must not be empty");
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserWithStats {
+    #[serde(flatten)]
+    pub user: User,
+    pub post_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BatchIds {
+    pub ids: Vec<Uuid>,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Queryable,
+    Selectable,
+    Identifiable,
+    Associations,
+    ToSchema,
+)]
 #[diesel(belongs_to(User, foreign_key = created_by))]
 #[diesel(table_name = posts)]
 pub struct Post {
@@ -31,7 +162,10 @@ pub struct Post {
     pub title: String,
     pub body: String,
     pub created_by: Uuid,
+    #[serde(serialize_with = "timestamp::serialize")]
     pub created_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+    pub featured: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Insertable)]
@@ -42,7 +176,7 @@ pub struct NewPost {
     pub created_by: Uuid,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct NewPostWithTags {
     pub title: String,
     pub body: String,
@@ -50,7 +184,322 @@ pub struct NewPostWithTags {
     pub tags: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Identifiable, Associations)]
+/// Validation messages keyed by the request field they refer to.
+pub type FieldErrors = HashMap<String, Vec<String>>;
+
+pub fn add_field_error(errors: &mut FieldErrors, field: &str, message: impl Into<String>) {
+    errors
+        .entry(field.to_string())
+        .or_default()
+        .push(message.into());
+}
+
+pub const MAX_TITLE_LENGTH: usize = 200;
+/// Also enforced by the `posts_tags_tag_length` check constraint, which
+/// must be changed with it.
+pub const MAX_TAG_LENGTH: usize = 50;
+/// Tags longer than this are accepted but reported back as a warning.
+pub const TAG_LENGTH_WARNING: usize = 30;
+pub const DEFAULT_MAX_TAGS_PER_POST: usize = 20;
+pub const DEFAULT_MIN_SEARCH_LENGTH: usize = 2;
+pub const MAX_COMMENT_LENGTH: usize = 5000;
+pub const DEFAULT_POPULAR_DAYS: i32 = 7;
+pub const MAX_POPULAR_LIMIT: i64 = 50;
+pub const MAX_LATEST_PER_AUTHOR_LIMIT: i64 = 100;
+pub const MAX_SEARCH_RESULTS_LIMIT: i64 = 20;
+pub const MAX_ATTACHMENT_URL_LENGTH: usize = 2048;
+/// Content types an attachment may declare.
+pub const ATTACHMENT_CONTENT_TYPES: &[&str] =
+    &["image/gif", "image/jpeg", "image/png", "image/webp"];
+
+/// Trims `value` and replaces each run of whitespace inside it with a single
+/// space. Meant for one-line fields such as titles and names; bodies keep
+/// their line breaks and are only trimmed.
+pub fn collapse_whitespace(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// URL-friendly form of a title: lowercase ASCII letters and digits with
+/// runs of anything else turned into single hyphens, e.g.
+/// `"Hello, World!"` becomes `"hello-world"`.
+pub fn slugify(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Trims and lowercases tags, dropping blanks and duplicates while keeping
+/// the order in which they were first given.
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+/// Checks a normalized tag list against the per-post cap and tag length.
+fn validate_tags(errors: &mut FieldErrors, tags: &[String], max_tags: usize) {
+    if tags.len() > max_tags {
+        add_field_error(
+            errors,
+            "tags",
+            format!("must contain at most {max_tags} entries"),
+        );
+    }
+    if tags.iter().any(|tag| tag.chars().count() > MAX_TAG_LENGTH) {
+        add_field_error(
+            errors,
+            "tags",
+            format!("must each be at most {MAX_TAG_LENGTH} characters"),
+        );
+    }
+}
+
+/// Soft checks on tags that are allowed through but probably unintended:
+/// embedded whitespace and unusually long names.
+fn tag_warnings(tags: &[String]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for tag in tags {
+        if tag.chars().any(char::is_whitespace) {
+            warnings.push(format!("tag '{tag}' contains spaces"));
+        }
+        let length = tag.chars().count();
+        if length > TAG_LENGTH_WARNING && length <= MAX_TAG_LENGTH {
+            warnings.push(format!(
+                "tag '{tag}' is unusually long ({length} characters)"
+            ));
+        }
+    }
+    warnings
+}
+
+impl NewPostWithTags {
+    /// Collapses whitespace in the title, trims the body and normalizes the
+    /// tags, so blank fields fail validation instead of being stored.
+    pub fn sanitize(&mut self) {
+        self.title = collapse_whitespace(&self.title);
+        self.body = self.body.trim().to_string();
+        self.tags = normalize_tags(&self.tags);
+    }
+
+    /// Checks the post before any database work, collecting messages for
+    /// each offending field. Tags are expected to be normalized already so
+    /// duplicates don't count towards `max_tags`. On success, returns any
+    /// non-fatal warnings about the tags.
+    pub fn validate(&self, max_tags: usize) -> Result<Vec<String>, FieldErrors> {
+        validate_post(&self.title, &self.body, &self.tags, max_tags)
+    }
+}
+
+/// Body of `POST /onboard`: a new user and their first post, which is
+/// created by that user.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OnboardRequest {
+    pub user: NewUser,
+    pub post: OnboardPost,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OnboardPost {
+    pub title: String,
+    pub body: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl OnboardPost {
+    pub fn into_new_post(self, created_by: Uuid) -> NewPostWithTags {
+        NewPostWithTags {
+            title: self.title,
+            body: self.body,
+            created_by,
+            tags: self.tags,
+        }
+    }
+}
+
+impl OnboardRequest {
+    pub fn sanitize(&mut self) {
+        self.user.sanitize();
+        self.post.title = collapse_whitespace(&self.post.title);
+        self.post.body = self.post.body.trim().to_string();
+        self.post.tags = normalize_tags(&self.post.tags);
+    }
+
+    /// Validates both parts, keying errors as `user.username`,
+    /// `post.title` and so on. On success, returns the post's tag warnings.
+    pub fn validate(&self, max_tags: usize) -> Result<Vec<String>, FieldErrors> {
+        let mut errors = FieldErrors::new();
+        let mut collect = |prefix: &str, part_errors: FieldErrors| {
+            for (field, messages) in part_errors {
+                errors.insert(format!("{prefix}.{field}"), messages);
+            }
+        };
+
+        if let Err(user_errors) = self.user.validate() {
+            collect("user", user_errors);
+        }
+        let warnings =
+            match validate_post(&self.post.title, &self.post.body, &self.post.tags, max_tags) {
+                Ok(warnings) => warnings,
+                Err(post_errors) => {
+                    collect("post", post_errors);
+                    Vec::new()
+                }
+            };
+
+        if errors.is_empty() {
+            Ok(warnings)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// The user and post created by `POST /onboard`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Onboarded {
+    pub user: User,
+    pub post: PostWithUserAndTags,
+}
+
+/// Shared checks for a new post's title, body and (normalized) tags.
+fn validate_post(
+    title: &str,
+    body: &str,
+    tags: &[String],
+    max_tags: usize,
+) -> Result<Vec<String>, FieldErrors> {
+    let mut errors = FieldErrors::new();
+
+    let title = title.trim();
+    if title.is_empty() {
+        add_field_error(&mut errors, "title", "must not be empty");
+    } else if title.chars().count() > MAX_TITLE_LENGTH {
+        add_field_error(
+            &mut errors,
+            "title",
+            format!("must be at most {MAX_TITLE_LENGTH} characters"),
+        );
+    }
+
+    if body.trim().is_empty() {
+        add_field_error(&mut errors, "body", "must not be empty");
+    }
+
+    validate_tags(&mut errors, tags, max_tags);
+
+    if errors.is_empty() {
+        Ok(tag_warnings(tags))
+    } else {
+        Err(errors)
+    }
+}
+
+/// Body of `PUT /posts/<id>/tags`: the complete new tag list.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SetTags {
+    pub tags: Vec<String>,
+}
+
+impl SetTags {
+    /// Tags are expected to be normalized already, as for new posts, and
+    /// the same warnings are returned on success.
+    pub fn validate(&self, max_tags: usize) -> Result<Vec<String>, FieldErrors> {
+        let mut errors = FieldErrors::new();
+        validate_tags(&mut errors, &self.tags, max_tags);
+
+        if errors.is_empty() {
+            Ok(tag_warnings(&self.tags))
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Result of replacing a post's tags: the new list and what changed.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TagChanges {
+    pub tags: Vec<String>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// Body of `PATCH /posts/<id>/author`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Reassign {
+    pub author_id: Uuid,
+}
+
+/// Outcome of moving a post to another author.
+#[derive(Debug)]
+pub enum Reassignment {
+    Reassigned(Post),
+    PostNotFound,
+    AuthorNotFound,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdatePost {
+    pub title: Option<String>,
+    pub body: Option<String>,
+}
+
+impl UpdatePost {
+    pub fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+
+        if self.title.is_none() && self.body.is_none() {
+            add_field_error(
+                &mut errors,
+                "request",
+                "at least one of title or body must be provided",
+            );
+        }
+        if let Some(title) = &self.title {
+            let title = title.trim();
+            if title.is_empty() {
+                add_field_error(&mut errors, "title", "must not be empty");
+            } else if title.chars().count() > MAX_TITLE_LENGTH {
+                add_field_error(
+                    &mut errors,
+                    "title",
+                    format!("must be at most {MAX_TITLE_LENGTH} characters"),
+                );
+            }
+        }
+        if let Some(body) = &self.body {
+            if body.trim().is_empty() {
+                add_field_error(&mut errors, "body", "must not be empty");
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[derive(Debug, AsChangeset)]
+#[diesel(table_name = posts)]
+pub struct PostChanges {
+    pub title: Option<String>,
+    pub body: Option<String>,
+    pub updated_by: Option<Uuid>,
+}
+
+#[derive(
+    Debug, Serialize, Deserialize, Queryable, Selectable, Identifiable, Associations, ToSchema,
+)]
 #[diesel(belongs_to(Post, foreign_key = fk_post_id))]
 #[diesel(table_name = posts_tags)]
 #[diesel(primary_key(fk_post_id, tag))]
@@ -66,7 +515,47 @@ pub struct NewPostTag {
     pub tag: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A search hit for typeahead dropdowns: just enough to show and link to
+/// the post.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PostSearchResult {
+    pub id: Uuid,
+    pub title: String,
+    pub slug: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, ToSchema)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: i64,
+}
+
+/// A tag in `GET /tags?links=true`, with links to its posts and its feed.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TagWithLinks {
+    #[serde(flatten)]
+    pub tag: TagCount,
+    pub posts_url: String,
+    /// Absent when `SITE_URL` is unset, since the feed is not served then.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feed_url: Option<String>,
+}
+
+/// Number of posts created in one calendar month (UTC).
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ArchiveMonth {
+    pub year: i32,
+    pub month: i32,
+    pub count: i64,
+}
+
+impl From<(i32, i32, i64)> for ArchiveMonth {
+    fn from((year, month, count): (i32, i32, i64)) -> Self {
+        ArchiveMonth { year, month, count }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CreatedBy {
     pub user_id: Uuid,
     pub username: String,
@@ -74,23 +563,881 @@ pub struct CreatedBy {
     pub last_name: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct PostWithUserAndTags {
     pub id: Uuid,
     pub title: String,
     pub body: String,
     pub created_by: Option<CreatedBy>,
+    #[serde(serialize_with = "timestamp::serialize")]
+    pub created_at: DateTime<Utc>,
+    #[serde(serialize_with = "timestamp::serialize")]
+    pub updated_at: DateTime<Utc>,
+    pub featured: bool,
+    pub view_count: i64,
+    /// Comments that have not been deleted. `list_posts` only counts them
+    /// when asked to with `?with=comment_count`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment_count: Option<i64>,
+    pub tags: Vec<String>,
+}
+
+/// Columns the post list may be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostSortField {
+    CreatedAt,
+    Title,
+}
+
+impl PostSortField {
+    const ALL: [(&'static str, PostSortField); 2] = [
+        ("created_at", PostSortField::CreatedAt),
+        ("title", PostSortField::Title),
+    ];
+
+    pub fn column(self) -> &'static str {
+        match self {
+            PostSortField::CreatedAt => "p.created_at",
+            PostSortField::Title => "p.title",
+        }
+    }
+
+    /// Direction for a column given without a prefix: newest first for
+    /// `created_at`, as `sort=created_at` always meant, and A to Z for
+    /// `title`.
+    fn natural_direction(self) -> SortDirection {
+        match self {
+            PostSortField::CreatedAt => SortDirection::Desc,
+            PostSortField::Title => SortDirection::Asc,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+/// Parsed `sort` parameter: comma-separated columns, each prefixed with `-`
+/// for descending or `+` for ascending, or bare for the column's natural
+/// direction, e.g. `title,+created_at`. Defaults to newest first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostSort(pub Vec<(PostSortField, SortDirection)>);
+
+impl Default for PostSort {
+    fn default() -> Self {
+        PostSort(vec![(PostSortField::CreatedAt, SortDirection::Desc)])
+    }
+}
+
+impl PostSort {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut columns = Vec::new();
+
+        for part in value.split(',').map(str::trim) {
+            let (name, direction) = if let Some(name) = part.strip_prefix('-') {
+                (name, Some(SortDirection::Desc))
+            } else if let Some(name) = part.strip_prefix('+') {
+                (name, Some(SortDirection::Asc))
+            } else {
+                (part, None)
+            };
+            if name.is_empty() {
+                return Err("must not contain empty columns".to_string());
+            }
+
+            let Some((_, field)) = PostSortField::ALL.iter().find(|(n, _)| *n == name) else {
+                let allowed: Vec<&str> = PostSortField::ALL.iter().map(|(n, _)| *n).collect();
+                return Err(format!(
+                    "unknown column '{name}', expected one of: {}",
+                    allowed.join(", ")
+                ));
+            };
+            if columns.iter().any(|(f, _)| f == field) {
+                return Err(format!("column '{name}' is listed more than once"));
+            }
+            columns.push((*field, direction.unwrap_or(field.natural_direction())));
+        }
+
+        Ok(PostSort(columns))
+    }
+}
+
+/// How the `search` term is compared: `contains` (the default) and
+/// `prefix` are case-insensitive `ILIKE` patterns, `exact` is a
+/// case-sensitive `=`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchMatch {
+    Exact,
+    #[default]
+    Contains,
+    Prefix,
+}
+
+impl SearchMatch {
+    const ALL: [(&'static str, SearchMatch); 3] = [
+        ("exact", SearchMatch::Exact),
+        ("contains", SearchMatch::Contains),
+        ("prefix", SearchMatch::Prefix),
+    ];
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        SearchMatch::ALL
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(value))
+            .map(|(_, search_match)| *search_match)
+            .ok_or_else(|| {
+                let allowed: Vec<&str> = SearchMatch::ALL.iter().map(|(n, _)| *n).collect();
+                format!("expected one of: {}", allowed.join(", "))
+            })
+    }
+
+    pub fn operator(self) -> &'static str {
+        match self {
+            SearchMatch::Exact => "=",
+            SearchMatch::Contains | SearchMatch::Prefix => "ILIKE",
+        }
+    }
+
+    /// The value bound for `term` on the right of `operator`.
+    pub fn pattern(self, term: &str) -> String {
+        match self {
+            SearchMatch::Exact => term.to_string(),
+            SearchMatch::Contains => format!("%{}%", escape_like(term)),
+            SearchMatch::Prefix => format!("{}%", escape_like(term)),
+        }
+    }
+}
+
+/// Escapes LIKE wildcards so user input only ever matches literally.
+pub fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[derive(Debug, Default, FromForm, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PostFilters {
+    pub search: Option<String>,
+    /// `exact`, `contains` or `prefix`; see `SearchMatch`.
+    #[field(name = "match")]
+    #[param(rename = "match")]
+    pub match_mode: Option<String>,
+    pub tag: Option<String>,
+    pub author_id: Option<Uuid>,
+    pub featured: Option<bool>,
+    /// Only posts without any tags; `false` is the same as leaving it out.
+    pub untagged: Option<bool>,
+    /// Matched against the author's username, first and last name only.
+    pub author: Option<String>,
+    /// Comma-separated extras to embed in each record. Only
+    /// `comment_count` exists so far.
+    pub with: Option<String>,
+}
+
+impl PostFilters {
+    /// The trimmed search term, or `None` when it is absent or blank.
+    pub fn search_term(&self) -> Option<&str> {
+        self.search
+            .as_deref()
+            .map(str::trim)
+            .filter(|term| !term.is_empty())
+    }
+
+    /// The exact-tag filter normalized the same way tags are on insert, so
+    /// `?tag=Rust` matches the stored `rust`. `None` when absent or blank.
+    pub fn tag_term(&self) -> Option<String> {
+        self.tag
+            .as_deref()
+            .map(|tag| tag.trim().to_lowercase())
+            .filter(|tag| !tag.is_empty())
+    }
+
+    /// The trimmed author name filter, or `None` when absent or blank.
+    pub fn author_term(&self) -> Option<&str> {
+        self.author
+            .as_deref()
+            .map(str::trim)
+            .filter(|term| !term.is_empty())
+    }
+
+    /// The parsed `match` parameter, or `contains` when it is absent or
+    /// invalid (which `validate` reports).
+    pub fn search_match(&self) -> SearchMatch {
+        self.match_mode
+            .as_deref()
+            .and_then(|value| SearchMatch::parse(value).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn untagged_only(&self) -> bool {
+        self.untagged.unwrap_or(false)
+    }
+
+    pub fn with_comment_count(&self) -> bool {
+        self.with_parts().any(|part| part == "comment_count")
+    }
+
+    fn with_parts(&self) -> impl Iterator<Item = &str> {
+        self.with
+            .as_deref()
+            .unwrap_or("")
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+    }
+
+    /// Rejects search and author terms so short they would match nearly
+    /// every post, and a tag filter combined with `untagged`, which could
+    /// never match.
+    pub fn validate(&self, min_search_length: usize) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+
+        for (field, term) in [
+            ("search", self.search_term()),
+            ("author", self.author_term()),
+        ] {
+            if term.is_some_and(|term| term.chars().count() < min_search_length) {
+                add_field_error(
+                    &mut errors,
+                    field,
+                    format!(
+                        "must be at least {min_search_length} characters, or omitted to list all posts"
+                    ),
+                );
+            }
+        }
+        if self.untagged_only() && self.tag_term().is_some() {
+            add_field_error(&mut errors, "tag", "cannot be combined with untagged=true");
+        }
+        if let Some(Err(message)) = self.match_mode.as_deref().map(SearchMatch::parse) {
+            add_field_error(&mut errors, "match", message);
+        }
+        if self.with_parts().any(|part| part != "comment_count") {
+            add_field_error(&mut errors, "with", "expected one of: comment_count");
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdatedBy {
+    pub user_id: Uuid,
+    pub username: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PostDetail {
+    #[serde(flatten)]
+    pub post: PostWithUserAndTags,
+    pub updated_by: Option<UpdatedBy>,
+    pub attachments: Vec<Attachment>,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Queryable,
+    Selectable,
+    Identifiable,
+    Associations,
+    ToSchema,
+)]
+#[diesel(belongs_to(Post, foreign_key = fk_post_id))]
+#[diesel(table_name = post_attachments)]
+pub struct Attachment {
+    pub id: Uuid,
+    #[serde(rename = "post_id")]
+    pub fk_post_id: Uuid,
+    pub url: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    #[serde(serialize_with = "timestamp::serialize")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Body of `POST /posts/<id>/attachments`, registering an externally hosted
+/// file.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NewAttachmentRequest {
+    pub url: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+}
+
+impl NewAttachmentRequest {
+    /// Expects `url` trimmed and `content_type` trimmed and lowercased.
+    pub fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+
+        if self.url.chars().count() > MAX_ATTACHMENT_URL_LENGTH {
+            add_field_error(
+                &mut errors,
+                "url",
+                format!("must be at most {MAX_ATTACHMENT_URL_LENGTH} characters"),
+            );
+        } else if !is_http_url(&self.url) {
+            add_field_error(&mut errors, "url", "must be an absolute http(s) URL");
+        }
+
+        if !ATTACHMENT_CONTENT_TYPES.contains(&self.content_type.as_str()) {
+            add_field_error(
+                &mut errors,
+                "content_type",
+                format!("must be one of: {}", ATTACHMENT_CONTENT_TYPES.join(", ")),
+            );
+        }
+
+        if self.size_bytes <= 0 {
+            add_field_error(&mut errors, "size_bytes", "must be greater than 0");
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// An `http://` or `https://` URL with a host and no whitespace.
+fn is_http_url(url: &str) -> bool {
+    let Some(rest) = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+    else {
+        return false;
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+
+    !host.is_empty() && !url.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = post_attachments)]
+pub struct NewAttachment {
+    pub fk_post_id: Uuid,
+    pub url: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable, ToSchema)]
+#[diesel(table_name = settings)]
+pub struct Setting {
+    pub key: String,
+    pub value: String,
+    #[serde(serialize_with = "timestamp::serialize")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Body of `PUT /settings/<key>`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SettingValue {
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable, Identifiable, ToSchema)]
+#[diesel(table_name = comments)]
+pub struct Comment {
+    pub id: Uuid,
+    #[serde(rename = "post_id")]
+    pub fk_post_id: Uuid,
+    pub author_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub body: String,
+    #[serde(serialize_with = "timestamp::serialize")]
     pub created_at: DateTime<Utc>,
+    #[serde(serialize_with = "timestamp::serialize_option")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Shown in place of a deleted comment's body.
+pub const DELETED_COMMENT_BODY: &str = "[deleted]";
+
+impl Comment {
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = comments)]
+pub struct NewComment {
+    pub fk_post_id: Uuid,
+    pub author_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub body: String,
+}
+
+/// Body of `POST /posts/<id>/comments`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NewCommentRequest {
+    pub body: String,
+    pub parent_id: Option<Uuid>,
+}
+
+fn validate_comment_body(body: &str) -> Result<(), FieldErrors> {
+    let mut errors = FieldErrors::new();
+
+    let body = body.trim();
+    if body.is_empty() {
+        add_field_error(&mut errors, "body", "must not be empty");
+    } else if body.chars().count() > MAX_COMMENT_LENGTH {
+        add_field_error(
+            &mut errors,
+            "body",
+            format!("must be at most {MAX_COMMENT_LENGTH} characters"),
+        );
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+impl NewCommentRequest {
+    pub fn sanitize(&mut self) {
+        self.body = self.body.trim().to_string();
+    }
+
+    pub fn validate(&self) -> Result<(), FieldErrors> {
+        validate_comment_body(&self.body)
+    }
+}
+
+/// Body of `PATCH /comments/<id>`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdateComment {
+    pub body: String,
+}
+
+impl UpdateComment {
+    pub fn sanitize(&mut self) {
+        self.body = self.body.trim().to_string();
+    }
+
+    pub fn validate(&self) -> Result<(), FieldErrors> {
+        validate_comment_body(&self.body)
+    }
+}
+
+/// A comment with its replies, as returned by the comment tree.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CommentNode {
+    #[serde(flatten)]
+    pub comment: Comment,
+    #[schema(no_recursion)]
+    pub replies: Vec<CommentNode>,
+}
+
+/// Nests a post's comments under their parents. Siblings keep the order of
+/// `comments`; a comment whose parent is missing is shown at the top level.
+/// Deleted comments stay in place so their replies keep their thread, but
+/// their body is replaced with `[deleted]`.
+pub fn build_comment_tree(comments: Vec<Comment>) -> Vec<CommentNode> {
+    let ids: std::collections::HashSet<Uuid> = comments.iter().map(|c| c.id).collect();
+    let mut children: HashMap<Option<Uuid>, Vec<Comment>> = HashMap::new();
+    for comment in comments {
+        let parent = comment.parent_id.filter(|parent| ids.contains(parent));
+        children.entry(parent).or_default().push(comment);
+    }
+
+    fn attach(
+        parent: Option<Uuid>,
+        children: &mut HashMap<Option<Uuid>, Vec<Comment>>,
+    ) -> Vec<CommentNode> {
+        children
+            .remove(&parent)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|mut comment| {
+                let replies = attach(Some(comment.id), children);
+                if comment.is_deleted() {
+                    comment.body = DELETED_COMMENT_BODY.to_string();
+                }
+                CommentNode { comment, replies }
+            })
+            .collect()
+    }
+
+    attach(None, &mut children)
+}
+
+/// Event sent to webhooks after a post is created.
+pub const POST_CREATED_EVENT: &str = "post.created";
+/// Events a webhook may subscribe to.
+pub const WEBHOOK_EVENTS: &[&str] = &[POST_CREATED_EVENT];
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable, ToSchema)]
+#[diesel(table_name = webhooks)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub url: String,
+    pub event: String,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    #[serde(serialize_with = "timestamp::serialize")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Insertable, ToSchema)]
+#[diesel(table_name = webhooks)]
+pub struct NewWebhook {
+    pub url: String,
+    pub event: String,
+    pub secret: String,
+}
+
+impl NewWebhook {
+    pub fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+
+        if !is_http_url(&self.url) {
+            add_field_error(&mut errors, "url", "must be an absolute http(s) URL");
+        }
+        if !WEBHOOK_EVENTS.contains(&self.event.as_str()) {
+            add_field_error(
+                &mut errors,
+                "event",
+                format!("must be one of: {}", WEBHOOK_EVENTS.join(", ")),
+            );
+        }
+        if self.secret.is_empty() {
+            add_field_error(&mut errors, "secret", "must not be empty");
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Version of the export/import document layout.
+pub const EXPORT_VERSION: u32 = 1;
+
+/// A user as it appears in an export/import document, keeping its id.
+#[derive(Debug, Serialize, Deserialize, Insertable, AsChangeset, ToSchema)]
+#[diesel(table_name = users)]
+pub struct DumpUser {
+    pub id: Uuid,
+    pub username: String,
+    pub first_name: String,
+    pub last_name: String,
+    #[serde(serialize_with = "timestamp::serialize_option")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl From<User> for DumpUser {
+    fn from(user: User) -> Self {
+        DumpUser {
+            id: user.id,
+            username: user.username,
+            first_name: user.first_name,
+            last_name: user.last_name,
+            created_at: Some(user.created_at),
+        }
+    }
+}
+
+/// A post with its tags as it appears in an export/import document.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DumpPost {
+    pub id: Uuid,
+    pub title: String,
+    pub body: String,
+    pub created_by: Uuid,
+    #[serde(serialize_with = "timestamp::serialize_option")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub featured: bool,
+    #[serde(default)]
     pub tags: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl DumpUser {
+    pub fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        if self.username.trim().is_empty() {
+            add_field_error(&mut errors, "username", "must not be empty");
+        }
+        if self.first_name.trim().is_empty() {
+            add_field_error(&mut errors, "first_name", "must not be empty");
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl DumpPost {
+    /// Same checks as for a new post, against the tags as they will be
+    /// stored.
+    pub fn validate(&self, max_tags: usize) -> Result<Vec<String>, FieldErrors> {
+        validate_post(
+            &self.title,
+            &self.body,
+            &normalize_tags(&self.tags),
+            max_tags,
+        )
+    }
+}
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[diesel(table_name = posts)]
+pub struct PostRecord<'a> {
+    pub id: Uuid,
+    pub title: &'a str,
+    pub body: &'a str,
+    pub created_by: Uuid,
+    pub created_at: Option<DateTime<Utc>>,
+    pub featured: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// Leave rows whose id already exists untouched.
+    #[default]
+    Skip,
+    /// Overwrite rows whose id already exists.
+    Upsert,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ImportRequest {
+    /// Export documents carry their layout version; hand-written ones may omit it.
+    pub version: Option<u32>,
+    #[serde(default)]
+    pub mode: ImportMode,
+    #[serde(default)]
+    pub users: Vec<DumpUser>,
+    #[serde(default)]
+    pub posts: Vec<DumpPost>,
+}
+
+impl ImportRequest {
+    /// Validates every user and post, keying errors by their position in the
+    /// document, e.g. `posts[2].title`.
+    pub fn validate(&self, max_tags: usize) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        let mut collect = |kind: &str, index: usize, item_errors: FieldErrors| {
+            for (field, messages) in item_errors {
+                errors.insert(format!("{kind}[{index}].{field}"), messages);
+            }
+        };
+
+        for (index, user) in self.users.iter().enumerate() {
+            if let Err(item_errors) = user.validate() {
+                collect("users", index, item_errors);
+            }
+        }
+        for (index, post) in self.posts.iter().enumerate() {
+            if let Err(item_errors) = post.validate(max_tags) {
+                collect("posts", index, item_errors);
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// What an import does with one user or post.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportAction {
+    Create,
+    Update,
+    Skip,
+}
+
+impl ImportAction {
+    pub fn for_row(exists: bool, mode: ImportMode) -> Self {
+        match (exists, mode) {
+            (false, _) => ImportAction::Create,
+            (true, ImportMode::Upsert) => ImportAction::Update,
+            (true, ImportMode::Skip) => ImportAction::Skip,
+        }
+    }
+}
+
+/// One user or post in a dry-run import: the id it would be stored under,
+/// what would happen to it, and why it would be rejected.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportItemResult {
+    pub index: usize,
+    pub id: Uuid,
+    pub action: ImportAction,
+    #[serde(skip_serializing_if = "FieldErrors::is_empty")]
+    #[schema(value_type = HashMap<String, Vec<String>>)]
+    pub errors: FieldErrors,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// Result of `?dry_run=true` on an import. `summary` is what the import
+/// would have reported, or `None` when validation failed and nothing was
+/// attempted; `error` is set when the database rejected the rolled-back
+/// attempt.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportPreview {
+    pub dry_run: bool,
+    pub valid: bool,
+    pub users: Vec<ImportItemResult>,
+    pub posts: Vec<ImportItemResult>,
+    pub summary: Option<ImportSummary>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct ImportCounts {
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct ImportSummary {
+    pub users: ImportCounts,
+    pub posts: ImportCounts,
+}
+
+/// Headline numbers for `GET /admin/stats`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct Stats {
+    pub users: i64,
+    pub posts: i64,
+    /// Distinct tags in use.
+    pub tags: i64,
+    /// Comments that have not been deleted.
+    pub comments: i64,
+}
+
+/// Body of `POST /admin/tags/rename`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RenameTag {
+    pub from: String,
+    pub to: String,
+}
+
+impl RenameTag {
+    /// Normalizes both names the way tags are stored.
+    pub fn sanitize(&mut self) {
+        self.from = self.from.trim().to_lowercase();
+        self.to = self.to.trim().to_lowercase();
+    }
+
+    pub fn validate(&self) -> Result<(), FieldErrors> {
+        let mut errors = FieldErrors::new();
+        for (field, tag) in [("from", &self.from), ("to", &self.to)] {
+            if tag.is_empty() {
+                add_field_error(&mut errors, field, "must not be empty");
+            }
+        }
+        if self.to.chars().count() > MAX_TAG_LENGTH {
+            add_field_error(
+                &mut errors,
+                "to",
+                format!("must be at most {MAX_TAG_LENGTH} characters"),
+            );
+        }
+        if !self.to.is_empty() && self.to == self.from {
+            add_field_error(&mut errors, "to", "must differ from `from`");
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Outcome of renaming a tag on every post. `renamed` posts had their tag
+/// changed; `merged` posts already had the new tag, so the old one was
+/// dropped instead.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TagRename {
+    pub from: String,
+    pub to: String,
+    pub renamed: usize,
+    pub merged: usize,
+}
+
+/// Rows removed by `DELETE /users/<id>/posts`, per table.
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct PurgedPosts {
+    pub posts: usize,
+    pub tags: usize,
+    pub comments: usize,
+    pub favorites: usize,
+    pub attachments: usize,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = favorites)]
+pub struct NewFavorite {
+    pub fk_user_id: Uuid,
+    pub fk_post_id: Uuid,
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, ToSchema)]
+#[diesel(table_name = audit_log)]
+pub struct AuditEntry {
+    pub id: Uuid,
+    pub entity_type: String,
+    pub entity_id: Uuid,
+    pub action: String,
+    pub actor_id: Option<Uuid>,
+    #[serde(serialize_with = "timestamp::serialize")]
+    pub at: DateTime<Utc>,
+    #[schema(value_type = Option<Object>)]
+    pub diff: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = audit_log)]
+pub struct NewAuditEntry {
+    pub entity_type: &'static str,
+    pub entity_id: Uuid,
+    pub action: &'static str,
+    pub actor_id: Option<Uuid>,
+    pub diff: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct PaginatedResponse<T> {
     pub records: Vec<T>,
     pub meta: PaginationMeta,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct PaginationMeta {
     pub current_page: i64,
     pub per_page: i64,
@@ -98,4 +1445,542 @@ pub struct PaginationMeta {
     pub to: i64,
     pub total_pages: i64,
     pub total_docs: i64,
+    /// `current_page` is past the last page (or below 1), so `records` is
+    /// empty because of the page number rather than the filters.
+    pub out_of_range: bool,
+    /// The `limit` the client asked for, present only when it was clamped
+    /// to `per_page`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requested_limit: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query_ms: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(title: &str, body: &str) -> NewPostWithTags {
+        NewPostWithTags {
+            title: title.to_string(),
+            body: body.to_string(),
+            created_by: Uuid::new_v4(),
+            tags: vec![],
+        }
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn field_errors(fields: &[(&str, &[&str])]) -> FieldErrors {
+        fields
+            .iter()
+            .map(|(field, messages)| (field.to_string(), strings(messages)))
+            .collect()
+    }
+
+    #[test]
+    fn timestamps_always_have_microseconds_and_a_utc_offset() {
+        let whole: DateTime<Utc> = "2024-01-01T00:00:00Z".parse().unwrap();
+        assert_eq!(timestamp::format(&whole), "2024-01-01T00:00:00.000000Z");
+        let user = User {
+            id: Uuid::nil(),
+            username: "ada".to_string(),
+            first_name: "Ada".to_string(),
+            last_name: String::new(),
+            created_at: "2024-01-01T12:30:00.5+02:00".parse().unwrap(),
+        };
+        assert_eq!(
+            serde_json::to_value(&user).unwrap()["created_at"],
+            "2024-01-01T10:30:00.500000Z"
+        );
+    }
+
+    #[test]
+    fn slugify_keeps_ascii_words_joined_by_hyphens() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(
+            slugify("  Rust 2024 -- what's new?  "),
+            "rust-2024-what-s-new"
+        );
+        assert_eq!(slugify("Crème brûlée"), "cr-me-br-l-e");
+        assert_eq!(slugify("!!!"), "");
+    }
+
+    #[test]
+    fn sanitize_trims_and_collapses_one_line_fields() {
+        let mut p = post("  Hello \t  world ", "\n  Line one\n\nLine  two  \n");
+        p.tags = strings(&[" Rust ", "rust"]);
+        p.sanitize();
+        assert_eq!(p.title, "Hello world");
+        assert_eq!(p.body, "Line one\n\nLine  two");
+        assert_eq!(p.tags, vec!["rust"]);
+
+        let mut user = NewUser {
+            username: " ada ".to_string(),
+            first_name: "Ada  Augusta".to_string(),
+            last_name: "  ".to_string(),
+        };
+        user.sanitize();
+        assert_eq!(user.username, "ada");
+        assert_eq!(user.first_name, "Ada Augusta");
+        assert!(user.validate().is_ok());
+    }
+
+    #[test]
+    fn fields_blank_after_sanitizing_fail_validation() {
+        let mut p = post(" \t ", " \n ");
+        p.sanitize();
+        let errors = p.validate(DEFAULT_MAX_TAGS_PER_POST).unwrap_err();
+        assert!(errors.contains_key("title") && errors.contains_key("body"));
+
+        let mut user = NewUser {
+            username: "   ".to_string(),
+            first_name: "\t".to_string(),
+            last_name: String::new(),
+        };
+        user.sanitize();
+        let errors = user.validate().unwrap_err();
+        assert!(errors.contains_key("username") && errors.contains_key("first_name"));
+
+        let mut comment = UpdateComment {
+            body: " \n ".to_string(),
+        };
+        comment.sanitize();
+        assert!(comment.validate().is_err());
+    }
+
+    #[test]
+    fn validate_accepts_a_normal_post() {
+        assert!(post("Hello", "World")
+            .validate(DEFAULT_MAX_TAGS_PER_POST)
+            .is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_empty_title_and_body() {
+        let errors = post("", "")
+            .validate(DEFAULT_MAX_TAGS_PER_POST)
+            .unwrap_err();
+        assert_eq!(
+            errors,
+            field_errors(&[
+                ("title", &["must not be empty"]),
+                ("body", &["must not be empty"])
+            ])
+        );
+    }
+
+    #[test]
+    fn validate_rejects_whitespace_only_title() {
+        let errors = post(" \t\n ", "World")
+            .validate(DEFAULT_MAX_TAGS_PER_POST)
+            .unwrap_err();
+        assert_eq!(errors, field_errors(&[("title", &["must not be empty"])]));
+    }
+
+    #[test]
+    fn validate_rejects_over_length_title() {
+        let title = "x".repeat(MAX_TITLE_LENGTH + 1);
+        let errors = post(&title, "World")
+            .validate(DEFAULT_MAX_TAGS_PER_POST)
+            .unwrap_err();
+        assert_eq!(
+            errors,
+            field_errors(&[("title", &["must be at most 200 characters"])])
+        );
+
+        let padded = format!("  {}  ", "x".repeat(MAX_TITLE_LENGTH));
+        assert!(post(&padded, "World")
+            .validate(DEFAULT_MAX_TAGS_PER_POST)
+            .is_ok());
+    }
+
+    #[test]
+    fn rename_tag_is_normalized_and_must_change_the_name() {
+        let mut rename = RenameTag {
+            from: " JavScript ".to_string(),
+            to: "javscript".to_string(),
+        };
+        rename.sanitize();
+        assert_eq!(rename.from, "javscript");
+        assert_eq!(
+            rename.validate(),
+            Err(field_errors(&[("to", &["must differ from `from`"])]))
+        );
+
+        rename.to = "javascript".to_string();
+        assert!(rename.validate().is_ok());
+
+        rename.from = String::new();
+        rename.to = "x".repeat(MAX_TAG_LENGTH + 1);
+        let errors = rename.validate().unwrap_err();
+        assert!(errors.contains_key("from"));
+        assert!(errors.contains_key("to"));
+    }
+
+    #[test]
+    fn onboard_errors_are_keyed_by_part() {
+        let mut request = OnboardRequest {
+            user: NewUser {
+                username: "  ".to_string(),
+                first_name: "Ada".to_string(),
+                last_name: String::new(),
+            },
+            post: OnboardPost {
+                title: " Hello   world ".to_string(),
+                body: " ".to_string(),
+                tags: strings(&["Rust", "rust"]),
+            },
+        };
+        request.sanitize();
+        assert_eq!(request.post.title, "Hello world");
+        assert_eq!(request.post.tags, vec!["rust"]);
+        assert_eq!(
+            request.validate(DEFAULT_MAX_TAGS_PER_POST),
+            Err(field_errors(&[
+                ("user.username", &["must not be empty"]),
+                ("post.body", &["must not be empty"]),
+            ]))
+        );
+
+        request.user.username = "ada".to_string();
+        request.post.body = "First!".to_string();
+        assert_eq!(request.validate(DEFAULT_MAX_TAGS_PER_POST), Ok(vec![]));
+    }
+
+    #[test]
+    fn normalize_tags_trims_lowercases_and_dedups() {
+        let tags = strings(&[" Rust ", "rust", "", "  ", "Web", "RUST"]);
+        assert_eq!(normalize_tags(&tags), strings(&["rust", "web"]));
+    }
+
+    #[test]
+    fn validate_limits_tag_count_after_dedup() {
+        let mut p = post("Hello", "World");
+        p.tags = normalize_tags(&strings(&["a", "A", "b", "B", "c"]));
+        assert!(p.validate(3).is_ok());
+
+        p.tags.push("d".to_string());
+        let errors = p.validate(3).unwrap_err();
+        assert_eq!(
+            errors,
+            field_errors(&[("tags", &["must contain at most 3 entries"])])
+        );
+    }
+
+    #[test]
+    fn validate_rejects_over_length_tag() {
+        let mut p = post("Hello", "World");
+        p.tags = vec!["x".repeat(MAX_TAG_LENGTH)];
+        assert!(p.validate(DEFAULT_MAX_TAGS_PER_POST).is_ok());
+
+        p.tags = vec!["x".repeat(MAX_TAG_LENGTH + 1)];
+        let errors = p.validate(DEFAULT_MAX_TAGS_PER_POST).unwrap_err();
+        assert_eq!(
+            errors,
+            field_errors(&[("tags", &["must each be at most 50 characters"])])
+        );
+    }
+
+    #[test]
+    fn validate_warns_about_spaced_and_long_tags() {
+        let mut p = post("Hello", "World");
+        p.tags = normalize_tags(&strings(&["rust", "web dev"]));
+        assert_eq!(
+            p.validate(DEFAULT_MAX_TAGS_PER_POST).unwrap(),
+            vec!["tag 'web dev' contains spaces".to_string()]
+        );
+
+        p.tags = vec!["x".repeat(TAG_LENGTH_WARNING + 1)];
+        let warnings = p.validate(DEFAULT_MAX_TAGS_PER_POST).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].ends_with("is unusually long (31 characters)"));
+
+        p.tags = vec!["x".repeat(TAG_LENGTH_WARNING)];
+        assert!(p.validate(DEFAULT_MAX_TAGS_PER_POST).unwrap().is_empty());
+    }
+
+    #[test]
+    fn warnings_do_not_hide_hard_errors() {
+        let mut p = post("", "World");
+        p.tags = strings(&["web dev"]);
+        let errors = p.validate(DEFAULT_MAX_TAGS_PER_POST).unwrap_err();
+        assert_eq!(errors, field_errors(&[("title", &["must not be empty"])]));
+    }
+
+    #[test]
+    fn update_validate_requires_a_change_and_checks_fields() {
+        let empty = UpdatePost {
+            title: None,
+            body: None,
+        };
+        assert_eq!(
+            empty.validate().unwrap_err(),
+            field_errors(&[(
+                "request",
+                &["at least one of title or body must be provided"]
+            )])
+        );
+
+        let blank = UpdatePost {
+            title: Some("  ".to_string()),
+            body: Some(String::new()),
+        };
+        assert_eq!(
+            blank.validate().unwrap_err(),
+            field_errors(&[
+                ("title", &["must not be empty"]),
+                ("body", &["must not be empty"])
+            ])
+        );
+
+        let title_only = UpdatePost {
+            title: Some("New title".to_string()),
+            body: None,
+        };
+        assert!(title_only.validate().is_ok());
+    }
+
+    #[test]
+    fn sort_parses_columns_and_directions() {
+        assert_eq!(
+            PostSort::parse("-created_at, title").unwrap(),
+            PostSort(vec![
+                (PostSortField::CreatedAt, SortDirection::Desc),
+                (PostSortField::Title, SortDirection::Asc),
+            ])
+        );
+        assert_eq!(PostSort::parse("-created_at").unwrap(), PostSort::default());
+        assert_eq!(PostSort::parse("created_at").unwrap(), PostSort::default());
+        assert_eq!(
+            PostSort::parse("+created_at,-title").unwrap(),
+            PostSort(vec![
+                (PostSortField::CreatedAt, SortDirection::Asc),
+                (PostSortField::Title, SortDirection::Desc),
+            ])
+        );
+    }
+
+    #[test]
+    fn sort_rejects_unknown_duplicate_and_empty_columns() {
+        assert_eq!(
+            PostSort::parse("-body").unwrap_err(),
+            "unknown column 'body', expected one of: created_at, title"
+        );
+        assert_eq!(
+            PostSort::parse("title,-title").unwrap_err(),
+            "column 'title' is listed more than once"
+        );
+        assert!(PostSort::parse("").is_err());
+        assert!(PostSort::parse("title,").is_err());
+        assert!(PostSort::parse("-").is_err());
+        assert!(PostSort::parse("+").is_err());
+    }
+
+    fn search(term: &str) -> PostFilters {
+        PostFilters {
+            search: Some(term.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn short_search_terms_are_rejected_after_trimming() {
+        assert_eq!(
+            search(" a ").validate(DEFAULT_MIN_SEARCH_LENGTH),
+            Err(field_errors(&[(
+                "search",
+                &["must be at least 2 characters, or omitted to list all posts"]
+            )]))
+        );
+        assert!(search("ab").validate(DEFAULT_MIN_SEARCH_LENGTH).is_ok());
+        assert!(search("é").validate(1).is_ok());
+    }
+
+    #[test]
+    fn tag_filter_conflicts_with_untagged() {
+        let filters = PostFilters {
+            tag: Some("rust".to_string()),
+            untagged: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(
+            filters.validate(DEFAULT_MIN_SEARCH_LENGTH),
+            Err(field_errors(&[(
+                "tag",
+                &["cannot be combined with untagged=true"]
+            )]))
+        );
+
+        let filters = PostFilters {
+            tag: Some("rust".to_string()),
+            untagged: Some(false),
+            ..Default::default()
+        };
+        assert!(filters.validate(DEFAULT_MIN_SEARCH_LENGTH).is_ok());
+    }
+
+    #[test]
+    fn with_accepts_only_known_extras() {
+        let mut filters = PostFilters::default();
+        assert!(!filters.with_comment_count());
+
+        filters.with = Some(" comment_count ,".to_string());
+        assert!(filters.validate(DEFAULT_MIN_SEARCH_LENGTH).is_ok());
+        assert!(filters.with_comment_count());
+
+        filters.with = Some("comment_count,likes".to_string());
+        assert_eq!(
+            filters.validate(DEFAULT_MIN_SEARCH_LENGTH),
+            Err(field_errors(&[(
+                "with",
+                &["expected one of: comment_count"]
+            )]))
+        );
+    }
+
+    #[test]
+    fn match_mode_is_checked_against_the_allowlist() {
+        assert_eq!(search("rust").search_match(), SearchMatch::Contains);
+
+        let mut filters = search("rust");
+        filters.match_mode = Some(" Prefix ".to_string());
+        assert!(filters.validate(DEFAULT_MIN_SEARCH_LENGTH).is_ok());
+        assert_eq!(filters.search_match(), SearchMatch::Prefix);
+
+        filters.match_mode = Some("regex".to_string());
+        assert_eq!(
+            filters.validate(DEFAULT_MIN_SEARCH_LENGTH),
+            Err(field_errors(&[(
+                "match",
+                &["expected one of: exact, contains, prefix"]
+            )]))
+        );
+        assert_eq!(filters.search_match(), SearchMatch::Contains);
+
+        assert_eq!(SearchMatch::Exact.pattern("ru"), "ru");
+        assert_eq!(SearchMatch::Contains.pattern("ru"), "%ru%");
+        assert_eq!(SearchMatch::Prefix.pattern("ru"), "ru%");
+        assert_eq!(SearchMatch::Contains.pattern("50%_"), "%50\\%\\_%");
+        assert_eq!(SearchMatch::Prefix.pattern("a\\b"), "a\\\\b%");
+        assert_eq!(SearchMatch::Exact.pattern("50%"), "50%");
+    }
+
+    #[test]
+    fn blank_search_lists_everything() {
+        let filters = search("   ");
+        assert!(filters.validate(DEFAULT_MIN_SEARCH_LENGTH).is_ok());
+        assert_eq!(filters.search_term(), None);
+        assert_eq!(search(" rust ").search_term(), Some("rust"));
+    }
+
+    fn attachment(url: &str, content_type: &str, size_bytes: i64) -> NewAttachmentRequest {
+        NewAttachmentRequest {
+            url: url.to_string(),
+            content_type: content_type.to_string(),
+            size_bytes,
+        }
+    }
+
+    #[test]
+    fn attachment_accepts_http_image_urls() {
+        assert!(attachment("https://cdn.example.com/a.png", "image/png", 10)
+            .validate()
+            .is_ok());
+        assert!(attachment("http://example.com", "image/webp", 1)
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn attachment_rejects_bad_url_type_and_size() {
+        assert_eq!(
+            attachment("ftp://example.com/a.png", "text/html", 0).validate(),
+            Err(field_errors(&[
+                ("url", &["must be an absolute http(s) URL"]),
+                (
+                    "content_type",
+                    &["must be one of: image/gif, image/jpeg, image/png, image/webp"]
+                ),
+                ("size_bytes", &["must be greater than 0"]),
+            ]))
+        );
+        for url in [
+            "https://",
+            "https:///path",
+            "https://exa mple.com",
+            "/a.png",
+        ] {
+            assert!(attachment(url, "image/png", 1).validate().is_err(), "{url}");
+        }
+    }
+
+    fn comment(id: u128, parent: Option<u128>) -> Comment {
+        Comment {
+            id: Uuid::from_u128(id),
+            fk_post_id: Uuid::nil(),
+            author_id: Uuid::nil(),
+            parent_id: parent.map(Uuid::from_u128),
+            body: format!("comment {id}"),
+            created_at: Utc::now(),
+            deleted_at: None,
+        }
+    }
+
+    fn shape(nodes: &[CommentNode]) -> Vec<(u128, Vec<u128>)> {
+        nodes
+            .iter()
+            .map(|node| {
+                let replies = node
+                    .replies
+                    .iter()
+                    .map(|r| r.comment.id.as_u128())
+                    .collect();
+                (node.comment.id.as_u128(), replies)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn comment_tree_nests_replies_in_order() {
+        let tree = build_comment_tree(vec![
+            comment(1, None),
+            comment(2, Some(1)),
+            comment(3, None),
+            comment(4, Some(2)),
+            comment(5, Some(1)),
+        ]);
+
+        assert_eq!(shape(&tree), vec![(1, vec![2, 5]), (3, vec![])]);
+        assert_eq!(shape(&tree[0].replies), vec![(2, vec![4]), (5, vec![])]);
+    }
+
+    #[test]
+    fn deleted_comment_keeps_its_replies() {
+        let mut deleted = comment(1, None);
+        deleted.deleted_at = Some(Utc::now());
+        let tree = build_comment_tree(vec![deleted, comment(2, Some(1))]);
+
+        assert_eq!(shape(&tree), vec![(1, vec![2])]);
+        assert_eq!(tree[0].comment.body, DELETED_COMMENT_BODY);
+        assert_eq!(tree[0].replies[0].comment.body, "comment 2");
+    }
+
+    #[test]
+    fn comment_with_unknown_parent_is_top_level() {
+        let tree = build_comment_tree(vec![comment(1, Some(99))]);
+        assert_eq!(shape(&tree), vec![(1, vec![])]);
+    }
+
+    #[test]
+    fn v7_ids_sort_in_creation_order() {
+        let first = IdVersion::V7.new_id();
+        let second = IdVersion::V7.new_id();
+        assert_eq!(first.get_version_num(), 7);
+        assert!(first < second);
+
+        assert_eq!(IdVersion::default().new_id().get_version_num(), 4);
+    }
 }
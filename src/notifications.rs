@@ -0,0 +1,200 @@
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use rocket::tokio::sync::broadcast;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::models::{Comment, Post};
+use crate::repository::PostRepository;
+
+/// Postgres channel `create_with_tags` notifies with the new post's id.
+pub const NEW_POST_CHANNEL: &str = "new_post";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Managed state that fans newly created posts out to in-process subscribers.
+/// Posts arrive through Postgres NOTIFY, so creations on other instances
+/// sharing the database are seen too.
+pub struct PostEvents {
+    sender: broadcast::Sender<Post>,
+}
+
+impl PostEvents {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(64);
+        PostEvents { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Post> {
+        self.sender.subscribe()
+    }
+
+    /// Starts the background listener on its own connection, outside the
+    /// pool, so a long-lived LISTEN never ties up a pooled connection.
+    pub fn spawn_listener(&self, database_url: String) {
+        let sender = self.sender.clone();
+        thread::spawn(move || loop {
+            if let Err(e) = listen(&database_url, &sender) {
+                eprintln!("Post notification listener failed: {e}, reconnecting");
+            }
+            thread::sleep(RECONNECT_DELAY);
+        });
+    }
+}
+
+fn listen(
+    database_url: &str,
+    sender: &broadcast::Sender<Post>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut conn = PgConnection::establish(database_url)?;
+    diesel::sql_query(format!("LISTEN {NEW_POST_CHANNEL}")).execute(&mut conn)?;
+
+    loop {
+        let mut ids = Vec::new();
+        for notification in conn.notifications_iter() {
+            if let Ok(id) = Uuid::parse_str(&notification?.payload) {
+                ids.push(id);
+            }
+        }
+
+        for id in ids {
+            if let Some(post) = PostRepository::find_by_id(&mut conn, id)? {
+                // Sending only fails when nobody is subscribed, which is fine.
+                let _ = sender.send(post);
+            }
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Managed state that pushes new comments to the viewers of each post's
+/// thread. A post gets its own channel while someone is watching it, so
+/// viewers only receive comments on their post and publishing to an unwatched
+/// post is a map lookup. Only comments created on this instance are seen.
+pub struct CommentEvents {
+    channels: Mutex<HashMap<Uuid, broadcast::Sender<Comment>>>,
+}
+
+impl CommentEvents {
+    pub fn new() -> Self {
+        CommentEvents {
+            channels: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Starts watching `post_id`. The post's channel is removed once its
+    /// last subscription is dropped.
+    pub fn subscribe(&self, post_id: Uuid) -> CommentSubscription<'_> {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        let receiver = channels
+            .entry(post_id)
+            .or_insert_with(|| broadcast::channel(64).0)
+            .subscribe();
+        CommentSubscription {
+            events: self,
+            post_id,
+            receiver: Some(receiver),
+        }
+    }
+
+    pub fn publish(&self, comment: &Comment) {
+        let channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(sender) = channels.get(&comment.fk_post_id) {
+            // Sending only fails when nobody is subscribed, which is fine.
+            let _ = sender.send(comment.clone());
+        }
+    }
+
+    #[cfg(test)]
+    fn watched(&self) -> usize {
+        self.channels
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+}
+
+/// New comments on one post, from `CommentEvents::subscribe`.
+pub struct CommentSubscription<'a> {
+    events: &'a CommentEvents,
+    post_id: Uuid,
+    receiver: Option<broadcast::Receiver<Comment>>,
+}
+
+impl CommentSubscription<'_> {
+    pub async fn recv(&mut self) -> Result<Comment, broadcast::error::RecvError> {
+        match self.receiver.as_mut() {
+            Some(receiver) => receiver.recv().await,
+            None => Err(broadcast::error::RecvError::Closed),
+        }
+    }
+}
+
+impl Drop for CommentSubscription<'_> {
+    fn drop(&mut self) {
+        let mut channels = self
+            .events
+            .channels
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        // Dropped under the lock so the count below can't race another
+        // viewer of the same post leaving.
+        self.receiver.take();
+        if channels
+            .get(&self.post_id)
+            .is_some_and(|sender| sender.receiver_count() == 0)
+        {
+            channels.remove(&self.post_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn comment(post_id: Uuid) -> Comment {
+        Comment {
+            id: Uuid::new_v4(),
+            fk_post_id: post_id,
+            author_id: Uuid::new_v4(),
+            parent_id: None,
+            body: "hello".to_string(),
+            created_at: Utc::now(),
+            deleted_at: None,
+        }
+    }
+
+    #[rocket::async_test]
+    async fn comments_reach_only_viewers_of_their_post() {
+        let events = CommentEvents::new();
+        let (watched, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut subscription = events.subscribe(watched);
+
+        events.publish(&comment(other));
+        let sent = comment(watched);
+        events.publish(&sent);
+
+        assert_eq!(subscription.recv().await.unwrap().id, sent.id);
+        assert_eq!(events.watched(), 1);
+    }
+
+    #[test]
+    fn channels_are_removed_when_the_last_viewer_leaves() {
+        let events = CommentEvents::new();
+        let post_id = Uuid::new_v4();
+        let first = events.subscribe(post_id);
+        let second = events.subscribe(post_id);
+
+        drop(first);
+        assert_eq!(events.watched(), 1);
+        drop(second);
+        assert_eq!(events.watched(), 0);
+    }
+}
@@ -0,0 +1,236 @@
+use rocket::response::content::RawHtml;
+use rocket::serde::json::Json;
+use rocket::State;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
+use utoipa::openapi::server::Server;
+use utoipa::{Modify, OpenApi};
+
+use crate::config::Config;
+use crate::models::ImportPreview;
+use crate::{graphql, handlers};
+
+/// OpenAPI 3 description of the routes mounted under `API_BASE_PATH`,
+/// generated from the `#[utoipa::path]` attributes on the handlers.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Blog API",
+        description = "Every JSON response is wrapped in `{ \"success\": ..., ... }`. \
+            Write endpoints may answer 429 and any endpoint 503 when the database pool \
+            is exhausted; both carry `Retry-After`."
+    ),
+    paths(
+        handlers::create_user,
+        handlers::get_user,
+        handlers::username_available,
+        handlers::onboard,
+        handlers::batch_users,
+        handlers::create_post,
+        handlers::list_posts,
+        handlers::count_posts,
+        handlers::post_archive,
+        handlers::popular_posts,
+        handlers::latest_per_author,
+        handlers::stream_posts,
+        handlers::export_posts,
+        handlers::batch_posts,
+        handlers::get_post,
+        handlers::get_post_by_slug,
+        handlers::update_post,
+        handlers::toggle_featured,
+        handlers::reassign_post,
+        handlers::delete_user_posts,
+        handlers::set_post_tags,
+        handlers::add_attachment,
+        handlers::create_comment,
+        handlers::comment_tree,
+        handlers::comment_thread_ws,
+        handlers::update_comment,
+        handlers::delete_comment,
+        handlers::list_tags,
+        handlers::tag_posts,
+        handlers::suggest_tags,
+        handlers::search_posts,
+        handlers::rss_feed,
+        handlers::orphaned_tags,
+        handlers::cleanup_orphaned_tags,
+        handlers::rename_tag,
+        handlers::delete_tag,
+        handlers::stats,
+        handlers::list_audit,
+        handlers::import_data,
+        handlers::export_data,
+        handlers::create_webhook,
+        handlers::list_webhooks,
+        handlers::get_setting,
+        handlers::put_setting,
+        handlers::add_favorite,
+        handlers::remove_favorite,
+        handlers::list_favorites,
+        graphql::graphql,
+    ),
+    components(schemas(ImportPreview)),
+    modifiers(&HeaderAuth),
+    tags(
+        (name = "users"),
+        (name = "posts"),
+        (name = "comments"),
+        (name = "tags"),
+        (name = "favorites"),
+        (name = "feeds", description = "Feeds and live updates"),
+        (name = "admin", description = "Requires `X-Admin-Token`"),
+        (name = "graphql"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Security schemes for the `X-Admin-Token` and `X-User-Id` headers that the
+/// `Admin` and `AuthUser` guards read.
+struct HeaderAuth;
+
+impl Modify for HeaderAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "admin_token",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-Admin-Token"))),
+        );
+        components.add_security_scheme(
+            "user_id",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-User-Id"))),
+        );
+    }
+}
+
+/// The spec, with the configured base path as its only server so the
+/// documented paths resolve.
+#[get("/openapi.json")]
+pub fn openapi_json(config: &State<Config>) -> Json<utoipa::openapi::OpenApi> {
+    let mut doc = ApiDoc::openapi();
+    doc.servers = Some(vec![Server::new(config.api_base_path.clone())]);
+    Json(doc)
+}
+
+/// Swagger UI over `/openapi.json`, loaded from a CDN so nothing has to be
+/// vendored.
+#[get("/docs")]
+pub fn docs() -> RawHtml<&'static str> {
+    RawHtml(SWAGGER_UI)
+}
+
+const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>Blog API docs</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
+
+/// Response shapes that handlers build inline with `serde_json::json!`,
+/// described here for the spec only.
+#[allow(dead_code)]
+pub mod schemas {
+    use std::collections::HashMap;
+
+    use utoipa::ToSchema;
+    use uuid::Uuid;
+
+    use crate::models::{DumpPost, DumpUser, PostWithUserAndTags};
+
+    #[derive(ToSchema)]
+    pub struct Success<T> {
+        pub success: bool,
+        pub data: T,
+    }
+
+    /// A success envelope for writes that validate tags; `warnings` is only
+    /// present when there is something to report.
+    #[derive(ToSchema)]
+    pub struct SuccessWithWarnings<T> {
+        pub success: bool,
+        pub data: T,
+        pub warnings: Option<Vec<String>>,
+    }
+
+    #[derive(ToSchema)]
+    pub struct ErrorBody {
+        pub success: bool,
+        pub error: String,
+    }
+
+    /// Sent with 422: messages keyed by the request field they refer to.
+    #[derive(ToSchema)]
+    pub struct ValidationErrorBody {
+        pub success: bool,
+        pub errors: HashMap<String, Vec<String>>,
+    }
+
+    #[derive(ToSchema)]
+    pub struct PostBatch {
+        pub records: Vec<PostWithUserAndTags>,
+        /// Requested ids without a post, in request order.
+        pub not_found: Vec<Uuid>,
+    }
+
+    #[derive(ToSchema)]
+    pub struct UsernameAvailability {
+        pub available: bool,
+    }
+
+    #[derive(ToSchema)]
+    pub struct PostCount {
+        pub count: i64,
+    }
+
+    #[derive(ToSchema)]
+    pub struct DeletedTag {
+        pub tag: String,
+        /// Posts the tag was removed from.
+        pub posts: usize,
+    }
+
+    #[derive(ToSchema)]
+    pub struct RemovedTags {
+        pub removed: usize,
+    }
+
+    #[derive(ToSchema)]
+    pub struct Favorite {
+        pub post_id: Uuid,
+        pub favorited: bool,
+    }
+
+    /// `GET /admin/export`, which `POST /admin/import` accepts as is.
+    #[derive(ToSchema)]
+    pub struct ExportDocument {
+        pub version: u32,
+        pub users: Vec<DumpUser>,
+        pub posts: Vec<DumpPost>,
+    }
+
+    #[derive(ToSchema)]
+    pub struct GraphqlRequest {
+        pub query: String,
+        #[schema(value_type = Option<Object>)]
+        pub variables: Option<serde_json::Value>,
+        #[schema(rename = "operationName")]
+        pub operation_name: Option<String>,
+    }
+
+    #[derive(ToSchema)]
+    pub struct GraphqlResponse {
+        #[schema(value_type = Option<Object>)]
+        pub data: Option<serde_json::Value>,
+        #[schema(value_type = Option<Vec<Object>>)]
+        pub errors: Option<Vec<serde_json::Value>>,
+    }
+}
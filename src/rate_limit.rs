@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+
+use crate::config::Config;
+
+pub const DEFAULT_WRITES_PER_MINUTE: u32 = 20;
+/// How often idle buckets are swept out of the map.
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Per-client-IP token buckets for write endpoints. Each IP may burst up to
+/// `per_minute` writes, refilled continuously at the same rate per minute.
+/// A limit of 0 disables limiting.
+pub struct RateLimiter {
+    per_minute: u32,
+    trusted_ip_header: Option<String>,
+    state: Mutex<State>,
+}
+
+struct State {
+    buckets: HashMap<IpAddr, Bucket>,
+    last_cleanup: Instant,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32) -> Self {
+        RateLimiter {
+            per_minute,
+            trusted_ip_header: None,
+            state: Mutex::new(State {
+                buckets: HashMap::new(),
+                last_cleanup: Instant::now(),
+            }),
+        }
+    }
+
+    /// Limited by `WRITE_RATE_LIMIT_PER_MINUTE`, keyed as `TRUSTED_IP_HEADER`
+    /// says.
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(config.writes_per_minute).trusting(config.trusted_ip_header.clone())
+    }
+
+    /// Keys buckets on `header` when it is set, rather than the connection's
+    /// address. Only for a header a proxy in front always overwrites.
+    pub fn trusting(mut self, header: Option<String>) -> Self {
+        self.trusted_ip_header = header;
+        self
+    }
+
+    /// The address `req` is limited under: the last entry of the trusted
+    /// header, which is the one the proxy added, or else the peer address.
+    fn client_ip(&self, req: &Request<'_>) -> Option<IpAddr> {
+        self.trusted_ip_header
+            .as_deref()
+            .and_then(|header| req.headers().get_one(header))
+            .and_then(|value| value.rsplit(',').next()?.trim().parse().ok())
+            .or_else(|| req.remote().map(|remote| remote.ip()))
+    }
+
+    /// Takes a token for `ip`, or returns how long until one is available.
+    pub fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        self.check_at(ip, Instant::now())
+    }
+
+    fn check_at(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        if self.per_minute == 0 {
+            return Ok(());
+        }
+        let capacity = f64::from(self.per_minute);
+        let per_second = capacity / 60.0;
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        if now.duration_since(state.last_cleanup) >= CLEANUP_INTERVAL {
+            // A bucket that would have refilled completely is the same as no
+            // bucket at all.
+            state.buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * per_second
+                    < capacity
+            });
+            state.last_cleanup = now;
+        }
+
+        let bucket = state.buckets.entry(ip).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_second).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+        }
+    }
+
+    #[cfg(test)]
+    fn tracked(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .buckets
+            .len()
+    }
+}
+
+/// Seconds the client should wait, set when `WriteLimit` rejects a request
+/// so the 429 catcher can send `Retry-After`.
+pub struct RetryAfter(pub Option<u64>);
+
+/// Request guard for write endpoints. Takes a token from the client IP's
+/// bucket in the managed `RateLimiter`, failing with
+/// `429 Too Many Requests` when it is empty. The client IP is the peer
+/// address unless `TRUSTED_IP_HEADER` names a proxy's header, so clients
+/// cannot pick their own bucket. Requests without a known client IP are not
+/// limited.
+pub struct WriteLimit;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for WriteLimit {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(limiter) = req.rocket().state::<RateLimiter>() else {
+            return Outcome::Success(WriteLimit);
+        };
+        let Some(ip) = limiter.client_ip(req) else {
+            return Outcome::Success(WriteLimit);
+        };
+
+        match limiter.check(ip) {
+            Ok(()) => Outcome::Success(WriteLimit),
+            Err(wait) => {
+                // Round up so retrying after the advertised delay succeeds.
+                let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+                req.local_cache(|| RetryAfter(Some(seconds)));
+                Outcome::Error((Status::TooManyRequests, ()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::http::Header;
+    use rocket::local::blocking::Client;
+    use std::net::SocketAddr;
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::from([10, 0, 0, last])
+    }
+
+    #[test]
+    fn bucket_allows_a_burst_then_refills_over_time() {
+        let limiter = RateLimiter::new(3);
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.check_at(ip(1), start).is_ok());
+        }
+        let wait = limiter.check_at(ip(1), start).unwrap_err();
+        assert_eq!(wait.as_secs(), 20);
+
+        // Other clients have their own bucket.
+        assert!(limiter.check_at(ip(2), start).is_ok());
+
+        assert!(limiter
+            .check_at(ip(1), start + Duration::from_secs(19))
+            .is_err());
+        assert!(limiter
+            .check_at(ip(1), start + Duration::from_secs(21))
+            .is_ok());
+    }
+
+    #[test]
+    fn idle_buckets_are_cleaned_up() {
+        let limiter = RateLimiter::new(2);
+        let start = Instant::now();
+        limiter.check_at(ip(1), start).unwrap();
+        limiter.check_at(ip(2), start).unwrap();
+        assert_eq!(limiter.tracked(), 2);
+
+        let later = start + CLEANUP_INTERVAL;
+        limiter.check_at(ip(3), later).unwrap();
+        assert_eq!(limiter.tracked(), 1);
+    }
+
+    #[test]
+    fn zero_disables_the_limit() {
+        let limiter = RateLimiter::new(0);
+        let now = Instant::now();
+        for _ in 0..100 {
+            assert!(limiter.check_at(ip(1), now).is_ok());
+        }
+    }
+
+    #[post("/write")]
+    fn write(_limit: WriteLimit) -> &'static str {
+        "ok"
+    }
+
+    fn client(limiter: RateLimiter) -> Client {
+        let rocket = rocket::build()
+            .manage(limiter)
+            .mount("/", routes![write])
+            .register("/", catchers![crate::errors::too_many_requests]);
+        Client::tracked(rocket).unwrap()
+    }
+
+    fn peer(last: u8) -> SocketAddr {
+        SocketAddr::new(ip(last), 40000)
+    }
+
+    #[test]
+    fn exceeding_the_limit_returns_429_with_retry_after() {
+        let client = client(RateLimiter::new(1));
+        let from = |last: u8| client.post("/write").remote(peer(last));
+
+        assert_eq!(from(1).dispatch().status(), Status::Ok);
+        let limited = from(1).dispatch();
+        assert_eq!(limited.status(), Status::TooManyRequests);
+        assert_eq!(limited.headers().get_one("Retry-After"), Some("60"));
+
+        assert_eq!(from(2).dispatch().status(), Status::Ok);
+    }
+
+    #[test]
+    fn ip_headers_are_ignored_unless_trusted() {
+        let client = client(RateLimiter::new(1));
+        let spoofed = |addr: &'static str| {
+            client
+                .post("/write")
+                .remote(peer(1))
+                .header(Header::new("X-Real-IP", addr))
+        };
+
+        assert_eq!(spoofed("10.0.0.7").dispatch().status(), Status::Ok);
+        assert_eq!(
+            spoofed("10.0.0.8").dispatch().status(),
+            Status::TooManyRequests
+        );
+    }
+
+    #[test]
+    fn a_trusted_header_keys_on_the_address_the_proxy_added() {
+        let limiter = RateLimiter::new(1).trusting(Some("X-Forwarded-For".to_string()));
+        let client = client(limiter);
+        let via_proxy = |value: &'static str| {
+            client
+                .post("/write")
+                .remote(peer(1))
+                .header(Header::new("X-Forwarded-For", value))
+        };
+
+        assert_eq!(via_proxy("10.0.0.7").dispatch().status(), Status::Ok);
+        // The client may prepend whatever it likes; the last entry counts.
+        assert_eq!(
+            via_proxy("10.0.0.9, 10.0.0.7").dispatch().status(),
+            Status::TooManyRequests
+        );
+        assert_eq!(via_proxy("10.0.0.8").dispatch().status(), Status::Ok);
+    }
+}
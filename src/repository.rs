@@ -1,14 +1,155 @@
 use chrono::{DateTime, Utc};
-use diesel::pg::PgConnection;
+use diesel::connection::{AnsiTransactionManager, TransactionManager};
+use diesel::pg::{Pg, PgConnection};
 use diesel::prelude::*;
-use diesel::sql_types::{Array, BigInt, Nullable, Text, Timestamptz, Uuid as SqlUuid};
+use diesel::query_builder::{BoxedSqlQuery, SqlQuery};
+use diesel::result::DatabaseErrorKind;
+use diesel::sql_types::{
+    Array, BigInt, Bool, Integer, Nullable, Text, Timestamptz, Uuid as SqlUuid,
+};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::time::Duration;
 use uuid::Uuid;
 
+use crate::author_cache::AuthorCache;
 use crate::models::{
-    CreatedBy, NewPost, NewPostTag, NewPostWithTags, NewUser, PaginationMeta, Post,
-    PostWithUserAndTags, User,
+    escape_like, normalize_tags, slugify, Attachment, AuditEntry, Comment, CreatedBy, DumpPost,
+    DumpUser, IdVersion, ImportAction, ImportMode, ImportRequest, ImportSummary, NewAttachment,
+    NewAuditEntry, NewComment, NewFavorite, NewPost, NewPostTag, NewPostWithTags, NewUser,
+    NewWebhook, OnboardPost, Onboarded, PaginationMeta, Post, PostChanges, PostDetail, PostFilters,
+    PostRecord, PostSearchResult, PostSort, PostSortField, PostTag, PostWithUserAndTags,
+    PurgedPosts, Reassignment, SearchMatch, Setting, SortDirection, Stats, TagChanges, TagCount,
+    TagRename, UpdatedBy, User, UserWithStats, Webhook,
+};
+use crate::notifications::NEW_POST_CHANNEL;
+use crate::schema::{
+    audit_log, comments, favorites, post_attachments, posts, posts_tags, settings, users, webhooks,
 };
-use crate::schema::{posts, posts_tags, users};
+
+define_sql_function!(fn lower(x: Text) -> Text);
+
+/// Isolation level for a write transaction, set with `SET TRANSACTION
+/// ISOLATION LEVEL`. Transactions without one get Postgres' default,
+/// normally `READ COMMITTED`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IsolationLevel {
+    ReadCommitted,
+    RepeatableRead,
+    Serializable,
+}
+
+impl IsolationLevel {
+    fn sql(self) -> &'static str {
+        match self {
+            IsolationLevel::ReadCommitted => "READ COMMITTED",
+            IsolationLevel::RepeatableRead => "REPEATABLE READ",
+            IsolationLevel::Serializable => "SERIALIZABLE",
+        }
+    }
+}
+
+impl FromStr for IsolationLevel {
+    type Err = String;
+
+    /// The SQL name in any case, with spaces or underscores between words,
+    /// e.g. `serializable` or `REPEATABLE_READ`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim().replace('_', " ").to_ascii_uppercase();
+        [
+            IsolationLevel::ReadCommitted,
+            IsolationLevel::RepeatableRead,
+            IsolationLevel::Serializable,
+        ]
+        .into_iter()
+        .find(|level| level.sql() == value)
+        .ok_or_else(|| "expected read_committed, repeatable_read or serializable".to_string())
+    }
+}
+
+/// Sets `level` on the transaction `conn` has just opened; it must come
+/// before any other statement. Inside a nested transaction (a savepoint) the
+/// outer transaction's level already applies and Postgres would refuse the
+/// change, so nothing is done there.
+fn set_isolation_level(
+    conn: &mut PgConnection,
+    level: Option<IsolationLevel>,
+) -> Result<(), diesel::result::Error> {
+    let Some(level) = level else {
+        return Ok(());
+    };
+    let depth = AnsiTransactionManager::transaction_manager_status_mut(conn).transaction_depth()?;
+    if depth.is_some_and(|depth| depth.get() > 1) {
+        return Ok(());
+    }
+
+    diesel::sql_query(format!("SET TRANSACTION ISOLATION LEVEL {}", level.sql()))
+        .execute(conn)
+        .map(|_| ())
+}
+
+/// Attempts `with_retry` gives the create transactions before giving up.
+pub const TRANSACTION_ATTEMPTS: u32 = 3;
+/// Wait before the first retry, doubled for each one after it.
+const RETRY_BACKOFF: Duration = Duration::from_millis(5);
+
+/// Runs `f` in a transaction, running it again (up to `attempts` times in
+/// all) when Postgres aborts it with a serialization failure or a deadlock,
+/// which a fresh attempt usually gets past. Any other error is returned at
+/// once. `f` must be safe to run more than once.
+///
+/// Only a top-level transaction is retried. Inside another transaction `f`
+/// runs in a savepoint, and rolling back to a savepoint can't rescue a
+/// transaction Postgres has aborted, so the error goes straight to the
+/// outer transaction, which is the one to retry.
+pub fn with_retry<T>(
+    conn: &mut PgConnection,
+    attempts: u32,
+    mut f: impl FnMut(&mut PgConnection) -> Result<T, diesel::result::Error>,
+) -> Result<T, diesel::result::Error> {
+    let depth = AnsiTransactionManager::transaction_manager_status_mut(conn).transaction_depth()?;
+    if depth.is_some() {
+        return conn.transaction(f);
+    }
+
+    let mut attempt = 1;
+    loop {
+        match conn.transaction(&mut f) {
+            Err(error) if attempt < attempts && is_retryable(&error) => {
+                std::thread::sleep(RETRY_BACKOFF * 2u32.pow(attempt - 1));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Check constraint holding `posts_tags.tag` to `MAX_TAG_LENGTH` characters.
+pub const TAG_LENGTH_CONSTRAINT: &str = "posts_tags_tag_length";
+
+/// Whether the database refused a tag longer than `MAX_TAG_LENGTH`, which
+/// validation should normally have caught first.
+pub fn is_tag_too_long(error: &diesel::result::Error) -> bool {
+    matches!(
+        error,
+        diesel::result::Error::DatabaseError(DatabaseErrorKind::CheckViolation, info)
+            if info.constraint_name() == Some(TAG_LENGTH_CONSTRAINT)
+    )
+}
+
+fn is_retryable(error: &diesel::result::Error) -> bool {
+    match error {
+        diesel::result::Error::DatabaseError(DatabaseErrorKind::SerializationFailure, _) => true,
+        // Diesel has no kind for deadlocks (40P01) and doesn't expose the
+        // SQLSTATE, so go by Postgres' message. That is only English with the
+        // default `lc_messages`; under another locale deadlocks are not
+        // retried.
+        diesel::result::Error::DatabaseError(_, info) => {
+            info.message().contains("deadlock detected")
+        }
+        _ => false,
+    }
+}
 
 #[derive(QueryableByName, Debug)]
 struct CountResult {
@@ -16,6 +157,16 @@ struct CountResult {
     count: i64,
 }
 
+#[derive(QueryableByName, Debug)]
+struct ArchiveCountResult {
+    #[diesel(sql_type = Integer)]
+    year: i32,
+    #[diesel(sql_type = Integer)]
+    month: i32,
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
 #[derive(QueryableByName, Debug)]
 struct PostWithTagsQueryResult {
     #[diesel(sql_type = SqlUuid)]
@@ -26,6 +177,14 @@ struct PostWithTagsQueryResult {
     body: String,
     #[diesel(sql_type = Timestamptz)]
     created_at: DateTime<Utc>,
+    #[diesel(sql_type = Timestamptz)]
+    updated_at: DateTime<Utc>,
+    #[diesel(sql_type = Bool)]
+    featured: bool,
+    #[diesel(sql_type = BigInt)]
+    view_count: i64,
+    #[diesel(sql_type = BigInt)]
+    comment_count: i64,
     #[diesel(sql_type = Nullable<SqlUuid>)]
     user_id: Option<Uuid>,
     #[diesel(sql_type = Nullable<Text>)]
@@ -38,47 +197,562 @@ struct PostWithTagsQueryResult {
     tags: Vec<Option<String>>,
 }
 
+#[derive(QueryableByName, Debug)]
+struct PostSearchRow {
+    #[diesel(sql_type = SqlUuid)]
+    id: Uuid,
+    #[diesel(sql_type = Text)]
+    title: String,
+}
+
+#[derive(QueryableByName, Debug)]
+struct PostRowResult {
+    #[diesel(sql_type = SqlUuid)]
+    id: Uuid,
+    #[diesel(sql_type = Text)]
+    title: String,
+    #[diesel(sql_type = Text)]
+    body: String,
+    #[diesel(sql_type = SqlUuid)]
+    created_by: Uuid,
+    #[diesel(sql_type = Timestamptz)]
+    created_at: DateTime<Utc>,
+    #[diesel(sql_type = Timestamptz)]
+    updated_at: DateTime<Utc>,
+    #[diesel(sql_type = Bool)]
+    featured: bool,
+    #[diesel(sql_type = BigInt)]
+    view_count: i64,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    comment_count: Option<i64>,
+    #[diesel(sql_type = Array<Nullable<Text>>)]
+    tags: Vec<Option<String>>,
+}
+
+impl PostRowResult {
+    fn with_author(self, created_by: Option<CreatedBy>) -> PostWithUserAndTags {
+        PostWithUserAndTags {
+            id: self.id,
+            title: self.title,
+            body: self.body,
+            created_by,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            featured: self.featured,
+            view_count: self.view_count,
+            comment_count: self.comment_count,
+            tags: self.tags.into_iter().flatten().collect(),
+        }
+    }
+}
+
+/// Authors for `ids`, taken from `cache` where possible. The rest are loaded
+/// in one query and added to the cache.
+fn resolve_authors(
+    conn: &mut PgConnection,
+    cache: Option<&AuthorCache>,
+    ids: impl IntoIterator<Item = Uuid>,
+) -> Result<HashMap<Uuid, CreatedBy>, diesel::result::Error> {
+    let mut authors = HashMap::new();
+    let mut misses = Vec::new();
+    for id in ids {
+        if authors.contains_key(&id) || misses.contains(&id) {
+            continue;
+        }
+        match cache.and_then(|cache| cache.get(id)) {
+            Some(author) => {
+                authors.insert(id, author);
+            }
+            None => misses.push(id),
+        }
+    }
+
+    if !misses.is_empty() {
+        for user in UserRepository::find_by_ids(conn, &misses)? {
+            let author = CreatedBy {
+                user_id: user.id,
+                username: user.username,
+                first_name: user.first_name,
+                last_name: Some(user.last_name),
+            };
+            if let Some(cache) = cache {
+                cache.insert(author.clone());
+            }
+            authors.insert(author.user_id, author);
+        }
+    }
+
+    Ok(authors)
+}
+
+impl From<PostWithTagsQueryResult> for PostWithUserAndTags {
+    fn from(result: PostWithTagsQueryResult) -> Self {
+        let created_by = if let (Some(user_id), Some(username), Some(first_name)) =
+            (result.user_id, result.username, result.first_name)
+        {
+            Some(CreatedBy {
+                user_id,
+                username,
+                first_name,
+                last_name: result.last_name,
+            })
+        } else {
+            None
+        };
+
+        let tags: Vec<String> = result.tags.into_iter().flatten().collect();
+
+        PostWithUserAndTags {
+            id: result.id,
+            title: result.title,
+            body: result.body,
+            created_by,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+            featured: result.featured,
+            view_count: result.view_count,
+            comment_count: Some(result.comment_count),
+            tags,
+        }
+    }
+}
+
+// Live comments per post, aggregated before joining so the count is not
+// multiplied by tag rows.
+const COMMENT_COUNTS_JOIN_SQL: &str = r#"
+            LEFT JOIN (
+                SELECT fk_post_id, COUNT(*) as comment_count
+                FROM comments
+                WHERE deleted_at IS NULL
+                GROUP BY fk_post_id
+            ) cc ON cc.fk_post_id = p.id
+"#;
+
+// Post list query: tags aggregated, authors resolved afterwards by
+// `resolve_authors` so they can come from the `AuthorCache`. Comments are
+// only counted when asked for; otherwise `comment_count` is NULL.
+fn post_with_tags_sql(with_comment_count: bool) -> String {
+    let (comment_count, comment_counts_join) = if with_comment_count {
+        (
+            "COALESCE(MAX(cc.comment_count), 0)",
+            COMMENT_COUNTS_JOIN_SQL,
+        )
+    } else {
+        ("NULL::bigint", "")
+    };
+    format!(
+        r#"
+            SELECT
+                p.id,
+                p.title,
+                p.body,
+                p.created_by,
+                p.created_at,
+                p.updated_at,
+                p.featured,
+                p.view_count,
+                {comment_count} as comment_count,
+                COALESCE(ARRAY_AGG(DISTINCT pt.tag ORDER BY pt.tag) FILTER (WHERE pt.tag IS NOT NULL), '{{}}') as tags
+            FROM posts p
+            LEFT JOIN posts_tags pt ON p.id = pt.fk_post_id
+            {comment_counts_join}
+"#
+    )
+}
+
+const POST_WITH_TAGS_GROUP_BY_SQL: &str = r#"
+            GROUP BY p.id, p.title, p.body, p.created_by, p.created_at, p.updated_at, p.featured, p.view_count
+"#;
+
+// Main query with array aggregation for tags and LEFT JOIN for users.
+// Callers put their WHERE clause between the select and the GROUP BY.
+// Always counts comments, joined like `COMMENT_COUNTS_JOIN_SQL`.
+const POST_WITH_USER_AND_TAGS_SQL: &str = r#"
+            SELECT 
+                p.id,
+                p.title,
+                p.body,
+                p.created_at,
+                p.updated_at,
+                p.featured,
+                p.view_count,
+                u.id as user_id,
+                u.username,
+                u.first_name,
+                u.last_name,
+                COALESCE(cc.comment_count, 0) as comment_count,
+                COALESCE(ARRAY_AGG(DISTINCT pt.tag ORDER BY pt.tag) FILTER (WHERE pt.tag IS NOT NULL), '{}') as tags
+            FROM posts p
+            LEFT JOIN users u ON p.created_by = u.id
+            LEFT JOIN posts_tags pt ON p.id = pt.fk_post_id
+            LEFT JOIN (
+                SELECT fk_post_id, COUNT(*) as comment_count
+                FROM comments
+                WHERE deleted_at IS NULL
+                GROUP BY fk_post_id
+            ) cc ON cc.fk_post_id = p.id
+"#;
+
+const POST_WITH_USER_AND_TAGS_GROUP_BY_SQL: &str = r#"
+            GROUP BY p.id, p.title, p.body, p.created_at, p.updated_at, p.featured, p.view_count, u.id, u.username, u.first_name, u.last_name, cc.comment_count
+"#;
+
+// WHERE clause shared by the count and list queries so both always filter the
+// same rows. Bind order: $1 search pattern, $2 exact tag, $3 author id,
+// $4 featured flag, $5 untagged only, $6 author name pattern. The search
+// term is compared with `search_match`'s operator.
+// Tag predicates use EXISTS rather than the joined `pt` rows so that a match
+// never trims the aggregated tag list or duplicates a post. Author fields are
+// matched through EXISTS too, so the list query needs no join on `users`.
+fn post_filters_sql(search_match: SearchMatch) -> String {
+    let op = search_match.operator();
+    format!(
+        r#"
+            WHERE ($1::text IS NULL OR 
+                   p.title {op} $1 OR 
+                   p.body {op} $1 OR 
+                   EXISTS (
+                   SELECT 1 FROM users su
+                   WHERE su.id = p.created_by AND (
+                         su.username {op} $1 OR
+                         su.first_name {op} $1 OR
+                         su.last_name {op} $1)) OR
+                   EXISTS (
+                   SELECT 1 FROM posts_tags st
+                   WHERE st.fk_post_id = p.id AND st.tag {op} $1))
+              AND ($2::text IS NULL OR EXISTS (
+                   SELECT 1 FROM posts_tags ft
+                   WHERE ft.fk_post_id = p.id AND ft.tag = $2))
+              AND ($3::uuid IS NULL OR p.created_by = $3)
+              AND ($4::bool IS NULL OR p.featured = $4)
+              AND (NOT $5::bool OR NOT EXISTS (
+                   SELECT 1 FROM posts_tags pt
+                   WHERE pt.fk_post_id = p.id))
+              AND ($6::text IS NULL OR EXISTS (
+                   SELECT 1 FROM users au
+                   WHERE au.id = p.created_by AND (
+                         au.username ILIKE $6 OR
+                         au.first_name ILIKE $6 OR
+                         au.last_name ILIKE $6)))
+"#
+    )
+}
+
+fn bind_post_filters<'a>(
+    query: BoxedSqlQuery<'a, Pg, SqlQuery>,
+    search_pattern: Option<&'a str>,
+    filters: &'a PostFilters,
+) -> BoxedSqlQuery<'a, Pg, SqlQuery> {
+    query
+        .bind::<Nullable<Text>, _>(search_pattern)
+        .bind::<Nullable<Text>, _>(filters.tag_term())
+        .bind::<Nullable<SqlUuid>, _>(filters.author_id)
+        .bind::<Nullable<Bool>, _>(filters.featured)
+        .bind::<Bool, _>(filters.untagged_only())
+        .bind::<Nullable<Text>, _>(
+            filters
+                .author_term()
+                .map(|name| format!("%{}%", escape_like(name))),
+        )
+}
+
+// Ties on the requested columns fall back to newest first, unless
+// `created_at` was requested itself, and every ordering ends in `p.id` so rows
+// sharing a timestamp or title keep a stable position between pages. Columns
+// come from the `PostSortField` allowlist, never from user input.
+fn order_by_sql(sort: &PostSort) -> String {
+    let mut columns: Vec<String> = sort
+        .0
+        .iter()
+        .map(|(field, direction)| {
+            let direction = match direction {
+                SortDirection::Asc => "ASC",
+                SortDirection::Desc => "DESC",
+            };
+            format!("{} {direction}", field.column())
+        })
+        .collect();
+    if !sort
+        .0
+        .iter()
+        .any(|(field, _)| *field == PostSortField::CreatedAt)
+    {
+        columns.push("p.created_at DESC".to_string());
+    }
+    columns.push("p.id DESC".to_string());
+    columns.join(", ")
+}
+
+fn search_pattern(filters: &PostFilters) -> Option<String> {
+    filters
+        .search_term()
+        .map(|s| filters.search_match().pattern(s))
+}
+
+/// Pagination metadata for `page` (1-based) of `limit` rows out of
+/// `total_docs`. `from`/`to` are the 1-based positions of the first and last
+/// row on the page, or both 0 when the page is empty, whether because there
+/// are no rows at all or because `page` is past the last one. With no rows,
+/// page 1 is still in range.
+fn compute_meta(page: i64, limit: i64, total_docs: i64) -> PaginationMeta {
+    let offset = (page - 1) * limit;
+    let total_pages = (total_docs + limit - 1) / limit;
+    let (from, to) = if page >= 1 && offset < total_docs {
+        (offset + 1, std::cmp::min(offset + limit, total_docs))
+    } else {
+        (0, 0)
+    };
+
+    PaginationMeta {
+        current_page: page,
+        per_page: limit,
+        from,
+        to,
+        total_pages,
+        total_docs,
+        out_of_range: page < 1 || page > total_pages.max(1),
+        requested_limit: None,
+        query_ms: None,
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> serde_json::Value {
+    serde_json::to_value(value).unwrap_or(serde_json::Value::Null)
+}
+
+/// Top-level fields that differ between two serialized records, as
+/// `{ "field": { "from": old, "to": new } }`.
+fn json_diff(before: &serde_json::Value, after: &serde_json::Value) -> serde_json::Value {
+    let mut diff = serde_json::Map::new();
+    if let (Some(before), Some(after)) = (before.as_object(), after.as_object()) {
+        for (field, new_value) in after {
+            let old_value = before.get(field).unwrap_or(&serde_json::Value::Null);
+            if old_value != new_value {
+                diff.insert(
+                    field.clone(),
+                    serde_json::json!({ "from": old_value, "to": new_value }),
+                );
+            }
+        }
+    }
+    serde_json::Value::Object(diff)
+}
+
 pub struct UserRepository;
 
 impl UserRepository {
+    /// The id is generated here, as `ids` says, rather than by the column
+    /// default.
     pub fn create(
         conn: &mut PgConnection,
         new_user: NewUser,
+        ids: IdVersion,
     ) -> Result<User, diesel::result::Error> {
-        let user = diesel::insert_into(users::table)
-            .values(&new_user)
-            .get_result(conn)?;
-        Ok(user)
+        conn.transaction::<User, diesel::result::Error, _>(|conn| {
+            // Set explicitly rather than trusting every schema to have the
+            // column default.
+            let user: User = diesel::insert_into(users::table)
+                .values((
+                    &new_user,
+                    users::id.eq(ids.new_id()),
+                    users::created_at.eq(diesel::dsl::now),
+                ))
+                .get_result(conn)?;
+
+            AuditRepository::record(
+                conn,
+                NewAuditEntry {
+                    entity_type: "user",
+                    entity_id: user.id,
+                    action: "create",
+                    actor_id: None,
+                    diff: Some(to_json(&user)),
+                },
+            )?;
+
+            Ok(user)
+        })
+    }
+
+    /// Creates the user and then their first post with its tags, all in one
+    /// transaction, so a failure at any step leaves neither behind. The
+    /// transaction runs at `isolation` and is retried as a whole, as for
+    /// `create_with_tags`.
+    pub fn create_with_first_post(
+        conn: &mut PgConnection,
+        new_user: NewUser,
+        post: OnboardPost,
+        ids: IdVersion,
+        isolation: Option<IsolationLevel>,
+    ) -> Result<Onboarded, diesel::result::Error> {
+        with_retry(conn, TRANSACTION_ATTEMPTS, |conn| {
+            set_isolation_level(conn, isolation)?;
+            let user = Self::create(conn, new_user.clone(), ids)?;
+            let post = PostRepository::create_with_tags(
+                conn,
+                post.clone().into_new_post(user.id),
+                ids,
+                isolation,
+            )?;
+            let detail =
+                PostRepository::find(conn, post.id)?.ok_or(diesel::result::Error::NotFound)?;
+
+            Ok(Onboarded {
+                user,
+                post: detail.post,
+            })
+        })
+    }
+
+    pub fn get(conn: &mut PgConnection, id: Uuid) -> Result<Option<User>, diesel::result::Error> {
+        users::table
+            .find(id)
+            .select(User::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// The user with their post count, which costs an aggregate over their
+    /// posts; prefer `get` when the count isn't needed.
+    pub fn get_with_stats(
+        conn: &mut PgConnection,
+        id: Uuid,
+    ) -> Result<Option<UserWithStats>, diesel::result::Error> {
+        let result = users::table
+            .left_join(posts::table)
+            .filter(users::id.eq(id))
+            .group_by(users::id)
+            .select((User::as_select(), diesel::dsl::count(posts::id.nullable())))
+            .first::<(User, i64)>(conn)
+            .optional()?;
+
+        Ok(result.map(|(user, post_count)| UserWithStats { user, post_count }))
+    }
+
+    /// Usernames are unique regardless of case, so `Alice` finds `alice`.
+    pub fn find_by_username(
+        conn: &mut PgConnection,
+        username: &str,
+    ) -> Result<Option<User>, diesel::result::Error> {
+        users::table
+            .filter(lower(users::username).eq(username.to_lowercase()))
+            .select(User::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Whether `username` is taken, ignoring case like the unique index.
+    pub fn username_exists(
+        conn: &mut PgConnection,
+        username: &str,
+    ) -> Result<bool, diesel::result::Error> {
+        diesel::select(diesel::dsl::exists(
+            users::table.filter(lower(users::username).eq(username.to_lowercase())),
+        ))
+        .get_result(conn)
+    }
+
+    /// One page of users in id order, starting after `after`, for exports.
+    pub fn export_page(
+        conn: &mut PgConnection,
+        after: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<DumpUser>, diesel::result::Error> {
+        let mut query = users::table
+            .select(User::as_select())
+            .order(users::id.asc())
+            .limit(limit)
+            .into_boxed();
+        if let Some(after) = after {
+            query = query.filter(users::id.gt(after));
+        }
+
+        Ok(query.load(conn)?.into_iter().map(Into::into).collect())
+    }
+
+    pub fn find_by_ids(
+        conn: &mut PgConnection,
+        ids: &[Uuid],
+    ) -> Result<Vec<User>, diesel::result::Error> {
+        users::table
+            .filter(users::id.eq_any(ids))
+            .select(User::as_select())
+            .load(conn)
+    }
+
+    /// `find_by_ids` with each user's post count.
+    pub fn find_by_ids_with_stats(
+        conn: &mut PgConnection,
+        ids: &[Uuid],
+    ) -> Result<Vec<UserWithStats>, diesel::result::Error> {
+        let rows = users::table
+            .left_join(posts::table)
+            .filter(users::id.eq_any(ids))
+            .group_by(users::id)
+            .select((User::as_select(), diesel::dsl::count(posts::id.nullable())))
+            .load::<(User, i64)>(conn)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(user, post_count)| UserWithStats { user, post_count })
+            .collect())
     }
 }
 
 pub struct PostRepository;
 
 impl PostRepository {
+    /// Like `UserRepository::create`, the id is generated as `ids` says. The
+    /// transaction runs at `isolation` (the server default when `None`) and
+    /// is retried on serialization failures and deadlocks.
     pub fn create_with_tags(
         conn: &mut PgConnection,
         new_post_with_tags: NewPostWithTags,
+        ids: IdVersion,
+        isolation: Option<IsolationLevel>,
     ) -> Result<Post, diesel::result::Error> {
-        conn.transaction::<Post, diesel::result::Error, _>(|conn| {
+        let post = with_retry(conn, TRANSACTION_ATTEMPTS, |conn| {
+            set_isolation_level(conn, isolation)?;
+
             // Create the post first
             let new_post = NewPost {
-                title: new_post_with_tags.title,
-                body: new_post_with_tags.body,
+                title: new_post_with_tags.title.clone(),
+                body: new_post_with_tags.body.clone(),
                 created_by: new_post_with_tags.created_by,
             };
 
             let post = diesel::insert_into(posts::table)
-                .values(&new_post)
-                .get_result::<Post>(conn)?;
+                .values((
+                    &new_post,
+                    posts::id.eq(ids.new_id()),
+                    posts::created_at.eq(diesel::dsl::now),
+                    posts::updated_at.eq(diesel::dsl::now),
+                ))
+                .returning(Post::as_returning())
+                .get_result(conn)?;
+
+            AuditRepository::record(
+                conn,
+                NewAuditEntry {
+                    entity_type: "post",
+                    entity_id: post.id,
+                    action: "create",
+                    actor_id: Some(post.created_by),
+                    diff: Some(serde_json::json!({
+                        "post": to_json(&post),
+                        "tags": new_post_with_tags.tags,
+                    })),
+                },
+            )?;
 
             // Create the tags if any
             if !new_post_with_tags.tags.is_empty() {
                 let post_tags: Vec<NewPostTag> = new_post_with_tags
                     .tags
-                    .into_iter()
+                    .iter()
                     .map(|tag| NewPostTag {
                         fk_post_id: post.id,
-                        tag,
+                        tag: tag.clone(),
                     })
                     .collect();
 
@@ -88,113 +762,2974 @@ impl PostRepository {
             }
 
             Ok(post)
-        })
+        })?;
+
+        // Announce the post only once it is committed so listeners can read
+        // it. The post already exists, so a failed NOTIFY must not fail it.
+        let _ = diesel::sql_query("SELECT pg_notify($1, $2)")
+            .bind::<Text, _>(NEW_POST_CHANNEL)
+            .bind::<Text, _>(post.id.to_string())
+            .execute(conn);
+
+        Ok(post)
     }
 
-    pub fn find_with_user_and_tags(
+    pub fn find_by_id(
         conn: &mut PgConnection,
-        page: i64,
-        limit: i64,
-        search: Option<&str>,
-    ) -> Result<(Vec<PostWithUserAndTags>, PaginationMeta), diesel::result::Error> {
-        let offset = (page - 1) * limit;
+        id: Uuid,
+    ) -> Result<Option<Post>, diesel::result::Error> {
+        posts::table
+            .find(id)
+            .select(Post::as_select())
+            .first(conn)
+            .optional()
+    }
 
-        // Build the count query using Diesel's sql_query with proper bindings
-        let count_sql = r#"
-            SELECT COUNT(DISTINCT p.id)
-            FROM posts p
-            LEFT JOIN users u ON p.created_by = u.id
-            LEFT JOIN posts_tags pt ON p.id = pt.fk_post_id
-            WHERE ($1::text IS NULL OR 
-                   p.title ILIKE $1 OR 
-                   p.body ILIKE $1 OR 
-                   u.username ILIKE $1 OR 
-                   u.first_name ILIKE $1 OR 
-                   u.last_name ILIKE $1 OR
-                   pt.tag ILIKE $1)
-        "#;
-
-        let search_pattern = search.map(|s| format!("%{s}%"));
-        let count_result: CountResult = diesel::sql_query(count_sql)
-            .bind::<Nullable<Text>, _>(search_pattern.as_deref())
-            .get_result(conn)?;
-        let total_docs = count_result.count;
+    pub fn find(
+        conn: &mut PgConnection,
+        id: Uuid,
+    ) -> Result<Option<PostDetail>, diesel::result::Error> {
+        let detail_sql = format!(
+            r#"
+            {POST_WITH_USER_AND_TAGS_SQL}
+            WHERE p.id = $1
+            {POST_WITH_USER_AND_TAGS_GROUP_BY_SQL}
+        "#
+        );
 
-        let total_pages = (total_docs + limit - 1) / limit;
+        let result: Option<PostWithTagsQueryResult> = diesel::sql_query(detail_sql)
+            .bind::<SqlUuid, _>(id)
+            .get_result(conn)
+            .optional()?;
+        let Some(result) = result else {
+            return Ok(None);
+        };
 
-        // Main query with array aggregation for tags and LEFT JOIN for users
-        // This uses Diesel's sql_query but only for the ARRAY_AGG part
-        let main_sql = r#"
-            SELECT 
-                p.id,
-                p.title,
-                p.body,
-                p.created_at,
-                u.id as user_id,
-                u.username,
-                u.first_name,
-                u.last_name,
-                COALESCE(ARRAY_AGG(DISTINCT pt.tag) FILTER (WHERE pt.tag IS NOT NULL), '{}') as tags
-            FROM posts p
-            LEFT JOIN users u ON p.created_by = u.id
-            LEFT JOIN posts_tags pt ON p.id = pt.fk_post_id
-            WHERE ($1::text IS NULL OR 
-                   p.title ILIKE $1 OR 
-                   p.body ILIKE $1 OR 
-                   u.username ILIKE $1 OR 
-                   u.first_name ILIKE $1 OR 
-                   u.last_name ILIKE $1 OR
-                   pt.tag ILIKE $1)
-            GROUP BY p.id, p.title, p.body, p.created_at, u.id, u.username, u.first_name, u.last_name
-            ORDER BY p.created_at DESC
-            LIMIT $2 OFFSET $3
-        "#;
+        let updated_by = posts::table
+            .inner_join(users::table.on(posts::updated_by.eq(users::id.nullable())))
+            .filter(posts::id.eq(id))
+            .select((users::id, users::username))
+            .first::<(Uuid, String)>(conn)
+            .optional()?
+            .map(|(user_id, username)| UpdatedBy { user_id, username });
 
-        let results: Vec<PostWithTagsQueryResult> = diesel::sql_query(main_sql)
-            .bind::<Nullable<Text>, _>(search_pattern.as_deref())
-            .bind::<BigInt, _>(limit)
-            .bind::<BigInt, _>(offset)
+        let attachments = post_attachments::table
+            .filter(post_attachments::fk_post_id.eq(id))
+            .select(Attachment::as_select())
+            .order((
+                post_attachments::created_at.asc(),
+                post_attachments::id.asc(),
+            ))
             .load(conn)?;
 
-        // Transform results into PostWithUserAndTags structs
-        let posts_with_users_and_tags = results
-            .into_iter()
-            .map(|result| {
-                let created_by = if let (Some(user_id), Some(username), Some(first_name)) =
-                    (result.user_id, result.username, result.first_name)
-                {
-                    Some(CreatedBy {
-                        user_id,
-                        username,
-                        first_name,
-                        last_name: result.last_name,
-                    })
-                } else {
-                    None
-                };
+        Ok(Some(PostDetail {
+            post: result.into(),
+            updated_by,
+            attachments,
+        }))
+    }
 
-                let tags: Vec<String> = result.tags.into_iter().flatten().collect();
+    pub fn update(
+        conn: &mut PgConnection,
+        id: Uuid,
+        changes: PostChanges,
+    ) -> Result<Option<Post>, diesel::result::Error> {
+        conn.transaction::<Option<Post>, diesel::result::Error, _>(|conn| {
+            let Some(before) = posts::table
+                .find(id)
+                .select(Post::as_select())
+                .for_update()
+                .first(conn)
+                .optional()?
+            else {
+                return Ok(None);
+            };
 
-                PostWithUserAndTags {
-                    id: result.id,
-                    title: result.title,
-                    body: result.body,
-                    created_by,
-                    created_at: result.created_at,
-                    tags,
-                }
-            })
-            .collect();
+            let post = diesel::update(posts::table.find(id))
+                .set((&changes, posts::updated_at.eq(diesel::dsl::now)))
+                .returning(Post::as_returning())
+                .get_result(conn)?;
 
-        let meta = PaginationMeta {
-            current_page: page,
-            per_page: limit,
-            from: offset + 1,
-            to: std::cmp::min(offset + limit, total_docs),
-            total_pages,
-            total_docs,
-        };
+            AuditRepository::record(
+                conn,
+                NewAuditEntry {
+                    entity_type: "post",
+                    entity_id: post.id,
+                    action: "update",
+                    actor_id: changes.updated_by,
+                    diff: Some(json_diff(&to_json(&before), &to_json(&post))),
+                },
+            )?;
 
-        Ok((posts_with_users_and_tags, meta))
+            Ok(Some(post))
+        })
+    }
+
+    /// Makes `author_id` the post's author, auditing the change. A missing
+    /// author is detected by the foreign key on `created_by` rather than a
+    /// separate lookup, so it can't be deleted in between.
+    pub fn reassign(
+        conn: &mut PgConnection,
+        post_id: Uuid,
+        author_id: Uuid,
+    ) -> Result<Reassignment, diesel::result::Error> {
+        let result = conn.transaction::<Reassignment, diesel::result::Error, _>(|conn| {
+            let Some(previous) = posts::table
+                .find(post_id)
+                .select(posts::created_by)
+                .for_update()
+                .first::<Uuid>(conn)
+                .optional()?
+            else {
+                return Ok(Reassignment::PostNotFound);
+            };
+
+            let post = diesel::update(posts::table.find(post_id))
+                .set((
+                    posts::created_by.eq(author_id),
+                    posts::updated_at.eq(diesel::dsl::now),
+                ))
+                .returning(Post::as_returning())
+                .get_result(conn)?;
+
+            AuditRepository::record(
+                conn,
+                NewAuditEntry {
+                    entity_type: "post",
+                    entity_id: post_id,
+                    action: "update",
+                    actor_id: None,
+                    diff: Some(serde_json::json!({
+                        "created_by": { "from": previous, "to": author_id }
+                    })),
+                },
+            )?;
+
+            Ok(Reassignment::Reassigned(post))
+        });
+
+        match result {
+            Err(diesel::result::Error::DatabaseError(
+                DatabaseErrorKind::ForeignKeyViolation,
+                _,
+            )) => Ok(Reassignment::AuthorNotFound),
+            other => other,
+        }
+    }
+
+    /// Hard-deletes every post by `user_id` together with their tags,
+    /// comments, favorites and attachments in one transaction, and audits
+    /// the purge against the user. The user row itself is kept. Returns
+    /// `None` when the user does not exist.
+    pub fn delete_all_for_user(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+    ) -> Result<Option<PurgedPosts>, diesel::result::Error> {
+        conn.transaction::<Option<PurgedPosts>, diesel::result::Error, _>(|conn| {
+            let exists = users::table
+                .find(user_id)
+                .select(users::id)
+                .for_update()
+                .first::<Uuid>(conn)
+                .optional()?
+                .is_some();
+            if !exists {
+                return Ok(None);
+            }
+
+            let post_ids: Vec<Uuid> = posts::table
+                .filter(posts::created_by.eq(user_id))
+                .select(posts::id)
+                .for_update()
+                .load(conn)?;
+
+            // The foreign keys cascade, but deleting children explicitly
+            // lets us report what went.
+            let purged = PurgedPosts {
+                tags: diesel::delete(
+                    posts_tags::table.filter(posts_tags::fk_post_id.eq_any(&post_ids)),
+                )
+                .execute(conn)?,
+                comments: diesel::delete(
+                    comments::table.filter(comments::fk_post_id.eq_any(&post_ids)),
+                )
+                .execute(conn)?,
+                favorites: diesel::delete(
+                    favorites::table.filter(favorites::fk_post_id.eq_any(&post_ids)),
+                )
+                .execute(conn)?,
+                attachments: diesel::delete(
+                    post_attachments::table.filter(post_attachments::fk_post_id.eq_any(&post_ids)),
+                )
+                .execute(conn)?,
+                posts: diesel::delete(posts::table.filter(posts::id.eq_any(&post_ids)))
+                    .execute(conn)?,
+            };
+
+            AuditRepository::record(
+                conn,
+                NewAuditEntry {
+                    entity_type: "user",
+                    entity_id: user_id,
+                    action: "delete_posts",
+                    actor_id: None,
+                    diff: Some(serde_json::json!({
+                        "post_ids": post_ids,
+                        "deleted": to_json(&purged),
+                    })),
+                },
+            )?;
+
+            Ok(Some(purged))
+        })
+    }
+
+    /// Flips the post's `featured` flag and audits the change. Returns
+    /// `None` when the post does not exist.
+    pub fn toggle_featured(
+        conn: &mut PgConnection,
+        id: Uuid,
+    ) -> Result<Option<Post>, diesel::result::Error> {
+        conn.transaction::<Option<Post>, diesel::result::Error, _>(|conn| {
+            let Some(featured) = posts::table
+                .find(id)
+                .select(posts::featured)
+                .for_update()
+                .first::<bool>(conn)
+                .optional()?
+            else {
+                return Ok(None);
+            };
+
+            let post = diesel::update(posts::table.find(id))
+                .set((
+                    posts::featured.eq(!featured),
+                    posts::updated_at.eq(diesel::dsl::now),
+                ))
+                .returning(Post::as_returning())
+                .get_result(conn)?;
+
+            AuditRepository::record(
+                conn,
+                NewAuditEntry {
+                    entity_type: "post",
+                    entity_id: id,
+                    action: "update",
+                    actor_id: None,
+                    diff: Some(serde_json::json!({
+                        "featured": { "from": featured, "to": post.featured }
+                    })),
+                },
+            )?;
+
+            Ok(Some(post))
+        })
+    }
+
+    /// Replaces the post's tags with `tags` (already normalized), deleting
+    /// and inserting only the difference so unchanged rows are untouched.
+    /// Returns `None` when the post does not exist.
+    pub fn set_tags(
+        conn: &mut PgConnection,
+        post_id: Uuid,
+        tags: &[String],
+        updated_by: Option<Uuid>,
+    ) -> Result<Option<TagChanges>, diesel::result::Error> {
+        conn.transaction::<Option<TagChanges>, diesel::result::Error, _>(|conn| {
+            // Locking the post serializes concurrent saves of its tag list.
+            let post = posts::table
+                .find(post_id)
+                .select(posts::id)
+                .for_update()
+                .first::<Uuid>(conn)
+                .optional()?;
+            if post.is_none() {
+                return Ok(None);
+            }
+
+            let current: Vec<String> = posts_tags::table
+                .filter(posts_tags::fk_post_id.eq(post_id))
+                .select(posts_tags::tag)
+                .order(posts_tags::tag.asc())
+                .load(conn)?;
+
+            let removed: Vec<String> = current
+                .iter()
+                .filter(|tag| !tags.contains(tag))
+                .cloned()
+                .collect();
+            let mut added: Vec<String> = tags
+                .iter()
+                .filter(|tag| !current.contains(tag))
+                .cloned()
+                .collect();
+            added.sort();
+
+            if !removed.is_empty() {
+                diesel::delete(
+                    posts_tags::table
+                        .filter(posts_tags::fk_post_id.eq(post_id))
+                        .filter(posts_tags::tag.eq_any(&removed)),
+                )
+                .execute(conn)?;
+            }
+            if !added.is_empty() {
+                let new_tags: Vec<NewPostTag> = added
+                    .iter()
+                    .map(|tag| NewPostTag {
+                        fk_post_id: post_id,
+                        tag: tag.clone(),
+                    })
+                    .collect();
+                diesel::insert_into(posts_tags::table)
+                    .values(&new_tags)
+                    .execute(conn)?;
+            }
+
+            let mut tags = tags.to_vec();
+            tags.sort();
+
+            if !added.is_empty() || !removed.is_empty() {
+                diesel::update(posts::table.find(post_id))
+                    .set((
+                        posts::updated_by.eq(updated_by),
+                        posts::updated_at.eq(diesel::dsl::now),
+                    ))
+                    .execute(conn)?;
+                AuditRepository::record(
+                    conn,
+                    NewAuditEntry {
+                        entity_type: "post",
+                        entity_id: post_id,
+                        action: "update",
+                        actor_id: updated_by,
+                        diff: Some(serde_json::json!({
+                            "tags": { "from": current, "to": tags }
+                        })),
+                    },
+                )?;
+            }
+
+            Ok(Some(TagChanges {
+                tags,
+                added,
+                removed,
+            }))
+        })
+    }
+
+    /// Registers an attachment on a post. Returns `None` when the post does
+    /// not exist.
+    pub fn add_attachment(
+        conn: &mut PgConnection,
+        attachment: NewAttachment,
+    ) -> Result<Option<Attachment>, diesel::result::Error> {
+        conn.transaction::<Option<Attachment>, diesel::result::Error, _>(|conn| {
+            // Attachments are part of the post detail, so they count as a
+            // modification of the post.
+            let touched = diesel::update(posts::table.find(attachment.fk_post_id))
+                .set(posts::updated_at.eq(diesel::dsl::now))
+                .execute(conn)?;
+            if touched == 0 {
+                return Ok(None);
+            }
+
+            diesel::insert_into(post_attachments::table)
+                .values(&attachment)
+                .returning(Attachment::as_returning())
+                .get_result(conn)
+                .map(Some)
+        })
+    }
+
+    /// One page of posts with their tags in id order, starting after
+    /// `after`, for exports.
+    pub fn export_page(
+        conn: &mut PgConnection,
+        after: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<DumpPost>, diesel::result::Error> {
+        let mut query = posts::table
+            .select(Post::as_select())
+            .order(posts::id.asc())
+            .limit(limit)
+            .into_boxed();
+        if let Some(after) = after {
+            query = query.filter(posts::id.gt(after));
+        }
+        let page: Vec<Post> = query.load(conn)?;
+
+        let tags: Vec<PostTag> = PostTag::belonging_to(&page)
+            .select(PostTag::as_select())
+            .order(posts_tags::tag.asc())
+            .load(conn)?;
+
+        Ok(tags
+            .grouped_by(&page)
+            .into_iter()
+            .zip(page)
+            .map(|(tags, post)| DumpPost {
+                id: post.id,
+                title: post.title,
+                body: post.body,
+                created_by: post.created_by,
+                created_at: Some(post.created_at),
+                featured: post.featured,
+                tags: tags.into_iter().map(|t| t.tag).collect(),
+            })
+            .collect())
+    }
+
+    /// Like `export_page`, but as full records with their author, for the
+    /// NDJSON export. Comments are not counted.
+    pub fn export_page_with_user_and_tags(
+        conn: &mut PgConnection,
+        after: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<PostWithUserAndTags>, diesel::result::Error> {
+        let sql = format!(
+            r#"
+            {select_sql}
+            WHERE ($1::uuid IS NULL OR p.id > $1)
+            {POST_WITH_TAGS_GROUP_BY_SQL}
+            ORDER BY p.id
+            LIMIT $2
+        "#,
+            select_sql = post_with_tags_sql(false),
+        );
+        let rows: Vec<PostRowResult> = diesel::sql_query(sql)
+            .bind::<Nullable<SqlUuid>, _>(after)
+            .bind::<BigInt, _>(limit)
+            .load(conn)?;
+
+        let authors = resolve_authors(conn, None, rows.iter().map(|row| row.created_by))?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let author = authors.get(&row.created_by).cloned();
+                row.with_author(author)
+            })
+            .collect())
+    }
+
+    pub fn count(
+        conn: &mut PgConnection,
+        filters: &PostFilters,
+    ) -> Result<i64, diesel::result::Error> {
+        // Build the count query using Diesel's sql_query with proper bindings
+        let count_sql = format!(
+            r#"
+            SELECT COUNT(*)
+            FROM posts p
+            {filters_sql}
+        "#,
+            filters_sql = post_filters_sql(filters.search_match())
+        );
+
+        let search_pattern = search_pattern(filters);
+        let count_result: CountResult = bind_post_filters(
+            diesel::sql_query(count_sql).into_boxed(),
+            search_pattern.as_deref(),
+            filters,
+        )
+        .get_result(conn)?;
+        Ok(count_result.count)
+    }
+
+    /// The posts with the given ids, in the order the ids were given.
+    /// Unknown ids are skipped and repeated ids yield a single post.
+    pub fn find_many(
+        conn: &mut PgConnection,
+        ids: &[Uuid],
+    ) -> Result<Vec<PostWithUserAndTags>, diesel::result::Error> {
+        let many_sql = format!(
+            r#"
+            {POST_WITH_USER_AND_TAGS_SQL}
+            WHERE p.id = ANY($1)
+            {POST_WITH_USER_AND_TAGS_GROUP_BY_SQL}
+        "#
+        );
+
+        let results: Vec<PostWithTagsQueryResult> = diesel::sql_query(many_sql)
+            .bind::<Array<SqlUuid>, _>(ids)
+            .load(conn)?;
+        let mut by_id: HashMap<Uuid, PostWithUserAndTags> = results
+            .into_iter()
+            .map(|result| (result.id, result.into()))
+            .collect();
+
+        Ok(ids.iter().filter_map(|id| by_id.remove(id)).collect())
+    }
+
+    /// Counts one view of the post. Returns `false` when it does not exist.
+    pub fn record_view(conn: &mut PgConnection, id: Uuid) -> Result<bool, diesel::result::Error> {
+        let updated = diesel::update(posts::table.find(id))
+            .set(posts::view_count.eq(posts::view_count + 1))
+            .execute(conn)?;
+        Ok(updated > 0)
+    }
+
+    /// The most viewed posts created in the last `days` days, or ever when
+    /// `days` is 0. Ties go to the newer post.
+    pub fn popular(
+        conn: &mut PgConnection,
+        days: i32,
+        limit: i64,
+    ) -> Result<Vec<PostWithUserAndTags>, diesel::result::Error> {
+        let popular_sql = format!(
+            r#"
+            {POST_WITH_USER_AND_TAGS_SQL}
+            WHERE ($1 = 0 OR p.created_at >= NOW() - make_interval(days => $1))
+            {POST_WITH_USER_AND_TAGS_GROUP_BY_SQL}
+            ORDER BY p.view_count DESC, p.created_at DESC, p.id DESC
+            LIMIT $2
+        "#
+        );
+
+        let results: Vec<PostWithTagsQueryResult> = diesel::sql_query(popular_sql)
+            .bind::<Integer, _>(days)
+            .bind::<BigInt, _>(limit)
+            .load(conn)?;
+        Ok(results.into_iter().map(Into::into).collect())
+    }
+
+    /// Each author's newest post, newest first, at most `limit` of them.
+    /// `DISTINCT ON` picks one post per author; authors without posts simply
+    /// don't appear.
+    pub fn latest_per_author(
+        conn: &mut PgConnection,
+        limit: i64,
+    ) -> Result<Vec<PostWithUserAndTags>, diesel::result::Error> {
+        let latest_sql = format!(
+            r#"
+            {POST_WITH_USER_AND_TAGS_SQL}
+            WHERE p.id IN (
+                SELECT DISTINCT ON (lp.created_by) lp.id
+                FROM posts lp
+                ORDER BY lp.created_by, lp.created_at DESC, lp.id DESC
+            )
+            {POST_WITH_USER_AND_TAGS_GROUP_BY_SQL}
+            ORDER BY p.created_at DESC, p.id DESC
+            LIMIT $1
+        "#
+        );
+
+        let results: Vec<PostWithTagsQueryResult> = diesel::sql_query(latest_sql)
+            .bind::<BigInt, _>(limit)
+            .load(conn)?;
+        Ok(results.into_iter().map(Into::into).collect())
+    }
+
+    /// Post counts per `(year, month)`, newest month first. Months are taken
+    /// in UTC so the buckets don't shift with the session time zone.
+    pub fn archive_counts(
+        conn: &mut PgConnection,
+    ) -> Result<Vec<(i32, i32, i64)>, diesel::result::Error> {
+        let rows: Vec<ArchiveCountResult> = diesel::sql_query(
+            r#"
+            SELECT
+                EXTRACT(YEAR FROM bucket)::int AS year,
+                EXTRACT(MONTH FROM bucket)::int AS month,
+                COUNT(*) AS count
+            FROM (
+                SELECT date_trunc('month', created_at AT TIME ZONE 'UTC') AS bucket
+                FROM posts
+            ) p
+            GROUP BY bucket
+            ORDER BY bucket DESC
+        "#,
+        )
+        .load(conn)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.year, row.month, row.count))
+            .collect())
+    }
+
+    pub fn find_with_user_and_tags(
+        conn: &mut PgConnection,
+        page: i64,
+        limit: i64,
+        sort: &PostSort,
+        filters: &PostFilters,
+        authors: Option<&AuthorCache>,
+    ) -> Result<(Vec<PostWithUserAndTags>, PaginationMeta), diesel::result::Error> {
+        let offset = (page - 1) * limit;
+
+        let total_docs = Self::count(conn, filters)?;
+
+        // This uses Diesel's sql_query but only for the ARRAY_AGG part
+        let main_sql = format!(
+            r#"
+            {select_sql}
+            {filters_sql}
+            {POST_WITH_TAGS_GROUP_BY_SQL}
+            ORDER BY {order_by}
+            LIMIT $7 OFFSET $8
+        "#,
+            select_sql = post_with_tags_sql(filters.with_comment_count()),
+            filters_sql = post_filters_sql(filters.search_match()),
+            order_by = order_by_sql(sort)
+        );
+
+        let search_pattern = search_pattern(filters);
+        let results: Vec<PostRowResult> = bind_post_filters(
+            diesel::sql_query(main_sql).into_boxed(),
+            search_pattern.as_deref(),
+            filters,
+        )
+        .bind::<BigInt, _>(limit)
+        .bind::<BigInt, _>(offset)
+        .load(conn)?;
+
+        let authors = resolve_authors(conn, authors, results.iter().map(|row| row.created_by))?;
+        let posts_with_users_and_tags = results
+            .into_iter()
+            .map(|row| {
+                let author = authors.get(&row.created_by).cloned();
+                row.with_author(author)
+            })
+            .collect();
+
+        let meta = compute_meta(page, limit, total_docs);
+
+        Ok((posts_with_users_and_tags, meta))
+    }
+
+    /// Posts whose title or body contains `term` (case-insensitive, `%` and
+    /// `_` matched literally), most relevant first: an exact title, then
+    /// titles starting with `term`, then titles containing it, then body
+    /// matches, newest first within each.
+    pub fn search_titles(
+        conn: &mut PgConnection,
+        term: &str,
+        limit: i64,
+    ) -> Result<Vec<PostSearchResult>, diesel::result::Error> {
+        let term = escape_like(term);
+        let rows: Vec<PostSearchRow> = diesel::sql_query(
+            r#"
+            SELECT p.id, p.title
+            FROM posts p
+            WHERE p.title ILIKE $3 OR p.body ILIKE $3
+            ORDER BY CASE
+                       WHEN p.title ILIKE $1 THEN 0
+                       WHEN p.title ILIKE $2 THEN 1
+                       WHEN p.title ILIKE $3 THEN 2
+                       ELSE 3
+                     END,
+                     p.created_at DESC, p.id DESC
+            LIMIT $4
+            "#,
+        )
+        .bind::<Text, _>(&term)
+        .bind::<Text, _>(format!("{term}%"))
+        .bind::<Text, _>(format!("%{term}%"))
+        .bind::<BigInt, _>(limit)
+        .load(conn)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| PostSearchResult {
+                id: row.id,
+                slug: slugify(&row.title),
+                title: row.title,
+            })
+            .collect())
+    }
+
+    /// Tags starting with `prefix`, most used first. An empty prefix returns
+    /// the most used tags overall.
+    pub fn suggest_tags(
+        conn: &mut PgConnection,
+        prefix: &str,
+        limit: i64,
+    ) -> Result<Vec<TagCount>, diesel::result::Error> {
+        let mut query = posts_tags::table
+            .group_by(posts_tags::tag)
+            .select((posts_tags::tag, diesel::dsl::count_star()))
+            .order((diesel::dsl::count_star().desc(), posts_tags::tag.asc()))
+            .limit(limit)
+            .into_boxed();
+
+        if !prefix.is_empty() {
+            query = query.filter(posts_tags::tag.ilike(format!("{}%", escape_like(prefix))));
+        }
+
+        query.load(conn)
+    }
+
+    /// Every tag in use with its number of posts, most used first.
+    pub fn tag_counts(conn: &mut PgConnection) -> Result<Vec<TagCount>, diesel::result::Error> {
+        posts_tags::table
+            .group_by(posts_tags::tag)
+            .select((posts_tags::tag, diesel::dsl::count_star()))
+            .order((diesel::dsl::count_star().desc(), posts_tags::tag.asc()))
+            .load(conn)
+    }
+
+    /// Renames tag `from` to `to` on every post in one transaction. Posts
+    /// that already carry `to` just lose `from`, since a post can't have the
+    /// same tag twice. Touched posts get a new `updated_at`.
+    pub fn rename_tag(
+        conn: &mut PgConnection,
+        from: &str,
+        to: &str,
+    ) -> Result<TagRename, diesel::result::Error> {
+        conn.transaction::<TagRename, diesel::result::Error, _>(|conn| {
+            let affected: Vec<Uuid> = posts_tags::table
+                .filter(posts_tags::tag.eq(from))
+                .select(posts_tags::fk_post_id)
+                .for_update()
+                .load(conn)?;
+
+            let already_tagged: Vec<Uuid> = posts_tags::table
+                .filter(posts_tags::tag.eq(to))
+                .filter(posts_tags::fk_post_id.eq_any(&affected))
+                .select(posts_tags::fk_post_id)
+                .load(conn)?;
+
+            let merged = diesel::delete(
+                posts_tags::table
+                    .filter(posts_tags::tag.eq(from))
+                    .filter(posts_tags::fk_post_id.eq_any(&already_tagged)),
+            )
+            .execute(conn)?;
+            let renamed = diesel::update(posts_tags::table.filter(posts_tags::tag.eq(from)))
+                .set(posts_tags::tag.eq(to))
+                .execute(conn)?;
+
+            if !affected.is_empty() {
+                diesel::update(posts::table.filter(posts::id.eq_any(&affected)))
+                    .set(posts::updated_at.eq(diesel::dsl::now))
+                    .execute(conn)?;
+            }
+
+            Ok(TagRename {
+                from: from.to_string(),
+                to: to.to_string(),
+                renamed,
+                merged,
+            })
+        })
+    }
+
+    /// Removes `tag` from every post, returning how many posts had it.
+    /// Touched posts get a new `updated_at`.
+    pub fn delete_tag(conn: &mut PgConnection, tag: &str) -> Result<usize, diesel::result::Error> {
+        conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+            let affected: Vec<Uuid> =
+                diesel::delete(posts_tags::table.filter(posts_tags::tag.eq(tag)))
+                    .returning(posts_tags::fk_post_id)
+                    .get_results(conn)?;
+
+            if !affected.is_empty() {
+                diesel::update(posts::table.filter(posts::id.eq_any(&affected)))
+                    .set(posts::updated_at.eq(diesel::dsl::now))
+                    .execute(conn)?;
+            }
+            Ok(affected.len())
+        })
+    }
+
+    pub fn orphaned_tags(conn: &mut PgConnection) -> Result<Vec<PostTag>, diesel::result::Error> {
+        posts_tags::table
+            .filter(diesel::dsl::not(diesel::dsl::exists(
+                posts::table.filter(posts::id.eq(posts_tags::fk_post_id)),
+            )))
+            .order((posts_tags::fk_post_id, posts_tags::tag))
+            .select(PostTag::as_select())
+            .load(conn)
+    }
+
+    pub fn delete_orphaned_tags(conn: &mut PgConnection) -> Result<usize, diesel::result::Error> {
+        conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+            diesel::delete(
+                posts_tags::table.filter(diesel::dsl::not(diesel::dsl::exists(
+                    posts::table.filter(posts::id.eq(posts_tags::fk_post_id)),
+                ))),
+            )
+            .execute(conn)
+        })
+    }
+}
+
+pub struct AuditRepository;
+
+impl AuditRepository {
+    /// Appends an entry. Call it inside the transaction of the write being
+    /// audited so a rolled-back write leaves no trace.
+    pub fn record(
+        conn: &mut PgConnection,
+        entry: NewAuditEntry,
+    ) -> Result<(), diesel::result::Error> {
+        diesel::insert_into(audit_log::table)
+            .values(&entry)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn list(
+        conn: &mut PgConnection,
+        entity_id: Option<Uuid>,
+        page: i64,
+        limit: i64,
+    ) -> Result<(Vec<AuditEntry>, PaginationMeta), diesel::result::Error> {
+        let filtered = || {
+            let mut query = audit_log::table.into_boxed();
+            if let Some(entity_id) = entity_id {
+                query = query.filter(audit_log::entity_id.eq(entity_id));
+            }
+            query
+        };
+
+        let total_docs = filtered().count().get_result(conn)?;
+        let entries = filtered()
+            .order((audit_log::at.desc(), audit_log::id.desc()))
+            .limit(limit)
+            .offset((page - 1) * limit)
+            .select(AuditEntry::as_select())
+            .load(conn)?;
+
+        Ok((entries, compute_meta(page, limit, total_docs)))
+    }
+}
+
+pub struct FavoriteRepository;
+
+impl FavoriteRepository {
+    /// Returns whether the favorite was newly added; repeating it is a no-op.
+    pub fn add(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        post_id: Uuid,
+    ) -> Result<bool, diesel::result::Error> {
+        let inserted = diesel::insert_into(favorites::table)
+            .values(&NewFavorite {
+                fk_user_id: user_id,
+                fk_post_id: post_id,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(inserted > 0)
+    }
+
+    /// Returns whether a favorite was removed.
+    pub fn remove(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        post_id: Uuid,
+    ) -> Result<bool, diesel::result::Error> {
+        let removed = diesel::delete(favorites::table.find((user_id, post_id))).execute(conn)?;
+        Ok(removed > 0)
+    }
+
+    /// Posts favorited by `user_id`, most recently favorited first.
+    pub fn list_for_user(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        page: i64,
+        limit: i64,
+    ) -> Result<(Vec<PostWithUserAndTags>, PaginationMeta), diesel::result::Error> {
+        let offset = (page - 1) * limit;
+
+        let total_docs: i64 = favorites::table
+            .filter(favorites::fk_user_id.eq(user_id))
+            .count()
+            .get_result(conn)?;
+
+        let main_sql = format!(
+            r#"
+            {POST_WITH_USER_AND_TAGS_SQL}
+            JOIN favorites f ON f.fk_post_id = p.id AND f.fk_user_id = $1
+            {POST_WITH_USER_AND_TAGS_GROUP_BY_SQL}, f.created_at
+            ORDER BY f.created_at DESC, p.id DESC
+            LIMIT $2 OFFSET $3
+        "#
+        );
+
+        let results: Vec<PostWithTagsQueryResult> = diesel::sql_query(main_sql)
+            .bind::<SqlUuid, _>(user_id)
+            .bind::<BigInt, _>(limit)
+            .bind::<BigInt, _>(offset)
+            .load(conn)?;
+
+        let posts = results.into_iter().map(Into::into).collect();
+
+        Ok((posts, compute_meta(page, limit, total_docs)))
+    }
+}
+
+pub struct CommentRepository;
+
+impl CommentRepository {
+    pub fn create(
+        conn: &mut PgConnection,
+        comment: NewComment,
+    ) -> Result<Comment, diesel::result::Error> {
+        diesel::insert_into(comments::table)
+            .values(&comment)
+            .returning(Comment::as_returning())
+            .get_result(conn)
+    }
+
+    pub fn find_by_id(
+        conn: &mut PgConnection,
+        id: Uuid,
+    ) -> Result<Option<Comment>, diesel::result::Error> {
+        comments::table
+            .find(id)
+            .select(Comment::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Replaces the body of a comment that has not been deleted.
+    pub fn update_body(
+        conn: &mut PgConnection,
+        id: Uuid,
+        body: &str,
+    ) -> Result<Option<Comment>, diesel::result::Error> {
+        diesel::update(
+            comments::table
+                .find(id)
+                .filter(comments::deleted_at.is_null()),
+        )
+        .set(comments::body.eq(body))
+        .returning(Comment::as_returning())
+        .get_result(conn)
+        .optional()
+    }
+
+    /// Marks a comment deleted, keeping the row so replies stay threaded.
+    /// Returns `None` if it does not exist or was already deleted.
+    pub fn soft_delete(
+        conn: &mut PgConnection,
+        id: Uuid,
+    ) -> Result<Option<Comment>, diesel::result::Error> {
+        diesel::update(
+            comments::table
+                .find(id)
+                .filter(comments::deleted_at.is_null()),
+        )
+        .set(comments::deleted_at.eq(diesel::dsl::now))
+        .returning(Comment::as_returning())
+        .get_result(conn)
+        .optional()
+    }
+
+    /// Every comment on a post, oldest first, including deleted ones.
+    pub fn list_for_post(
+        conn: &mut PgConnection,
+        post_id: Uuid,
+    ) -> Result<Vec<Comment>, diesel::result::Error> {
+        comments::table
+            .filter(comments::fk_post_id.eq(post_id))
+            .select(Comment::as_select())
+            .order((comments::created_at.asc(), comments::id.asc()))
+            .load(conn)
+    }
+}
+
+pub struct SettingsRepository;
+
+impl SettingsRepository {
+    pub fn all(conn: &mut PgConnection) -> Result<Vec<Setting>, diesel::result::Error> {
+        settings::table.select(Setting::as_select()).load(conn)
+    }
+
+    pub fn get(
+        conn: &mut PgConnection,
+        key: &str,
+    ) -> Result<Option<Setting>, diesel::result::Error> {
+        settings::table
+            .find(key)
+            .select(Setting::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Inserts or overwrites `key`.
+    pub fn set(
+        conn: &mut PgConnection,
+        key: &str,
+        value: &str,
+    ) -> Result<Setting, diesel::result::Error> {
+        diesel::insert_into(settings::table)
+            .values((settings::key.eq(key), settings::value.eq(value)))
+            .on_conflict(settings::key)
+            .do_update()
+            .set((
+                settings::value.eq(value),
+                settings::updated_at.eq(diesel::dsl::now),
+            ))
+            .returning(Setting::as_returning())
+            .get_result(conn)
+    }
+}
+
+pub struct WebhookRepository;
+
+impl WebhookRepository {
+    pub fn create(
+        conn: &mut PgConnection,
+        webhook: NewWebhook,
+    ) -> Result<Webhook, diesel::result::Error> {
+        diesel::insert_into(webhooks::table)
+            .values(&webhook)
+            .returning(Webhook::as_returning())
+            .get_result(conn)
+    }
+
+    pub fn list(conn: &mut PgConnection) -> Result<Vec<Webhook>, diesel::result::Error> {
+        webhooks::table
+            .select(Webhook::as_select())
+            .order(webhooks::created_at.asc())
+            .load(conn)
+    }
+
+    pub fn for_event(
+        conn: &mut PgConnection,
+        event: &str,
+    ) -> Result<Vec<Webhook>, diesel::result::Error> {
+        webhooks::table
+            .filter(webhooks::event.eq(event))
+            .select(Webhook::as_select())
+            .load(conn)
+    }
+}
+
+pub struct ImportRepository;
+
+impl ImportRepository {
+    /// Restores users and posts with their original ids in one transaction;
+    /// any error rolls the whole import back. Existing ids are skipped or
+    /// overwritten according to `mode`.
+    pub fn import(
+        conn: &mut PgConnection,
+        request: &ImportRequest,
+    ) -> Result<ImportSummary, diesel::result::Error> {
+        conn.transaction::<ImportSummary, diesel::result::Error, _>(|conn| {
+            let mut summary = ImportSummary::default();
+
+            for user in &request.users {
+                let exists = diesel::select(diesel::dsl::exists(users::table.find(user.id)))
+                    .get_result::<bool>(conn)?;
+                let action = match (exists, request.mode) {
+                    (true, ImportMode::Skip) => {
+                        summary.users.skipped += 1;
+                        continue;
+                    }
+                    (true, ImportMode::Upsert) => {
+                        diesel::update(users::table.find(user.id))
+                            .set(user)
+                            .execute(conn)?;
+                        summary.users.updated += 1;
+                        "update"
+                    }
+                    (false, _) => {
+                        diesel::insert_into(users::table)
+                            .values(user)
+                            .execute(conn)?;
+                        summary.users.inserted += 1;
+                        "create"
+                    }
+                };
+
+                AuditRepository::record(
+                    conn,
+                    NewAuditEntry {
+                        entity_type: "user",
+                        entity_id: user.id,
+                        action,
+                        actor_id: None,
+                        diff: Some(to_json(user)),
+                    },
+                )?;
+            }
+
+            for post in &request.posts {
+                let exists = diesel::select(diesel::dsl::exists(posts::table.find(post.id)))
+                    .get_result::<bool>(conn)?;
+                let record = PostRecord {
+                    id: post.id,
+                    title: &post.title,
+                    body: &post.body,
+                    created_by: post.created_by,
+                    created_at: post.created_at,
+                    featured: post.featured,
+                };
+                let action = match (exists, request.mode) {
+                    (true, ImportMode::Skip) => {
+                        summary.posts.skipped += 1;
+                        continue;
+                    }
+                    (true, ImportMode::Upsert) => {
+                        diesel::update(posts::table.find(post.id))
+                            .set((&record, posts::updated_at.eq(diesel::dsl::now)))
+                            .execute(conn)?;
+                        diesel::delete(
+                            posts_tags::table.filter(posts_tags::fk_post_id.eq(post.id)),
+                        )
+                        .execute(conn)?;
+                        summary.posts.updated += 1;
+                        "update"
+                    }
+                    (false, _) => {
+                        diesel::insert_into(posts::table)
+                            .values(&record)
+                            .execute(conn)?;
+                        summary.posts.inserted += 1;
+                        "create"
+                    }
+                };
+
+                let post_tags: Vec<NewPostTag> = normalize_tags(&post.tags)
+                    .into_iter()
+                    .map(|tag| NewPostTag {
+                        fk_post_id: post.id,
+                        tag,
+                    })
+                    .collect();
+                diesel::insert_into(posts_tags::table)
+                    .values(&post_tags)
+                    .execute(conn)?;
+
+                AuditRepository::record(
+                    conn,
+                    NewAuditEntry {
+                        entity_type: "post",
+                        entity_id: post.id,
+                        action,
+                        actor_id: None,
+                        diff: Some(to_json(post)),
+                    },
+                )?;
+            }
+
+            Ok(summary)
+        })
+    }
+
+    /// What `import` would do with each user and post, in document order,
+    /// based on which ids already exist.
+    pub fn plan(
+        conn: &mut PgConnection,
+        request: &ImportRequest,
+    ) -> Result<(Vec<ImportAction>, Vec<ImportAction>), diesel::result::Error> {
+        let user_ids: Vec<Uuid> = request.users.iter().map(|user| user.id).collect();
+        let post_ids: Vec<Uuid> = request.posts.iter().map(|post| post.id).collect();
+        let existing_users: HashSet<Uuid> = users::table
+            .filter(users::id.eq_any(&user_ids))
+            .select(users::id)
+            .load::<Uuid>(conn)?
+            .into_iter()
+            .collect();
+        let existing_posts: HashSet<Uuid> = posts::table
+            .filter(posts::id.eq_any(&post_ids))
+            .select(posts::id)
+            .load::<Uuid>(conn)?
+            .into_iter()
+            .collect();
+
+        let actions = |ids: &[Uuid], existing: &HashSet<Uuid>| {
+            ids.iter()
+                .map(|id| ImportAction::for_row(existing.contains(id), request.mode))
+                .collect()
+        };
+        Ok((
+            actions(&user_ids, &existing_users),
+            actions(&post_ids, &existing_posts),
+        ))
+    }
+
+    /// Runs `import` in a transaction that is always rolled back, so the
+    /// database errors a real run would hit show up without storing
+    /// anything.
+    pub fn dry_run(
+        conn: &mut PgConnection,
+        request: &ImportRequest,
+    ) -> Result<ImportSummary, diesel::result::Error> {
+        let mut outcome = None;
+        let rolled_back = conn.transaction::<(), diesel::result::Error, _>(|conn| {
+            outcome = Some(Self::import(conn, request));
+            Err(diesel::result::Error::RollbackTransaction)
+        });
+
+        match rolled_back {
+            Err(diesel::result::Error::RollbackTransaction) | Ok(()) => {
+                outcome.expect("the transaction ran the import")
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+pub struct StatsRepository;
+
+impl StatsRepository {
+    pub fn summary(conn: &mut PgConnection) -> Result<Stats, diesel::result::Error> {
+        Ok(Stats {
+            users: users::table.count().get_result(conn)?,
+            posts: posts::table.count().get_result(conn)?,
+            tags: posts_tags::table
+                .select(diesel::dsl::count(posts_tags::tag).aggregate_distinct())
+                .get_result(conn)?,
+            comments: comments::table
+                .filter(comments::deleted_at.is_null())
+                .count()
+                .get_result(conn)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::models::{build_comment_tree, MAX_TAG_LENGTH};
+
+    fn page_bounds(meta: &PaginationMeta) -> (i64, i64, i64) {
+        (meta.from, meta.to, meta.total_pages)
+    }
+
+    #[test]
+    fn compute_meta_with_no_docs_is_an_empty_first_page() {
+        let meta = compute_meta(1, 10, 0);
+        assert_eq!(page_bounds(&meta), (0, 0, 0));
+        assert!(!meta.out_of_range);
+        assert!(compute_meta(2, 10, 0).out_of_range);
+        assert_eq!(
+            (meta.current_page, meta.per_page, meta.total_docs),
+            (1, 10, 0)
+        );
+    }
+
+    #[test]
+    fn compute_meta_with_exactly_one_full_page() {
+        assert_eq!(page_bounds(&compute_meta(1, 10, 10)), (1, 10, 1));
+        assert_eq!(page_bounds(&compute_meta(2, 10, 10)), (0, 0, 1));
+    }
+
+    #[test]
+    fn compute_meta_with_a_partial_last_page() {
+        assert_eq!(page_bounds(&compute_meta(1, 10, 25)), (1, 10, 3));
+        assert_eq!(page_bounds(&compute_meta(2, 10, 25)), (11, 20, 3));
+        assert_eq!(page_bounds(&compute_meta(3, 10, 25)), (21, 25, 3));
+        assert!(!compute_meta(3, 10, 25).out_of_range);
+        assert_eq!(page_bounds(&compute_meta(1, 1, 1)), (1, 1, 1));
+    }
+
+    #[test]
+    fn compute_meta_beyond_the_last_page_stays_within_total_docs() {
+        for page in [4, 5, 100] {
+            let meta = compute_meta(page, 10, 25);
+            assert_eq!(page_bounds(&meta), (0, 0, 3), "page {page}");
+            assert!(meta.to <= meta.total_docs);
+            assert_eq!(meta.current_page, page);
+            assert!(meta.out_of_range, "page {page}");
+        }
+    }
+
+    #[test]
+    fn compute_meta_before_the_first_page_is_empty() {
+        let meta = compute_meta(0, 10, 25);
+        assert_eq!(page_bounds(&meta), (0, 0, 3));
+        assert!(meta.out_of_range);
+    }
+
+    fn insert_user(conn: &mut PgConnection, username: &str) -> User {
+        UserRepository::create(
+            conn,
+            NewUser {
+                username: username.to_string(),
+                first_name: "Test".to_string(),
+                last_name: "User".to_string(),
+            },
+            IdVersion::default(),
+        )
+        .unwrap()
+    }
+
+    #[derive(QueryableByName)]
+    struct Attempt {
+        #[diesel(sql_type = Integer)]
+        attempt: i32,
+    }
+
+    #[test]
+    fn with_retry_reruns_only_serialization_failures_and_deadlocks() {
+        // Retries only happen outside any transaction, so this can't use the
+        // test transaction. It only writes to a temporary table, which goes
+        // away with the connection.
+        let Some(mut conn) = db::test::bare_connection() else {
+            return;
+        };
+        let error = |kind, message: &str| {
+            diesel::result::Error::DatabaseError(kind, Box::new(message.to_string()))
+        };
+        diesel::sql_query("CREATE TEMPORARY TABLE attempts (attempt INTEGER NOT NULL)")
+            .execute(&mut conn)
+            .unwrap();
+
+        // The first attempt's insert is rolled back with it.
+        let mut calls = 0;
+        with_retry(&mut conn, 3, |conn| {
+            calls += 1;
+            diesel::sql_query("INSERT INTO attempts VALUES ($1)")
+                .bind::<Integer, _>(calls)
+                .execute(conn)?;
+            if calls == 1 {
+                return Err(error(
+                    DatabaseErrorKind::SerializationFailure,
+                    "could not serialize access",
+                ));
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(calls, 2);
+        let committed: Vec<i32> = diesel::sql_query("SELECT attempt FROM attempts")
+            .load::<Attempt>(&mut conn)
+            .unwrap()
+            .into_iter()
+            .map(|row| row.attempt)
+            .collect();
+        assert_eq!(committed, [2]);
+
+        let mut calls = 0;
+        let deadlock = with_retry(&mut conn, 3, |_| {
+            calls += 1;
+            match calls {
+                1 => Err(error(DatabaseErrorKind::Unknown, "deadlock detected")),
+                _ => Ok(()),
+            }
+        });
+        assert!(deadlock.is_ok());
+        assert_eq!(calls, 2);
+
+        let mut calls = 0;
+        let exhausted = with_retry(&mut conn, 3, |_| -> Result<(), _> {
+            calls += 1;
+            Err(error(DatabaseErrorKind::SerializationFailure, "again"))
+        });
+        assert!(exhausted.is_err());
+        assert_eq!(calls, 3);
+
+        let mut calls = 0;
+        let duplicate = with_retry(&mut conn, 3, |_| -> Result<(), _> {
+            calls += 1;
+            Err(error(DatabaseErrorKind::UniqueViolation, "duplicate key"))
+        });
+        assert!(matches!(
+            duplicate,
+            Err(diesel::result::Error::DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                _
+            ))
+        ));
+        assert_eq!(calls, 1);
+
+        // Nested in a transaction, the failure is left to the outer one.
+        let mut calls = 0;
+        let nested = conn.transaction(|conn| {
+            with_retry(conn, 3, |_| -> Result<(), _> {
+                calls += 1;
+                Err(error(DatabaseErrorKind::SerializationFailure, "again"))
+            })
+        });
+        assert!(nested.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn the_database_caps_tag_length_like_validation() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("long_tags_{marker}"));
+        let longest = "x".repeat(MAX_TAG_LENGTH);
+        insert_post(&mut conn, &user, &marker, &[&longest]);
+
+        // Runs in its own savepoint, so the test transaction survives.
+        let too_long = PostRepository::create_with_tags(
+            &mut conn,
+            NewPostWithTags {
+                title: marker.clone(),
+                body: "body".to_string(),
+                created_by: user.id,
+                tags: vec![format!("{longest}y")],
+            },
+            IdVersion::default(),
+            None,
+        )
+        .unwrap_err();
+        assert!(is_tag_too_long(&too_long));
+        assert!(!is_tag_too_long(&diesel::result::Error::NotFound));
+    }
+
+    #[derive(QueryableByName)]
+    struct Isolation {
+        #[diesel(sql_type = diesel::sql_types::Text)]
+        transaction_isolation: String,
+    }
+
+    fn current_isolation(conn: &mut PgConnection) -> String {
+        diesel::sql_query(
+            "SELECT current_setting('transaction_isolation') AS transaction_isolation",
+        )
+        .get_result::<Isolation>(conn)
+        .unwrap()
+        .transaction_isolation
+    }
+
+    #[test]
+    fn isolation_level_is_set_only_on_the_outermost_transaction() {
+        assert_eq!(
+            "Repeatable_Read".parse::<IsolationLevel>(),
+            Ok(IsolationLevel::RepeatableRead)
+        );
+        assert!("snapshot".parse::<IsolationLevel>().is_err());
+
+        let Some(mut conn) = db::test::bare_connection() else {
+            return;
+        };
+        // Nothing is written, and both transactions are rolled back anyway.
+        let _ = conn.transaction::<(), _, _>(|conn| {
+            set_isolation_level(conn, Some(IsolationLevel::Serializable))?;
+            assert_eq!(current_isolation(conn), "serializable");
+
+            let _ = conn.transaction::<(), _, _>(|conn| {
+                set_isolation_level(conn, Some(IsolationLevel::ReadCommitted))?;
+                assert_eq!(current_isolation(conn), "serializable");
+                Err(diesel::result::Error::RollbackTransaction)
+            });
+            Err(diesel::result::Error::RollbackTransaction)
+        });
+
+        let _ = conn.transaction::<(), _, _>(|conn| {
+            set_isolation_level(conn, None)?;
+            assert_eq!(current_isolation(conn), "read committed");
+            Err(diesel::result::Error::RollbackTransaction)
+        });
+    }
+
+    #[test]
+    fn v7_ids_are_set_on_insert_in_creation_order() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let new_user = |n: u8| NewUser {
+            username: format!("v7_{n}_{marker}"),
+            first_name: "Test".to_string(),
+            last_name: "User".to_string(),
+        };
+
+        let first = UserRepository::create(&mut conn, new_user(1), IdVersion::V7).unwrap();
+        let second = UserRepository::create(&mut conn, new_user(2), IdVersion::V7).unwrap();
+        assert_eq!(first.id.get_version_num(), 7);
+        assert!(first.id < second.id);
+
+        let post = PostRepository::create_with_tags(
+            &mut conn,
+            NewPostWithTags {
+                title: "v7".to_string(),
+                body: "body".to_string(),
+                created_by: second.id,
+                tags: vec![],
+            },
+            IdVersion::V7,
+            None,
+        )
+        .unwrap();
+        assert_eq!(post.id.get_version_num(), 7);
+        assert!(second.id < post.id);
+    }
+
+    fn insert_post(conn: &mut PgConnection, user: &User, title: &str, tags: &[&str]) -> Post {
+        PostRepository::create_with_tags(
+            conn,
+            NewPostWithTags {
+                title: title.to_string(),
+                body: "body".to_string(),
+                created_by: user.id,
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+            },
+            IdVersion::default(),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn total_docs_matches_posts_returned_across_pages() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let alice = insert_user(&mut conn, &format!("alice_{marker}"));
+        let bob = insert_user(&mut conn, &format!("bob_{marker}"));
+
+        let mut expected_tags = HashMap::new();
+        for i in 0..7 {
+            let author = if i % 2 == 0 { &alice } else { &bob };
+            let first = format!("{marker}-a");
+            let second = format!("{marker}-b{}", i % 3);
+            let tags = if i % 3 == 0 {
+                vec![]
+            } else {
+                vec![first.as_str(), second.as_str()]
+            };
+            let post = insert_post(&mut conn, author, &format!("{marker} post {i}"), &tags);
+            let mut tags: Vec<String> = tags.iter().map(|t| t.to_string()).collect();
+            tags.sort();
+            expected_tags.insert(post.id, tags);
+        }
+
+        let cases = [
+            PostFilters {
+                search: Some(marker.clone()),
+                ..Default::default()
+            },
+            PostFilters {
+                search: Some(format!("{marker}-b1")),
+                ..Default::default()
+            },
+            PostFilters {
+                tag: Some(format!("{marker}-a")),
+                ..Default::default()
+            },
+            PostFilters {
+                search: Some(marker.clone()),
+                tag: Some(format!("{marker}-b2")),
+                ..Default::default()
+            },
+            PostFilters {
+                search: Some(marker.clone()),
+                author_id: Some(bob.id),
+                ..Default::default()
+            },
+            PostFilters {
+                search: Some(marker.clone()),
+                untagged: Some(true),
+                ..Default::default()
+            },
+            PostFilters {
+                author: Some(format!("bob_{marker}")),
+                ..Default::default()
+            },
+            PostFilters {
+                search: Some(format!("{marker}-b1")),
+                author: Some(format!("alice_{marker}")),
+                ..Default::default()
+            },
+            PostFilters {
+                search: Some(marker.clone()),
+                author_id: Some(alice.id),
+                untagged: Some(true),
+                ..Default::default()
+            },
+        ];
+
+        for filters in &cases {
+            let total_docs = PostRepository::count(&mut conn, filters).unwrap();
+            let mut seen = HashSet::new();
+            let mut returned = 0;
+            let mut page = 1;
+            loop {
+                let (posts, meta) = PostRepository::find_with_user_and_tags(
+                    &mut conn,
+                    page,
+                    2,
+                    &PostSort::default(),
+                    filters,
+                    None,
+                )
+                .unwrap();
+                assert_eq!(meta.total_docs, total_docs, "{filters:?}");
+                for post in posts {
+                    let mut tags = post.tags.clone();
+                    tags.sort();
+                    assert_eq!(tags, expected_tags[&post.id], "{filters:?}");
+                    seen.insert(post.id);
+                    returned += 1;
+                }
+                if page >= meta.total_pages {
+                    break;
+                }
+                page += 1;
+            }
+
+            assert!(total_docs > 0, "{filters:?}");
+            assert_eq!(returned, total_docs, "{filters:?}");
+            assert_eq!(seen.len() as i64, total_docs, "{filters:?}");
+        }
+
+        // Posts 0, 3 and 6 have no tags.
+        let untagged = PostFilters {
+            search: Some(marker.clone()),
+            untagged: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(PostRepository::count(&mut conn, &untagged).unwrap(), 3);
+    }
+
+    #[test]
+    fn find_by_ids_omits_unknown_ids() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("batch_{marker}"));
+
+        let found = UserRepository::find_by_ids(&mut conn, &[user.id, Uuid::new_v4()]).unwrap();
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, user.id);
+    }
+
+    #[test]
+    fn find_many_keeps_request_order_and_skips_unknown_ids() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("batch_{marker}"));
+        let first = insert_post(&mut conn, &user, &marker, &["a"]);
+        let second = insert_post(&mut conn, &user, &marker, &[]);
+
+        let ids = [second.id, Uuid::new_v4(), first.id, second.id];
+        let found = PostRepository::find_many(&mut conn, &ids).unwrap();
+
+        assert_eq!(
+            found.iter().map(|post| post.id).collect::<Vec<_>>(),
+            vec![second.id, first.id]
+        );
+        assert_eq!(found[1].tags, vec!["a"]);
+        assert!(PostRepository::find_many(&mut conn, &[])
+            .unwrap()
+            .is_empty());
+    }
+
+    fn collect_pages(conn: &mut PgConnection, sort: &PostSort, filters: &PostFilters) -> Vec<Uuid> {
+        let mut ids = Vec::new();
+        let mut page = 1;
+        loop {
+            let (posts, meta) =
+                PostRepository::find_with_user_and_tags(conn, page, 3, sort, filters, None)
+                    .unwrap();
+            ids.extend(posts.into_iter().map(|post| post.id));
+            if page >= meta.total_pages {
+                return ids;
+            }
+            page += 1;
+        }
+    }
+
+    #[test]
+    fn pagination_is_stable_for_identical_timestamps() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("bulk_{marker}"));
+
+        // Every insert shares the test transaction, so NOW() and therefore
+        // created_at is identical for all of them.
+        let posts: Vec<Post> = (0..10)
+            .map(|_| insert_post(&mut conn, &user, &format!("{marker} same title"), &[]))
+            .collect();
+        assert!(posts.iter().all(|p| p.created_at == posts[0].created_at));
+
+        let mut expected: Vec<Uuid> = posts.iter().map(|post| post.id).collect();
+        expected.sort_by(|a, b| b.cmp(a));
+
+        let filters = PostFilters {
+            search: Some(marker),
+            ..Default::default()
+        };
+        for sort in ["-created_at", "title", "title,created_at"] {
+            let sort = PostSort::parse(sort).unwrap();
+            assert_eq!(collect_pages(&mut conn, &sort, &filters), expected);
+            assert_eq!(collect_pages(&mut conn, &sort, &filters), expected);
+        }
+    }
+
+    #[test]
+    fn title_ties_are_newest_first_and_bare_created_at_is_newest_first() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("sorted_{marker}"));
+        let older = insert_post(&mut conn, &user, &format!("{marker} same"), &[]);
+        let newer = insert_post(&mut conn, &user, &format!("{marker} same"), &[]);
+        let other = insert_post(&mut conn, &user, &format!("{marker} other"), &[]);
+        // Give the posts distinct timestamps, with `older` first and the id
+        // order working against the expected result.
+        for (post, hours) in [(&older, 3), (&newer, 2), (&other, 1)] {
+            diesel::update(posts::table.find(post.id))
+                .set(posts::created_at.eq(Utc::now() - chrono::Duration::hours(hours)))
+                .execute(&mut conn)
+                .unwrap();
+        }
+
+        let filters = PostFilters {
+            search: Some(marker),
+            ..Default::default()
+        };
+        let sorted = |conn: &mut PgConnection, sort: &str| {
+            collect_pages(conn, &PostSort::parse(sort).unwrap(), &filters)
+        };
+        assert_eq!(sorted(&mut conn, "title"), [other.id, newer.id, older.id]);
+        assert_eq!(
+            sorted(&mut conn, "created_at"),
+            [other.id, newer.id, older.id]
+        );
+        assert_eq!(
+            sorted(&mut conn, "+created_at"),
+            [older.id, newer.id, other.id]
+        );
+    }
+
+    #[test]
+    fn new_users_and_posts_get_a_current_created_at() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("fresh_{marker}"));
+        let post = insert_post(&mut conn, &user, "fresh", &[]);
+
+        // The test transaction may have started a moment before this.
+        let age = |created_at: DateTime<Utc>| (Utc::now() - created_at).num_seconds().abs();
+        assert!(
+            age(user.created_at) < 5,
+            "user created_at {}",
+            user.created_at
+        );
+        assert!(
+            age(post.created_at) < 5,
+            "post created_at {}",
+            post.created_at
+        );
+    }
+
+    #[test]
+    fn get_user_counts_posts_and_reports_zero() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let author = insert_user(&mut conn, &format!("author_{marker}"));
+        let lurker = insert_user(&mut conn, &format!("lurker_{marker}"));
+        insert_post(&mut conn, &author, "first", &["a", "b"]);
+        insert_post(&mut conn, &author, "second", &[]);
+
+        let author_stats = UserRepository::get_with_stats(&mut conn, author.id)
+            .unwrap()
+            .unwrap();
+        let lurker_stats = UserRepository::get_with_stats(&mut conn, lurker.id)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(author_stats.post_count, 2);
+        assert_eq!(lurker_stats.post_count, 0);
+        assert!(UserRepository::get_with_stats(&mut conn, Uuid::new_v4())
+            .unwrap()
+            .is_none());
+
+        let mut batch =
+            UserRepository::find_by_ids_with_stats(&mut conn, &[author.id, lurker.id]).unwrap();
+        batch.sort_by_key(|stats| stats.post_count);
+        let counts: Vec<(Uuid, i64)> = batch
+            .iter()
+            .map(|stats| (stats.user.id, stats.post_count))
+            .collect();
+        assert_eq!(counts, vec![(lurker.id, 0), (author.id, 2)]);
+        assert_eq!(
+            UserRepository::get(&mut conn, author.id)
+                .unwrap()
+                .unwrap()
+                .username,
+            author.username
+        );
+    }
+
+    #[test]
+    fn search_titles_ranks_title_matches_before_body_matches() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("typeahead_{marker}"));
+        let in_body = PostRepository::create_with_tags(
+            &mut conn,
+            NewPostWithTags {
+                title: "Unrelated".to_string(),
+                body: format!("mentions {marker}"),
+                created_by: user.id,
+                tags: vec![],
+            },
+            IdVersion::default(),
+            None,
+        )
+        .unwrap();
+        let contains = insert_post(&mut conn, &user, &format!("About {marker}"), &[]);
+        let prefix = insert_post(&mut conn, &user, &format!("{marker} Guide"), &[]);
+        let exact = insert_post(&mut conn, &user, &marker.to_uppercase(), &[]);
+
+        let results = PostRepository::search_titles(&mut conn, &marker, 10).unwrap();
+        let ids: Vec<Uuid> = results.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![exact.id, prefix.id, contains.id, in_body.id]);
+        assert_eq!(results[1].slug, format!("{marker}-guide"));
+
+        let limited = PostRepository::search_titles(&mut conn, &marker, 2).unwrap();
+        assert_eq!(limited.len(), 2);
+    }
+
+    #[test]
+    fn rename_tag_merges_into_posts_that_already_have_the_target() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let (typo, fixed) = (format!("{marker}javscript"), format!("{marker}javascript"));
+        let user = insert_user(&mut conn, &format!("renamer_{marker}"));
+        let only_typo = insert_post(&mut conn, &user, "one", &[&typo, "web"]);
+        let both = insert_post(&mut conn, &user, "two", &[&typo, &fixed]);
+
+        let result = PostRepository::rename_tag(&mut conn, &typo, &fixed).unwrap();
+        assert_eq!((result.renamed, result.merged), (1, 1));
+
+        let tags_of = |conn: &mut PgConnection, post: &Post| -> Vec<String> {
+            posts_tags::table
+                .filter(posts_tags::fk_post_id.eq(post.id))
+                .select(posts_tags::tag)
+                .order(posts_tags::tag.asc())
+                .load(conn)
+                .unwrap()
+        };
+        assert_eq!(
+            tags_of(&mut conn, &only_typo),
+            vec![fixed.clone(), "web".to_string()]
+        );
+        assert_eq!(tags_of(&mut conn, &both), vec![fixed.clone()]);
+
+        let again = PostRepository::rename_tag(&mut conn, &typo, &fixed).unwrap();
+        assert_eq!((again.renamed, again.merged), (0, 0));
+    }
+
+    #[test]
+    fn create_with_first_post_rolls_back_the_user_when_the_post_fails() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let new_user = |username: &str| NewUser {
+            username: username.to_string(),
+            first_name: "New".to_string(),
+            last_name: "Author".to_string(),
+        };
+        let post = |tags: &[&str]| OnboardPost {
+            title: format!("{marker} hello"),
+            body: "first post".to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        };
+
+        let onboarded = UserRepository::create_with_first_post(
+            &mut conn,
+            new_user(&format!("onboard_{marker}")),
+            post(&["intro"]),
+            IdVersion::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            onboarded.post.created_by.map(|author| author.user_id),
+            Some(onboarded.user.id)
+        );
+        assert_eq!(onboarded.post.tags, vec!["intro"]);
+
+        // A repeated tag violates the posts_tags key after the user insert.
+        let failed_name = format!("failed_{marker}");
+        assert!(UserRepository::create_with_first_post(
+            &mut conn,
+            new_user(&failed_name),
+            post(&["dup", "dup"]),
+            IdVersion::default(),
+            None,
+        )
+        .is_err());
+        let leftover: i64 = users::table
+            .filter(users::username.eq(&failed_name))
+            .count()
+            .get_result(&mut conn)
+            .unwrap();
+        assert_eq!(leftover, 0);
+    }
+
+    #[test]
+    fn delete_tag_removes_it_from_every_post() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let spam = format!("{marker}spam");
+        let user = insert_user(&mut conn, &format!("despammer_{marker}"));
+        let first = insert_post(&mut conn, &user, "one", &[&spam, "keep"]);
+        insert_post(&mut conn, &user, "two", &[&spam]);
+
+        assert_eq!(PostRepository::delete_tag(&mut conn, &spam).unwrap(), 2);
+        assert_eq!(PostRepository::delete_tag(&mut conn, &spam).unwrap(), 0);
+
+        let remaining: Vec<String> = posts_tags::table
+            .filter(posts_tags::fk_post_id.eq(first.id))
+            .select(posts_tags::tag)
+            .load(&mut conn)
+            .unwrap();
+        assert_eq!(remaining, vec!["keep"]);
+    }
+
+    #[test]
+    fn suggest_tags_matches_prefix_literally_by_usage() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("suggest_{marker}"));
+        let popular = format!("{marker}_rust");
+        let rare = format!("{marker}_ruby");
+        let wildcard = format!("{marker}xrust");
+        insert_post(&mut conn, &user, "one", &[&popular, &rare]);
+        insert_post(&mut conn, &user, "two", &[&popular, &wildcard]);
+
+        let suggestions =
+            PostRepository::suggest_tags(&mut conn, &format!("{marker}_ru"), 10).unwrap();
+        let tags: Vec<(&str, i64)> = suggestions
+            .iter()
+            .map(|s| (s.tag.as_str(), s.count))
+            .collect();
+
+        // `_` in the prefix must not match the `x` in the wildcard tag.
+        assert_eq!(tags, vec![(popular.as_str(), 2), (rare.as_str(), 1)]);
+    }
+
+    #[test]
+    fn tags_are_returned_alphabetically() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("sorted_{marker}"));
+        insert_post(&mut conn, &user, &marker, &["zeta", "alpha", "mu", "beta"]);
+
+        let filters = PostFilters {
+            search: Some(marker),
+            ..Default::default()
+        };
+        let (posts, _) = PostRepository::find_with_user_and_tags(
+            &mut conn,
+            1,
+            10,
+            &PostSort::default(),
+            &filters,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(posts[0].tags, vec!["alpha", "beta", "mu", "zeta"]);
+    }
+
+    #[test]
+    fn comment_count_ignores_deleted_comments_and_tag_rows() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("counted_{marker}"));
+        let tag = format!("t{marker}");
+        let discussed = insert_post(&mut conn, &user, &format!("{marker} a"), &[&tag, "x", "y"]);
+        insert_post(&mut conn, &user, &format!("{marker} b"), &[&tag]);
+        for body in ["one", "two", "gone"] {
+            let comment = CommentRepository::create(
+                &mut conn,
+                NewComment {
+                    fk_post_id: discussed.id,
+                    author_id: user.id,
+                    parent_id: None,
+                    body: body.to_string(),
+                },
+            )
+            .unwrap();
+            if body == "gone" {
+                CommentRepository::soft_delete(&mut conn, comment.id).unwrap();
+            }
+        }
+
+        let mut filters = PostFilters {
+            tag: Some(tag),
+            ..Default::default()
+        };
+        let mut counts = |filters: &PostFilters| {
+            let (posts, _) = PostRepository::find_with_user_and_tags(
+                &mut conn,
+                1,
+                10,
+                &PostSort::default(),
+                filters,
+                None,
+            )
+            .unwrap();
+            posts
+                .into_iter()
+                .map(|post| (post.id, post.comment_count))
+                .collect::<HashMap<Uuid, Option<i64>>>()
+        };
+        let without = counts(&filters);
+        assert_eq!(without.len(), 2);
+        assert!(without.values().all(Option::is_none));
+
+        filters.with = Some("comment_count".to_string());
+        let with = counts(&filters);
+        assert_eq!(with.len(), 2);
+        assert_eq!(with[&discussed.id], Some(2));
+        assert!(with.values().any(|&count| count == Some(0)));
+
+        let detail = PostRepository::find(&mut conn, discussed.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(detail.post.comment_count, Some(2));
+    }
+
+    #[test]
+    fn updated_by_is_null_until_edited() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let author = insert_user(&mut conn, &format!("writer_{marker}"));
+        let editor = insert_user(&mut conn, &format!("editor_{marker}"));
+        let post = insert_post(&mut conn, &author, "draft", &["news"]);
+
+        let detail = PostRepository::find(&mut conn, post.id).unwrap().unwrap();
+        assert!(detail.updated_by.is_none());
+        assert_eq!(detail.post.tags, vec!["news"]);
+
+        let changes = PostChanges {
+            title: Some("final".to_string()),
+            body: None,
+            updated_by: Some(editor.id),
+        };
+        let updated = PostRepository::update(&mut conn, post.id, changes)
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.title, "final");
+        assert_eq!(updated.body, post.body);
+
+        let detail = PostRepository::find(&mut conn, post.id).unwrap().unwrap();
+        let updated_by = detail.updated_by.unwrap();
+        assert_eq!(updated_by.user_id, editor.id);
+        assert_eq!(updated_by.username, editor.username);
+    }
+
+    #[test]
+    fn audit_rows_follow_their_transaction() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("audited_{marker}"));
+        let post = insert_post(&mut conn, &user, &marker, &[]);
+
+        PostRepository::update(
+            &mut conn,
+            post.id,
+            PostChanges {
+                title: Some(format!("{marker} edited")),
+                body: None,
+                updated_by: Some(user.id),
+            },
+        )
+        .unwrap();
+
+        let (entries, meta) = AuditRepository::list(&mut conn, Some(post.id), 1, 10).unwrap();
+        assert_eq!(meta.total_docs, 2);
+        // Both rows share the test transaction's timestamp, so their order is
+        // not meaningful here.
+        let mut actions: Vec<&str> = entries.iter().map(|e| e.action.as_str()).collect();
+        actions.sort_unstable();
+        assert_eq!(actions, vec!["create", "update"]);
+        assert!(entries.iter().all(|e| e.actor_id == Some(user.id)));
+        let update = entries.iter().find(|e| e.action == "update").unwrap();
+        assert_eq!(
+            update.diff,
+            Some(serde_json::json!({
+                "title": { "from": marker, "to": format!("{marker} edited") },
+                "updated_by": { "from": null, "to": user.id },
+            }))
+        );
+
+        // Duplicate tags violate the posts_tags key after the post and its
+        // audit row were written, so the whole transaction rolls back.
+        let failed = PostRepository::create_with_tags(
+            &mut conn,
+            NewPostWithTags {
+                title: format!("{marker} rolled back"),
+                body: "body".to_string(),
+                created_by: user.id,
+                tags: vec!["dup".to_string(), "dup".to_string()],
+            },
+            IdVersion::default(),
+            None,
+        );
+        assert!(failed.is_err());
+
+        let leftover: i64 = audit_log::table
+            .filter(audit_log::actor_id.eq(user.id))
+            .count()
+            .get_result(&mut conn)
+            .unwrap();
+        assert_eq!(leftover, 2);
+    }
+
+    fn import_request(
+        mode: ImportMode,
+        marker: &str,
+        user_id: Uuid,
+        post_id: Uuid,
+    ) -> ImportRequest {
+        serde_json::from_value(serde_json::json!({
+            "mode": mode,
+            "users": [{
+                "id": user_id,
+                "username": format!("imported_{marker}"),
+                "first_name": "Imported",
+                "last_name": "User",
+                "created_at": "2020-01-01T00:00:00Z",
+            }],
+            "posts": [{
+                "id": post_id,
+                "title": format!("{marker} {mode:?}"),
+                "body": "body",
+                "created_by": user_id,
+                "tags": ["Rust", "rust", "import"],
+            }],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn import_preserves_ids_and_honours_mode() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let (user_id, post_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let request = import_request(ImportMode::Skip, &marker, user_id, post_id);
+        let summary = ImportRepository::import(&mut conn, &request).unwrap();
+        assert_eq!((summary.users.inserted, summary.posts.inserted), (1, 1));
+
+        let detail = PostRepository::find(&mut conn, post_id).unwrap().unwrap();
+        assert_eq!(detail.post.created_by.unwrap().user_id, user_id);
+        assert_eq!(detail.post.tags, vec!["import", "rust"]);
+
+        let summary = ImportRepository::import(&mut conn, &request).unwrap();
+        assert_eq!((summary.users.skipped, summary.posts.skipped), (1, 1));
+
+        let request = import_request(ImportMode::Upsert, &marker, user_id, post_id);
+        let summary = ImportRepository::import(&mut conn, &request).unwrap();
+        assert_eq!((summary.users.updated, summary.posts.updated), (1, 1));
+        let detail = PostRepository::find(&mut conn, post_id).unwrap().unwrap();
+        assert_eq!(detail.post.title, format!("{marker} Upsert"));
+    }
+
+    #[test]
+    fn failed_import_rolls_back_everything() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let (user_id, post_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut request = import_request(ImportMode::Skip, &marker, user_id, post_id);
+        // The post's author is neither imported nor existing.
+        request.posts[0].created_by = Uuid::new_v4();
+
+        assert!(ImportRepository::import(&mut conn, &request).is_err());
+        assert!(UserRepository::find_by_ids(&mut conn, &[user_id])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn dry_run_reports_the_plan_and_stores_nothing() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let (user_id, post_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let existing = insert_user(&mut conn, &format!("existing_{marker}"));
+        let mut request = import_request(ImportMode::Skip, &marker, user_id, post_id);
+        request.users.push(DumpUser::from(existing));
+
+        let (users, posts) = ImportRepository::plan(&mut conn, &request).unwrap();
+        assert_eq!(users, vec![ImportAction::Create, ImportAction::Skip]);
+        assert_eq!(posts, vec![ImportAction::Create]);
+
+        let summary = ImportRepository::dry_run(&mut conn, &request).unwrap();
+        assert_eq!((summary.users.inserted, summary.users.skipped), (1, 1));
+        assert_eq!(summary.posts.inserted, 1);
+        assert!(UserRepository::find_by_ids(&mut conn, &[user_id])
+            .unwrap()
+            .is_empty());
+        assert!(PostRepository::find(&mut conn, post_id).unwrap().is_none());
+
+        request.posts[0].created_by = Uuid::new_v4();
+        assert!(ImportRepository::dry_run(&mut conn, &request).is_err());
+    }
+
+    #[test]
+    fn favorites_are_idempotent_and_listed_per_user() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("fan_{marker}"));
+        let first = insert_post(&mut conn, &user, &format!("{marker} first"), &["b", "a"]);
+        let second = insert_post(&mut conn, &user, &format!("{marker} second"), &[]);
+
+        assert!(FavoriteRepository::add(&mut conn, user.id, first.id).unwrap());
+        assert!(!FavoriteRepository::add(&mut conn, user.id, first.id).unwrap());
+        assert!(FavoriteRepository::add(&mut conn, user.id, second.id).unwrap());
+
+        let (posts, meta) = FavoriteRepository::list_for_user(&mut conn, user.id, 1, 10).unwrap();
+        assert_eq!(meta.total_docs, 2);
+        let first_listed = posts.iter().find(|p| p.id == first.id).unwrap();
+        assert_eq!(first_listed.tags, vec!["a", "b"]);
+
+        assert!(FavoriteRepository::remove(&mut conn, user.id, first.id).unwrap());
+        assert!(!FavoriteRepository::remove(&mut conn, user.id, first.id).unwrap());
+        let (posts, meta) = FavoriteRepository::list_for_user(&mut conn, user.id, 1, 10).unwrap();
+        assert_eq!(meta.total_docs, 1);
+        assert_eq!(posts[0].id, second.id);
+    }
+
+    #[test]
+    fn export_pages_cover_every_post_once_with_tags() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("exported_{marker}"));
+        let tagged = insert_post(&mut conn, &user, &marker, &["z", "a"]);
+        let untagged = insert_post(&mut conn, &user, &marker, &[]);
+
+        let mut exported = Vec::new();
+        let mut after = None;
+        loop {
+            let page = PostRepository::export_page(&mut conn, after, 1).unwrap();
+            let Some(last) = page.last() else {
+                break;
+            };
+            after = Some(last.id);
+            exported.extend(page);
+        }
+
+        let ours: Vec<&DumpPost> = exported
+            .iter()
+            .filter(|p| p.id == tagged.id || p.id == untagged.id)
+            .collect();
+        assert_eq!(ours.len(), 2);
+        for post in ours {
+            let expected: Vec<&str> = if post.id == tagged.id {
+                vec!["a", "z"]
+            } else {
+                vec![]
+            };
+            assert_eq!(post.tags, expected);
+        }
+    }
+
+    #[test]
+    fn ndjson_export_pages_carry_authors_and_tags_in_id_order() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("ndjson_{marker}"));
+        let tagged = insert_post(&mut conn, &user, &marker, &["z", "a"]);
+        let untagged = insert_post(&mut conn, &user, &marker, &[]);
+
+        let mut exported = Vec::new();
+        let mut after = None;
+        loop {
+            let page =
+                PostRepository::export_page_with_user_and_tags(&mut conn, after, 50).unwrap();
+            let Some(last) = page.last() else {
+                break;
+            };
+            after = Some(last.id);
+            exported.extend(page);
+        }
+
+        assert!(exported.windows(2).all(|pair| pair[0].id < pair[1].id));
+        let ours: Vec<&PostWithUserAndTags> = exported
+            .iter()
+            .filter(|p| p.id == tagged.id || p.id == untagged.id)
+            .collect();
+        assert_eq!(ours.len(), 2);
+        for post in ours {
+            let expected: Vec<&str> = if post.id == tagged.id {
+                vec!["a", "z"]
+            } else {
+                vec![]
+            };
+            assert_eq!(post.tags, expected);
+            assert_eq!(
+                post.created_by.as_ref().map(|author| author.user_id),
+                Some(user.id)
+            );
+            assert_eq!(post.comment_count, None);
+        }
+    }
+
+    #[test]
+    fn set_tags_applies_only_the_difference() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("tagger_{marker}"));
+        let post = insert_post(&mut conn, &user, &marker, &["keep", "drop"]);
+
+        let tags = vec!["new".to_string(), "keep".to_string()];
+        let changes = PostRepository::set_tags(&mut conn, post.id, &tags, Some(user.id))
+            .unwrap()
+            .unwrap();
+        assert_eq!(changes.tags, vec!["keep", "new"]);
+        assert_eq!(changes.added, vec!["new"]);
+        assert_eq!(changes.removed, vec!["drop"]);
+
+        let detail = PostRepository::find(&mut conn, post.id).unwrap().unwrap();
+        assert_eq!(detail.post.tags, vec!["keep", "new"]);
+        assert_eq!(detail.updated_by.unwrap().user_id, user.id);
+
+        let (entries, _) = AuditRepository::list(&mut conn, Some(post.id), 1, 10).unwrap();
+        let before = entries.len();
+        let unchanged = PostRepository::set_tags(&mut conn, post.id, &tags, Some(user.id))
+            .unwrap()
+            .unwrap();
+        assert!(unchanged.added.is_empty() && unchanged.removed.is_empty());
+        let (entries, _) = AuditRepository::list(&mut conn, Some(post.id), 1, 10).unwrap();
+        assert_eq!(entries.len(), before);
+
+        assert!(
+            PostRepository::set_tags(&mut conn, Uuid::new_v4(), &tags, None)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn attachments_appear_on_post_detail() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("illustrator_{marker}"));
+        let post = insert_post(&mut conn, &user, &marker, &[]);
+
+        let attachment = PostRepository::add_attachment(
+            &mut conn,
+            NewAttachment {
+                fk_post_id: post.id,
+                url: "https://cdn.example.com/a.png".to_string(),
+                content_type: "image/png".to_string(),
+                size_bytes: 1024,
+            },
+        )
+        .unwrap()
+        .unwrap();
+
+        let detail = PostRepository::find(&mut conn, post.id).unwrap().unwrap();
+        assert_eq!(detail.attachments.len(), 1);
+        assert_eq!(detail.attachments[0].id, attachment.id);
+
+        let missing = PostRepository::add_attachment(
+            &mut conn,
+            NewAttachment {
+                fk_post_id: Uuid::new_v4(),
+                url: "https://cdn.example.com/b.png".to_string(),
+                content_type: "image/png".to_string(),
+                size_bytes: 1,
+            },
+        )
+        .unwrap();
+        assert!(missing.is_none());
+    }
+
+    #[test]
+    fn archive_counts_group_by_month_newest_first() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("archivist_{marker}"));
+        let before = PostRepository::archive_counts(&mut conn).unwrap();
+
+        let dates = [
+            "1999-01-05T00:00:00Z",
+            "1999-01-31T23:00:00Z",
+            "1998-12-31T12:00:00Z",
+        ];
+        for date in dates {
+            let post = insert_post(&mut conn, &user, &marker, &[]);
+            diesel::update(posts::table.find(post.id))
+                .set(posts::created_at.eq(date.parse::<DateTime<Utc>>().unwrap()))
+                .execute(&mut conn)
+                .unwrap();
+        }
+
+        let after = PostRepository::archive_counts(&mut conn).unwrap();
+        let count_for = |counts: &[(i32, i32, i64)], year, month| {
+            counts
+                .iter()
+                .find(|(y, m, _)| (*y, *m) == (year, month))
+                .map_or(0, |(_, _, count)| *count)
+        };
+        assert_eq!(count_for(&after, 1999, 1) - count_for(&before, 1999, 1), 2);
+        assert_eq!(
+            count_for(&after, 1998, 12) - count_for(&before, 1998, 12),
+            1
+        );
+
+        let months: Vec<_> = after.iter().map(|(y, m, _)| (*y, *m)).collect();
+        let mut sorted = months.clone();
+        sorted.sort_by(|a, b| b.cmp(a));
+        assert_eq!(months, sorted);
+    }
+
+    #[test]
+    fn popular_orders_by_views_within_window() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("viewer_{marker}"));
+        let quiet = insert_post(&mut conn, &user, &marker, &[]);
+        let busy = insert_post(&mut conn, &user, &marker, &[]);
+        let old = insert_post(&mut conn, &user, &marker, &[]);
+        diesel::update(posts::table.find(old.id))
+            .set((
+                posts::created_at.eq(Utc::now() - chrono::Duration::days(30)),
+                posts::view_count.eq(i64::MAX / 2),
+            ))
+            .execute(&mut conn)
+            .unwrap();
+        diesel::update(posts::table.find(busy.id))
+            .set(posts::view_count.eq(i64::MAX / 4))
+            .execute(&mut conn)
+            .unwrap();
+        assert!(PostRepository::record_view(&mut conn, quiet.id).unwrap());
+        assert!(!PostRepository::record_view(&mut conn, Uuid::new_v4()).unwrap());
+
+        let ids =
+            |posts: Vec<PostWithUserAndTags>| posts.into_iter().map(|p| p.id).collect::<Vec<_>>();
+        let recent = ids(PostRepository::popular(&mut conn, 7, 2).unwrap());
+        assert_eq!(recent[0], busy.id);
+        assert!(!recent.contains(&old.id));
+
+        let all_time = ids(PostRepository::popular(&mut conn, 0, 2).unwrap());
+        assert_eq!(all_time, vec![old.id, busy.id]);
+
+        let detail = PostRepository::find(&mut conn, quiet.id).unwrap().unwrap();
+        assert_eq!(detail.post.view_count, 1);
+    }
+
+    #[test]
+    fn match_mode_switches_between_exact_prefix_and_contains() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("matcher_{marker}"));
+        insert_post(&mut conn, &user, "Untitled", &[&marker]);
+        insert_post(&mut conn, &user, "Untitled", &[&format!("{marker}-extra")]);
+        insert_post(&mut conn, &user, "Untitled", &[&format!("pre-{marker}")]);
+
+        let mut count = |mode: Option<&str>, term: &str| {
+            let filters = PostFilters {
+                search: Some(term.to_string()),
+                match_mode: mode.map(str::to_string),
+                ..Default::default()
+            };
+            PostRepository::count(&mut conn, &filters).unwrap()
+        };
+        assert_eq!(count(None, &marker), 3);
+        assert_eq!(count(Some("contains"), &marker), 3);
+        assert_eq!(count(Some("prefix"), &marker), 2);
+        assert_eq!(count(Some("exact"), &marker), 1);
+        // Exact matching is case-sensitive.
+        assert_eq!(count(Some("exact"), &marker.to_uppercase()), 0);
+        // Wildcards in the term are matched literally.
+        assert_eq!(count(Some("contains"), &format!("{marker}%")), 0);
+        assert_eq!(count(Some("prefix"), &format!("{marker}_extra")), 0);
+        assert_eq!(count(Some("prefix"), &format!("{marker}-extra")), 1);
+    }
+
+    #[test]
+    fn author_filter_ignores_titles_and_bodies() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let smith = UserRepository::create(
+            &mut conn,
+            NewUser {
+                username: format!("writer_{marker}"),
+                first_name: "Jane".to_string(),
+                last_name: format!("Smith{marker}"),
+            },
+            IdVersion::default(),
+        )
+        .unwrap();
+        let other = insert_user(&mut conn, &format!("other_{marker}"));
+        let by_smith = insert_post(&mut conn, &smith, "Gardening", &[]);
+        let about_smith = insert_post(&mut conn, &other, &format!("On Smith{marker}"), &[]);
+        diesel::update(posts::table.find(about_smith.id))
+            .set(posts::body.eq(format!("Smith{marker} wrote this")))
+            .execute(&mut conn)
+            .unwrap();
+
+        let filters = PostFilters {
+            author: Some(format!("smith{marker}")),
+            ..Default::default()
+        };
+        assert_eq!(PostRepository::count(&mut conn, &filters).unwrap(), 1);
+        let (posts, _) = PostRepository::find_with_user_and_tags(
+            &mut conn,
+            1,
+            10,
+            &PostSort::default(),
+            &filters,
+            None,
+        )
+        .unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].id, by_smith.id);
+
+        // Combined with `search`, both must match.
+        let filters = PostFilters {
+            search: Some("Gardening".to_string()),
+            author: Some(format!("other_{marker}")),
+            ..Default::default()
+        };
+        assert_eq!(PostRepository::count(&mut conn, &filters).unwrap(), 0);
+    }
+
+    #[test]
+    fn latest_per_author_keeps_each_authors_newest_post() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let ada = insert_user(&mut conn, &format!("ada_{marker}"));
+        let bob = insert_user(&mut conn, &format!("bob_{marker}"));
+        insert_user(&mut conn, &format!("cy_{marker}"));
+
+        // Dated in the future so they are the newest posts in the database.
+        let future: DateTime<Utc> = "2100-01-01T00:00:00Z".parse().unwrap();
+        let mut post_at = |author: &User, days: i64| {
+            let post = insert_post(&mut conn, author, &marker, &["team"]);
+            diesel::update(posts::table.find(post.id))
+                .set(posts::created_at.eq(future + chrono::Duration::days(days)))
+                .execute(&mut conn)
+                .unwrap();
+            post.id
+        };
+        post_at(&ada, 1);
+        let ada_latest = post_at(&ada, 3);
+        let bob_latest = post_at(&bob, 2);
+
+        let latest = PostRepository::latest_per_author(&mut conn, 2).unwrap();
+        let ids: Vec<Uuid> = latest.iter().map(|post| post.id).collect();
+        assert_eq!(ids, vec![ada_latest, bob_latest]);
+        assert_eq!(latest[0].created_by.as_ref().unwrap().user_id, ada.id);
+        assert_eq!(latest[0].tags, vec!["team"]);
+    }
+
+    #[test]
+    fn updated_at_moves_on_edits_but_not_views() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let author = insert_user(&mut conn, &format!("writer_{marker}"));
+        let post = insert_post(&mut conn, &author, &marker, &[]);
+        let long_ago: DateTime<Utc> = "2000-01-01T00:00:00Z".parse().unwrap();
+        diesel::update(posts::table.find(post.id))
+            .set(posts::updated_at.eq(long_ago))
+            .execute(&mut conn)
+            .unwrap();
+
+        PostRepository::record_view(&mut conn, post.id).unwrap();
+        let detail = PostRepository::find(&mut conn, post.id).unwrap().unwrap();
+        assert_eq!(detail.post.updated_at, long_ago);
+
+        PostRepository::toggle_featured(&mut conn, post.id).unwrap();
+        let detail = PostRepository::find(&mut conn, post.id).unwrap().unwrap();
+        assert!(detail.post.updated_at > long_ago);
+    }
+
+    #[test]
+    fn post_list_fills_and_uses_author_cache() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("cached_{marker}"));
+        insert_post(&mut conn, &user, &marker, &[]);
+        let cache = AuthorCache::new(10, std::time::Duration::from_secs(60));
+        let filters = PostFilters {
+            search: Some(marker.clone()),
+            ..Default::default()
+        };
+
+        let list = |conn: &mut PgConnection| {
+            let (posts, _) = PostRepository::find_with_user_and_tags(
+                conn,
+                1,
+                10,
+                &PostSort::default(),
+                &filters,
+                Some(&cache),
+            )
+            .unwrap();
+            posts[0].created_by.clone().unwrap()
+        };
+        assert_eq!(list(&mut conn).username, user.username);
+        assert_eq!(cache.get(user.id).unwrap().username, user.username);
+
+        // A cached author is served without reading `users` again.
+        diesel::update(users::table.find(user.id))
+            .set(users::first_name.eq("Renamed"))
+            .execute(&mut conn)
+            .unwrap();
+        assert_eq!(list(&mut conn).first_name, "Test");
+    }
+
+    #[test]
+    fn reassign_moves_post_or_reports_what_is_missing() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let author = insert_user(&mut conn, &format!("before_{marker}"));
+        let successor = insert_user(&mut conn, &format!("after_{marker}"));
+        let post = insert_post(&mut conn, &author, &marker, &[]);
+
+        match PostRepository::reassign(&mut conn, post.id, successor.id).unwrap() {
+            Reassignment::Reassigned(post) => assert_eq!(post.created_by, successor.id),
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(matches!(
+            PostRepository::reassign(&mut conn, post.id, Uuid::new_v4()).unwrap(),
+            Reassignment::AuthorNotFound
+        ));
+        assert!(matches!(
+            PostRepository::reassign(&mut conn, Uuid::new_v4(), successor.id).unwrap(),
+            Reassignment::PostNotFound
+        ));
+
+        let stored = PostRepository::find_by_id(&mut conn, post.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.created_by, successor.id);
+    }
+
+    #[test]
+    fn usernames_are_unique_regardless_of_case() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let bob = insert_user(&mut conn, &format!("Bob_{marker}"));
+
+        let duplicate = UserRepository::create(
+            &mut conn,
+            NewUser {
+                username: format!("bob_{marker}"),
+                first_name: "Other".to_string(),
+                last_name: "Bob".to_string(),
+            },
+            IdVersion::default(),
+        );
+        assert!(matches!(
+            duplicate,
+            Err(diesel::result::Error::DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                _
+            ))
+        ));
+
+        let found = UserRepository::find_by_username(&mut conn, &format!("BOB_{marker}"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id, bob.id);
+        assert_eq!(found.username, format!("Bob_{marker}"));
+
+        assert!(UserRepository::username_exists(&mut conn, &format!("bOB_{marker}")).unwrap());
+        assert!(!UserRepository::username_exists(&mut conn, &format!("Bob_{marker}x")).unwrap());
+    }
+
+    #[test]
+    fn tag_filter_ignores_case() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("rustacean_{marker}"));
+        let tag = format!("rust{marker}");
+        let post = insert_post(&mut conn, &user, &marker, &[&tag]);
+
+        let filters = PostFilters {
+            tag: Some(format!(" {} ", tag.to_uppercase())),
+            ..Default::default()
+        };
+        let (posts, _) = PostRepository::find_with_user_and_tags(
+            &mut conn,
+            1,
+            10,
+            &PostSort::default(),
+            &filters,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            posts.iter().map(|p| p.id).collect::<Vec<_>>(),
+            vec![post.id]
+        );
+        assert_eq!(PostRepository::count(&mut conn, &filters).unwrap(), 1);
+    }
+
+    #[test]
+    fn featured_filter_combines_with_search() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("editor_{marker}"));
+        let pinned = insert_post(&mut conn, &user, &format!("{marker} pinned"), &[]);
+        let plain = insert_post(&mut conn, &user, &format!("{marker} plain"), &[]);
+
+        let toggled = PostRepository::toggle_featured(&mut conn, pinned.id)
+            .unwrap()
+            .unwrap();
+        assert!(toggled.featured);
+
+        let list = |conn: &mut PgConnection, featured| {
+            let filters = PostFilters {
+                search: Some(marker.clone()),
+                featured,
+                ..Default::default()
+            };
+            let (posts, meta) = PostRepository::find_with_user_and_tags(
+                conn,
+                1,
+                10,
+                &PostSort::default(),
+                &filters,
+                None,
+            )
+            .unwrap();
+            assert_eq!(meta.total_docs, posts.len() as i64);
+            posts
+                .into_iter()
+                .map(|p| (p.id, p.featured))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(list(&mut conn, Some(true)), vec![(pinned.id, true)]);
+        assert_eq!(list(&mut conn, Some(false)), vec![(plain.id, false)]);
+        assert_eq!(list(&mut conn, None).len(), 2);
+
+        let untoggled = PostRepository::toggle_featured(&mut conn, pinned.id)
+            .unwrap()
+            .unwrap();
+        assert!(!untoggled.featured);
+        assert!(PostRepository::toggle_featured(&mut conn, Uuid::new_v4())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn settings_set_overwrites_existing_value() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let key = format!("test_{}", Uuid::new_v4().simple());
+
+        assert!(SettingsRepository::get(&mut conn, &key).unwrap().is_none());
+        SettingsRepository::set(&mut conn, &key, "1").unwrap();
+        let setting = SettingsRepository::set(&mut conn, &key, "2").unwrap();
+        assert_eq!(setting.value, "2");
+        assert_eq!(
+            SettingsRepository::get(&mut conn, &key)
+                .unwrap()
+                .unwrap()
+                .value,
+            "2"
+        );
+    }
+
+    #[test]
+    fn deleting_a_users_posts_removes_their_children_only() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let author = insert_user(&mut conn, &format!("purged_{marker}"));
+        let other = insert_user(&mut conn, &format!("kept_{marker}"));
+        let first = insert_post(&mut conn, &author, &format!("{marker} one"), &["a", "b"]);
+        insert_post(&mut conn, &author, &format!("{marker} two"), &["a"]);
+        let kept = insert_post(&mut conn, &other, &format!("{marker} kept"), &["a"]);
+        CommentRepository::create(
+            &mut conn,
+            NewComment {
+                fk_post_id: first.id,
+                author_id: other.id,
+                parent_id: None,
+                body: "hello".to_string(),
+            },
+        )
+        .unwrap();
+        FavoriteRepository::add(&mut conn, other.id, first.id).unwrap();
+
+        let purged = PostRepository::delete_all_for_user(&mut conn, author.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (purged.posts, purged.tags, purged.comments, purged.favorites),
+            (2, 3, 1, 1)
+        );
+        let orphaned_tags: i64 = posts_tags::table
+            .filter(posts_tags::fk_post_id.eq(first.id))
+            .count()
+            .get_result(&mut conn)
+            .unwrap();
+        assert_eq!(orphaned_tags, 0);
+        assert!(UserRepository::get(&mut conn, author.id).unwrap().is_some());
+        assert!(PostRepository::find(&mut conn, kept.id).unwrap().is_some());
+
+        assert!(
+            PostRepository::delete_all_for_user(&mut conn, Uuid::new_v4())
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn comment_replies_nest_under_their_parent() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let marker = Uuid::new_v4().simple().to_string();
+        let user = insert_user(&mut conn, &format!("commenter_{marker}"));
+        let post = insert_post(&mut conn, &user, &marker, &[]);
+
+        let comment = |conn: &mut PgConnection, parent_id| {
+            CommentRepository::create(
+                conn,
+                NewComment {
+                    fk_post_id: post.id,
+                    author_id: user.id,
+                    parent_id,
+                    body: "hello".to_string(),
+                },
+            )
+            .unwrap()
+        };
+        let root = comment(&mut conn, None);
+        let reply = comment(&mut conn, Some(root.id));
+
+        let tree =
+            build_comment_tree(CommentRepository::list_for_post(&mut conn, post.id).unwrap());
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].comment.id, root.id);
+        assert_eq!(tree[0].replies[0].comment.id, reply.id);
+
+        assert!(CommentRepository::soft_delete(&mut conn, root.id)
+            .unwrap()
+            .is_some());
+        assert!(CommentRepository::soft_delete(&mut conn, root.id)
+            .unwrap()
+            .is_none());
+        assert!(CommentRepository::update_body(&mut conn, root.id, "edit")
+            .unwrap()
+            .is_none());
+
+        let tree =
+            build_comment_tree(CommentRepository::list_for_post(&mut conn, post.id).unwrap());
+        assert_eq!(tree[0].comment.body, "[deleted]");
+        assert_eq!(tree[0].replies[0].comment.id, reply.id);
+    }
+
+    #[test]
+    fn stats_count_users_posts_distinct_tags_and_live_comments() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let before = StatsRepository::summary(&mut conn).unwrap();
+
+        let author = insert_user(&mut conn, "stats_author");
+        let shared = format!("stats-{}", Uuid::new_v4().simple());
+        let single = format!("stats-{}", Uuid::new_v4().simple());
+        let post = insert_post(&mut conn, &author, "Counted", &[&shared, &single]);
+        insert_post(&mut conn, &author, "Also counted", &[&shared]);
+        let new_comment = |body: &str| NewComment {
+            fk_post_id: post.id,
+            author_id: author.id,
+            parent_id: None,
+            body: body.to_string(),
+        };
+        CommentRepository::create(&mut conn, new_comment("kept")).unwrap();
+        let deleted = CommentRepository::create(&mut conn, new_comment("deleted")).unwrap();
+        CommentRepository::soft_delete(&mut conn, deleted.id).unwrap();
+
+        let after = StatsRepository::summary(&mut conn).unwrap();
+        assert_eq!(after.users - before.users, 1);
+        assert_eq!(after.posts - before.posts, 2);
+        assert_eq!(after.tags - before.tags, 2);
+        assert_eq!(after.comments - before.comments, 1);
+    }
+
+    #[test]
+    fn single_fetches_return_none_for_missing_rows() {
+        let Some(mut conn) = db::test::connection() else {
+            return;
+        };
+        let missing = Uuid::new_v4();
+
+        assert!(UserRepository::get(&mut conn, missing).unwrap().is_none());
+        assert!(UserRepository::get_with_stats(&mut conn, missing)
+            .unwrap()
+            .is_none());
+        assert!(
+            UserRepository::find_by_username(&mut conn, &missing.to_string())
+                .unwrap()
+                .is_none()
+        );
+        assert!(PostRepository::find_by_id(&mut conn, missing)
+            .unwrap()
+            .is_none());
+        assert!(PostRepository::find(&mut conn, missing).unwrap().is_none());
+        assert!(CommentRepository::find_by_id(&mut conn, missing)
+            .unwrap()
+            .is_none());
+        assert!(SettingsRepository::get(&mut conn, &missing.to_string())
+            .unwrap()
+            .is_none());
     }
 }
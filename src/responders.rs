@@ -0,0 +1,341 @@
+use chrono::{DateTime, Utc};
+use rocket::http::uri::Origin;
+use rocket::http::Status;
+use rocket::request::Request;
+use rocket::response::{self, Responder, Response};
+
+use crate::guards::CacheValidators;
+use crate::models::PaginationMeta;
+
+/// Wraps a paginated response and adds an RFC 5988 `Link` header with
+/// `first`, `prev`, `next` and `last` relations.
+pub struct WithLinks<R> {
+    pub inner: R,
+    pub link: Option<String>,
+}
+
+impl<R> WithLinks<R> {
+    pub fn new(inner: R, link: String) -> Self {
+        WithLinks {
+            inner,
+            link: Some(link),
+        }
+    }
+
+    /// A response without pagination, e.g. an error body.
+    pub fn without_links(inner: R) -> Self {
+        WithLinks { inner, link: None }
+    }
+}
+
+impl<'r, 'o: 'r, R: Responder<'r, 'o>> Responder<'r, 'o> for WithLinks<R> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+        let mut response = self.inner.respond_to(req)?;
+        if let Some(link) = self.link {
+            response.set_raw_header("Link", link);
+        }
+        Ok(response)
+    }
+}
+
+/// A cacheable response carrying `ETag` and `Last-Modified`. When the
+/// client's validators show its copy is current, the body is dropped and
+/// `304 Not Modified` is sent instead.
+pub struct Cached<R> {
+    inner: Option<R>,
+    etag: String,
+    last_modified: DateTime<Utc>,
+}
+
+impl<R> Cached<R> {
+    pub fn new(
+        inner: R,
+        etag: String,
+        last_modified: DateTime<Utc>,
+        validators: &CacheValidators,
+    ) -> Self {
+        let fresh = validators.is_fresh(&etag, last_modified);
+        Cached {
+            inner: (!fresh).then_some(inner),
+            etag,
+            last_modified,
+        }
+    }
+}
+
+impl<'r, 'o: 'r, R: Responder<'r, 'o>> Responder<'r, 'o> for Cached<R> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+        let mut response = match self.inner {
+            Some(inner) => inner.respond_to(req)?,
+            None => Response::build().status(Status::NotModified).finalize(),
+        };
+        response.set_raw_header("ETag", self.etag);
+        response.set_raw_header(
+            "Last-Modified",
+            self.last_modified
+                .format("%a, %d %b %Y %H:%M:%S GMT")
+                .to_string(),
+        );
+        Ok(response)
+    }
+}
+
+/// Adds `X-Limit-Clamped: true` when the `limit` a client asked for was
+/// outside the endpoint's bounds, so it can tell a short page from one that
+/// was cut down.
+pub struct LimitClamped<R> {
+    inner: R,
+    clamped: bool,
+}
+
+impl<R> LimitClamped<R> {
+    pub fn new(inner: R, clamped: bool) -> Self {
+        LimitClamped { inner, clamped }
+    }
+}
+
+impl<'r, 'o: 'r, R: Responder<'r, 'o>> Responder<'r, 'o> for LimitClamped<R> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+        let mut response = self.inner.respond_to(req)?;
+        if self.clamped {
+            response.set_raw_header("X-Limit-Clamped", "true");
+        }
+        Ok(response)
+    }
+}
+
+/// `snake_case` to `camelCase`: `created_at` becomes `createdAt`. Keys
+/// without underscores are unchanged.
+fn to_camel_case(key: &str) -> String {
+    let mut camel = String::with_capacity(key.len());
+    let mut upper = false;
+    for c in key.chars() {
+        if c == '_' && !camel.is_empty() {
+            upper = true;
+        } else if upper {
+            camel.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            camel.push(c);
+        }
+    }
+    camel
+}
+
+/// Fields whose value is a map keyed by data rather than by field names:
+/// validation `errors` are keyed by request field and audit `diff`s by
+/// column, so the keys inside must reach the client as they are.
+const DATA_KEYED_FIELDS: [&str; 2] = ["errors", "diff"];
+
+/// Renames object keys in `value`, at any depth, to camelCase, leaving the
+/// insides of `DATA_KEYED_FIELDS` alone.
+pub fn camel_case_keys(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => map
+            .into_iter()
+            .map(|(key, value)| {
+                let value = if DATA_KEYED_FIELDS.contains(&key.as_str()) {
+                    value
+                } else {
+                    camel_case_keys(value)
+                };
+                (to_camel_case(&key), value)
+            })
+            .collect(),
+        serde_json::Value::Array(items) => items.into_iter().map(camel_case_keys).collect(),
+        other => other,
+    }
+}
+
+/// The request's own path and query with `page` set to `page`, keeping every
+/// other parameter as the client sent it.
+fn page_url(origin: &Origin<'_>, page: i64) -> String {
+    let mut segments: Vec<String> = origin
+        .query()
+        .map(|query| {
+            query
+                .raw_segments()
+                .filter(|segment| segment.as_str().split('=').next() != Some("page"))
+                .map(|segment| segment.as_str().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    segments.push(format!("page={page}"));
+
+    format!("{}?{}", origin.path(), segments.join("&"))
+}
+
+/// Builds the `Link` header value. `prev` is omitted on the first page and
+/// `next` on the last; an empty result still links to page 1 as first/last.
+pub fn pagination_links(origin: &Origin<'_>, meta: &PaginationMeta) -> String {
+    let last = meta.total_pages.max(1);
+    let mut links = vec![(1, "first")];
+    if meta.current_page > 1 {
+        links.push(((meta.current_page - 1).min(last), "prev"));
+    }
+    if meta.current_page < last {
+        links.push((meta.current_page + 1, "next"));
+    }
+    links.push((last, "last"));
+
+    links
+        .into_iter()
+        .map(|(page, rel)| format!("<{}>; rel=\"{rel}\"", page_url(origin, page)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(current_page: i64, total_pages: i64) -> PaginationMeta {
+        PaginationMeta {
+            current_page,
+            per_page: 10,
+            from: 0,
+            to: 0,
+            total_pages,
+            total_docs: total_pages * 10,
+            out_of_range: current_page > total_pages.max(1),
+            requested_limit: None,
+            query_ms: None,
+        }
+    }
+
+    fn links(uri: &str, current_page: i64, total_pages: i64) -> String {
+        let origin = Origin::parse(uri).unwrap();
+        pagination_links(&origin, &meta(current_page, total_pages))
+    }
+
+    #[test]
+    fn middle_page_links_all_relations_and_keeps_query() {
+        assert_eq!(
+            links("/api/posts?tag=rust&page=2&limit=10", 2, 3),
+            concat!(
+                r#"</api/posts?tag=rust&limit=10&page=1>; rel="first", "#,
+                r#"</api/posts?tag=rust&limit=10&page=1>; rel="prev", "#,
+                r#"</api/posts?tag=rust&limit=10&page=3>; rel="next", "#,
+                r#"</api/posts?tag=rust&limit=10&page=3>; rel="last""#,
+            )
+        );
+    }
+
+    #[test]
+    fn first_and_last_pages_omit_prev_and_next() {
+        assert_eq!(
+            links("/api/posts", 1, 2),
+            r#"</api/posts?page=1>; rel="first", </api/posts?page=2>; rel="next", </api/posts?page=2>; rel="last""#
+        );
+        assert_eq!(
+            links("/api/posts?page=2&search=a%20b", 2, 2),
+            r#"</api/posts?search=a%20b&page=1>; rel="first", </api/posts?search=a%20b&page=1>; rel="prev", </api/posts?search=a%20b&page=2>; rel="last""#
+        );
+        assert_eq!(
+            links("/api/posts", 1, 0),
+            r#"</api/posts?page=1>; rel="first", </api/posts?page=1>; rel="last""#
+        );
+    }
+
+    mod cached {
+        use super::*;
+        use rocket::http::Header;
+        use rocket::local::blocking::Client;
+
+        const ETAG: &str = "W/\"post-1\"";
+        const LAST_MODIFIED: &str = "Tue, 02 Jan 2024 03:04:05 GMT";
+
+        #[get("/cached")]
+        fn cached(validators: CacheValidators) -> Cached<&'static str> {
+            let last_modified = "2024-01-02T03:04:05.678Z".parse().unwrap();
+            Cached::new("body", ETAG.to_string(), last_modified, &validators)
+        }
+
+        fn get(headers: &[(&'static str, &'static str)]) -> (Status, Option<String>) {
+            let client = Client::tracked(rocket::build().mount("/", routes![cached])).unwrap();
+            let mut request = client.get("/cached");
+            for (name, value) in headers {
+                request = request.header(Header::new(*name, *value));
+            }
+            let response = request.dispatch();
+            assert_eq!(response.headers().get_one("ETag"), Some(ETAG));
+            assert_eq!(
+                response.headers().get_one("Last-Modified"),
+                Some(LAST_MODIFIED)
+            );
+            (response.status(), response.into_string())
+        }
+
+        #[test]
+        fn matching_etag_is_not_modified() {
+            for tag in [ETAG, "\"post-1\"", "\"other\", W/\"post-1\"", "*"] {
+                let (status, body) = get(&[("If-None-Match", tag)]);
+                assert_eq!(status, Status::NotModified, "{tag}");
+                assert_eq!(body, None, "{tag}");
+            }
+        }
+
+        #[test]
+        fn stale_etag_returns_the_body() {
+            let (status, body) = get(&[("If-None-Match", "W/\"post-0\"")]);
+            assert_eq!(status, Status::Ok);
+            assert_eq!(body.as_deref(), Some("body"));
+
+            // If-None-Match takes precedence over a matching date.
+            let (status, _) = get(&[
+                ("If-None-Match", "W/\"post-0\""),
+                ("If-Modified-Since", LAST_MODIFIED),
+            ]);
+            assert_eq!(status, Status::Ok);
+        }
+
+        #[test]
+        fn if_modified_since_compares_whole_seconds() {
+            let (status, _) = get(&[("If-Modified-Since", LAST_MODIFIED)]);
+            assert_eq!(status, Status::NotModified);
+
+            let (status, _) = get(&[("If-Modified-Since", "Tue, 02 Jan 2024 03:04:04 GMT")]);
+            assert_eq!(status, Status::Ok);
+
+            let (status, _) = get(&[("If-Modified-Since", "yesterday")]);
+            assert_eq!(status, Status::Ok);
+        }
+    }
+
+    #[test]
+    fn keys_are_camel_cased_at_every_depth() {
+        let value = serde_json::json!({
+            "success": true,
+            "data": {
+                "records": [{ "created_at": "t", "created_by": { "user_id": 1 }, "view_count": 2 }],
+                "meta": { "current_page": 1, "out_of_range": false }
+            }
+        });
+        assert_eq!(
+            camel_case_keys(value),
+            serde_json::json!({
+                "success": true,
+                "data": {
+                    "records": [{ "createdAt": "t", "createdBy": { "userId": 1 }, "viewCount": 2 }],
+                    "meta": { "currentPage": 1, "outOfRange": false }
+                }
+            })
+        );
+        assert_eq!(to_camel_case("_private"), "_private");
+    }
+
+    #[test]
+    fn data_keyed_maps_keep_their_keys() {
+        let value = serde_json::json!({
+            "errors": { "first_name": ["is required"] },
+            "data": { "entity_id": 1, "diff": { "view_count": { "before": 1 } } }
+        });
+        assert_eq!(
+            camel_case_keys(value),
+            serde_json::json!({
+                "errors": { "first_name": ["is required"] },
+                "data": { "entityId": 1, "diff": { "view_count": { "before": 1 } } }
+            })
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use diesel::pg::PgConnection;
+use serde::Serialize;
+
+use crate::models::{IdVersion, NewPostWithTags, NewUser, PostFilters};
+use crate::repository::{PostRepository, UserRepository};
+
+struct SamplePost {
+    title: &'static str,
+    body: &'static str,
+    tags: &'static [&'static str],
+}
+
+struct SampleUser {
+    username: &'static str,
+    first_name: &'static str,
+    last_name: &'static str,
+    posts: &'static [SamplePost],
+}
+
+const SAMPLE_USERS: &[SampleUser] = &[
+    SampleUser {
+        username: "ada",
+        first_name: "Ada",
+        last_name: "Lovelace",
+        posts: &[
+            SamplePost {
+                title: "Notes on the Analytical Engine",
+                body: "The engine weaves algebraic patterns just as the loom weaves flowers.",
+                tags: &["history", "computing"],
+            },
+            SamplePost {
+                title: "Getting started with Rust",
+                body: "Ownership and borrowing make memory safety a compile-time property.",
+                tags: &["rust", "programming"],
+            },
+        ],
+    },
+    SampleUser {
+        username: "grace",
+        first_name: "Grace",
+        last_name: "Hopper",
+        posts: &[
+            SamplePost {
+                title: "Compilers for everyone",
+                body: "It is easier to ask forgiveness than it is to get permission.",
+                tags: &["compilers", "programming"],
+            },
+            SamplePost {
+                title: "Debugging war stories",
+                body: "The first actual case of a bug being found was a moth.",
+                tags: &["debugging"],
+            },
+        ],
+    },
+    SampleUser {
+        username: "linus",
+        first_name: "Linus",
+        last_name: "Torvalds",
+        posts: &[SamplePost {
+            title: "Designing Postgres schemas",
+            body: "Bad programmers worry about the code. Good programmers worry about data structures.",
+            tags: &["postgres", "backend", "rust"],
+        }],
+    },
+];
+
+#[derive(Debug, Default, Serialize)]
+pub struct SeedSummary {
+    pub users: usize,
+    pub posts: usize,
+    pub tags: usize,
+}
+
+/// Inserts the sample users, posts and tags through the repositories.
+/// Users that already exist are reused, and a user's posts are only created
+/// when they have none yet, so running it twice does not duplicate data.
+pub fn seed(conn: &mut PgConnection, ids: IdVersion) -> Result<SeedSummary, diesel::result::Error> {
+    let mut summary = SeedSummary::default();
+
+    for sample in SAMPLE_USERS {
+        let user = match UserRepository::find_by_username(conn, sample.username)? {
+            Some(user) => user,
+            None => {
+                summary.users += 1;
+                UserRepository::create(
+                    conn,
+                    NewUser {
+                        username: sample.username.to_string(),
+                        first_name: sample.first_name.to_string(),
+                        last_name: sample.last_name.to_string(),
+                    },
+                    ids,
+                )?
+            }
+        };
+
+        let filters = PostFilters {
+            author_id: Some(user.id),
+            ..Default::default()
+        };
+        if PostRepository::count(conn, &filters)? > 0 {
+            continue;
+        }
+
+        for post in sample.posts {
+            PostRepository::create_with_tags(
+                conn,
+                NewPostWithTags {
+                    title: post.title.to_string(),
+                    body: post.body.to_string(),
+                    created_by: user.id,
+                    tags: post.tags.iter().map(|tag| tag.to_string()).collect(),
+                },
+                ids,
+                None,
+            )?;
+            summary.posts += 1;
+            summary.tags += post.tags.len();
+        }
+    }
+
+    Ok(summary)
+}
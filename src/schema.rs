@@ -1,5 +1,48 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    audit_log (id) {
+        id -> Uuid,
+        entity_type -> Varchar,
+        entity_id -> Uuid,
+        action -> Varchar,
+        actor_id -> Nullable<Uuid>,
+        at -> Timestamptz,
+        diff -> Nullable<Jsonb>,
+    }
+}
+
+diesel::table! {
+    comments (id) {
+        id -> Uuid,
+        fk_post_id -> Uuid,
+        author_id -> Uuid,
+        parent_id -> Nullable<Uuid>,
+        body -> Text,
+        created_at -> Timestamptz,
+        deleted_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    favorites (fk_user_id, fk_post_id) {
+        fk_user_id -> Uuid,
+        fk_post_id -> Uuid,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    post_attachments (id) {
+        id -> Uuid,
+        fk_post_id -> Uuid,
+        url -> Text,
+        content_type -> Varchar,
+        size_bytes -> Int8,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     posts (id) {
         id -> Uuid,
@@ -7,6 +50,10 @@ diesel::table! {
         body -> Text,
         created_by -> Uuid,
         created_at -> Timestamptz,
+        updated_by -> Nullable<Uuid>,
+        featured -> Bool,
+        view_count -> Int8,
+        updated_at -> Timestamptz,
     }
 }
 
@@ -17,6 +64,14 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    settings (key) {
+        key -> Varchar,
+        value -> Text,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     users (id) {
         id -> Uuid,
@@ -27,7 +82,32 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    webhooks (id) {
+        id -> Uuid,
+        url -> Text,
+        event -> Varchar,
+        secret -> Varchar,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::joinable!(comments -> posts (fk_post_id));
+diesel::joinable!(comments -> users (author_id));
+diesel::joinable!(favorites -> posts (fk_post_id));
+diesel::joinable!(favorites -> users (fk_user_id));
+diesel::joinable!(post_attachments -> posts (fk_post_id));
 diesel::joinable!(posts -> users (created_by));
 diesel::joinable!(posts_tags -> posts (fk_post_id));
 
-diesel::allow_tables_to_appear_in_same_query!(posts, posts_tags, users,);
+diesel::allow_tables_to_appear_in_same_query!(
+    audit_log,
+    comments,
+    favorites,
+    post_attachments,
+    posts,
+    posts_tags,
+    settings,
+    users,
+    webhooks,
+);
@@ -0,0 +1,125 @@
+use diesel::pg::PgConnection;
+use diesel::Connection;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::models::{IdVersion, NewPostWithTags, NewUser};
+use crate::repository::{PostRepository, UserRepository};
+use crate::sample_data::SeedSummary;
+
+const FIRST_NAMES: &[&str] = &[
+    "Alan",
+    "Barbara",
+    "Edsger",
+    "Frances",
+    "John",
+    "Katherine",
+    "Ken",
+    "Margaret",
+    "Niklaus",
+    "Radia",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Allen", "Dijkstra", "Hamilton", "Johnson", "Kay", "Liskov", "McCarthy", "Perlman", "Thompson",
+    "Wirth",
+];
+
+const WORDS: &[&str] = &[
+    "async", "borrow", "cache", "compiler", "database", "deploy", "index", "latency", "macro",
+    "memory", "query", "schema", "server", "stream", "thread", "trait",
+];
+
+const TAGS: &[&str] = &[
+    "backend",
+    "databases",
+    "devops",
+    "performance",
+    "postgres",
+    "programming",
+    "rust",
+    "testing",
+    "web",
+];
+
+const MAX_TAGS_PER_GENERATED_POST: usize = 4;
+
+fn sentence(rng: &mut impl Rng, words: usize) -> String {
+    let words: Vec<&str> = (0..words)
+        .map(|_| *WORDS.choose(rng).expect("WORDS is not empty"))
+        .collect();
+    let sentence = words.join(" ");
+    let mut chars = sentence.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => sentence,
+    }
+}
+
+/// Creates `users` random users and spreads `posts` random posts with tags
+/// across them, all in one transaction. Usernames get a random suffix so
+/// repeated runs do not collide.
+pub fn generate(
+    conn: &mut PgConnection,
+    users: usize,
+    posts: usize,
+    ids: IdVersion,
+) -> Result<SeedSummary, diesel::result::Error> {
+    let mut rng = rand::thread_rng();
+
+    conn.transaction(|conn| {
+        let mut summary = SeedSummary::default();
+        let mut authors = Vec::with_capacity(users);
+
+        for _ in 0..users {
+            let first_name = FIRST_NAMES
+                .choose(&mut rng)
+                .expect("FIRST_NAMES is not empty");
+            let last_name = LAST_NAMES
+                .choose(&mut rng)
+                .expect("LAST_NAMES is not empty");
+            let suffix: u32 = rng.gen_range(0..1_000_000);
+
+            let user = UserRepository::create(
+                conn,
+                NewUser {
+                    username: format!("{}_{}_{suffix:06}", first_name, last_name).to_lowercase(),
+                    first_name: first_name.to_string(),
+                    last_name: last_name.to_string(),
+                },
+                ids,
+            )?;
+            authors.push(user.id);
+            summary.users += 1;
+        }
+
+        for _ in 0..posts {
+            let Some(author) = authors.choose(&mut rng) else {
+                break;
+            };
+            let tag_count = rng.gen_range(0..=MAX_TAGS_PER_GENERATED_POST);
+            let tags: Vec<String> = TAGS
+                .choose_multiple(&mut rng, tag_count)
+                .map(|tag| tag.to_string())
+                .collect();
+
+            let title_words = rng.gen_range(3..=6);
+            let body_words = rng.gen_range(20..=60);
+            summary.tags += tags.len();
+            PostRepository::create_with_tags(
+                conn,
+                NewPostWithTags {
+                    title: sentence(&mut rng, title_words),
+                    body: format!("{}.", sentence(&mut rng, body_words)),
+                    created_by: *author,
+                    tags,
+                },
+                ids,
+                None,
+            )?;
+            summary.posts += 1;
+        }
+
+        Ok(summary)
+    })
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::repository::SettingsRepository;
+
+/// Default page size for post lists.
+pub const POSTS_PER_PAGE: &str = "posts_per_page";
+pub const DEFAULT_POSTS_PER_PAGE: i64 = 10;
+pub const MAX_POSTS_PER_PAGE: i64 = 100;
+
+type Validator = fn(&str) -> Result<(), String>;
+
+/// Keys that may be stored, with a check for their values.
+const KNOWN_SETTINGS: &[(&str, Validator)] = &[(POSTS_PER_PAGE, validate_posts_per_page)];
+
+pub fn validate_posts_per_page(value: &str) -> Result<(), String> {
+    match value.parse::<i64>() {
+        Ok(n) if (1..=MAX_POSTS_PER_PAGE).contains(&n) => Ok(()),
+        _ => Err(format!(
+            "must be an integer between 1 and {MAX_POSTS_PER_PAGE}"
+        )),
+    }
+}
+
+/// Checks that `key` is a known setting and `value` is acceptable for it.
+/// Errors are keyed by the offending field, `key` or `value`.
+pub fn validate(key: &str, value: &str) -> Result<(), (&'static str, String)> {
+    let Some((_, check)) = KNOWN_SETTINGS.iter().find(|(known, _)| *known == key) else {
+        let known: Vec<&str> = KNOWN_SETTINGS.iter().map(|(known, _)| *known).collect();
+        return Err(("key", format!("must be one of: {}", known.join(", "))));
+    };
+    check(value).map_err(|message| ("value", message))
+}
+
+/// Managed cache of the `settings` table. Loaded at liftoff and updated by
+/// every write through the API, so reads never touch the database. Settings
+/// missing from the table fall back to the startup `Config`.
+pub struct Settings {
+    values: RwLock<HashMap<String, String>>,
+    default_page_size: i64,
+}
+
+impl Settings {
+    pub fn new(config: &Config) -> Self {
+        Settings {
+            values: RwLock::default(),
+            default_page_size: config.default_page_size,
+        }
+    }
+
+    /// Replaces the cache with the table's current contents.
+    pub fn load(&self, pool: &DbPool) -> Result<(), Box<dyn std::error::Error>> {
+        let mut conn = pool.get()?;
+        let rows = SettingsRepository::all(&mut conn)?;
+        let mut values = self.values.write().unwrap_or_else(|e| e.into_inner());
+        *values = rows.into_iter().map(|s| (s.key, s.value)).collect();
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.values
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(key)
+            .cloned()
+    }
+
+    pub fn insert(&self, key: &str, value: &str) {
+        self.values
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key.to_string(), value.to_string());
+    }
+
+    /// `posts_per_page` from the table, else `DEFAULT_PAGE_SIZE`, else 10.
+    pub fn posts_per_page(&self) -> i64 {
+        self.get(POSTS_PER_PAGE)
+            .filter(|value| validate_posts_per_page(value).is_ok())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(self.default_page_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_known_keys_with_valid_values_are_accepted() {
+        assert!(validate(POSTS_PER_PAGE, "25").is_ok());
+        assert_eq!(
+            validate(POSTS_PER_PAGE, "0"),
+            Err(("value", "must be an integer between 1 and 100".to_string()))
+        );
+        assert_eq!(validate(POSTS_PER_PAGE, "ten").unwrap_err().0, "value");
+        assert_eq!(
+            validate("theme", "dark"),
+            Err(("key", "must be one of: posts_per_page".to_string()))
+        );
+    }
+
+    #[test]
+    fn cached_value_wins_over_default() {
+        let settings = Settings::new(&Config::default());
+        assert_eq!(settings.posts_per_page(), DEFAULT_POSTS_PER_PAGE);
+        settings.insert(POSTS_PER_PAGE, "25");
+        assert_eq!(settings.posts_per_page(), 25);
+        assert_eq!(settings.get(POSTS_PER_PAGE).as_deref(), Some("25"));
+    }
+}
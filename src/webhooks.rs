@@ -0,0 +1,94 @@
+use hmac::{Hmac, Mac};
+use rocket::tokio;
+use sha2::Sha256;
+use std::time::Duration;
+
+use crate::db::DbPool;
+use crate::models::Webhook;
+use crate::repository::WebhookRepository;
+
+/// Header carrying `sha256=<hex HMAC of the body>` keyed by the webhook secret.
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Managed state that delivers events to registered webhooks in the
+/// background. Delivery is fire-and-forget: failures are logged and never
+/// reach the request that triggered them.
+pub struct Webhooks {
+    client: reqwest::Client,
+}
+
+impl Webhooks {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .build()
+            .expect("Failed to build webhook HTTP client");
+        Webhooks { client }
+    }
+
+    /// Sends `payload` to every webhook subscribed to `event`, once each.
+    pub fn dispatch(&self, pool: &DbPool, event: &'static str, payload: serde_json::Value) {
+        let client = self.client.clone();
+        let pool = pool.clone();
+
+        tokio::spawn(async move {
+            let hooks = tokio::task::spawn_blocking(move || {
+                let mut conn = pool.get()?;
+                WebhookRepository::for_event(&mut conn, event)
+                    .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)
+            })
+            .await;
+            let hooks = match hooks {
+                Ok(Ok(hooks)) => hooks,
+                Ok(Err(e)) => return eprintln!("Failed to load webhooks for {event}: {e}"),
+                Err(e) => return eprintln!("Failed to load webhooks for {event}: {e}"),
+            };
+
+            let body = payload.to_string();
+            for hook in hooks {
+                deliver(&client, &hook, event, &body).await;
+            }
+        });
+    }
+}
+
+async fn deliver(client: &reqwest::Client, hook: &Webhook, event: &str, body: &str) {
+    let result = client
+        .post(&hook.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, event)
+        .header(SIGNATURE_HEADER, signature(&hook.secret, body.as_bytes()))
+        .body(body.to_string())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+
+    if let Err(e) = result {
+        eprintln!("Webhook {} delivery to {} failed: {e}", hook.id, hook.url);
+    }
+}
+
+/// `sha256=` followed by the hex HMAC-SHA256 of `body` keyed by `secret`.
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_is_hex_hmac_sha256() {
+        // RFC 4231 test case 2.
+        assert_eq!(
+            signature("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}